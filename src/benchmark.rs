@@ -0,0 +1,138 @@
+// Timing of the constraint solvers on one long tether, headless, to see what the red–black solver
+// gains over the serial Gauss–Seidel sweep on this machine. Run with:
+//      cargo run --release -- --benchmark 50000
+//
+// The number is the elements of the tether, 50000 if left out. The tether is laid out straight and
+// fully deployed, spinning and charged, and every solver runs the same timesteps from there with the
+// same fixed number of iterations. Red–black runs with one thread, then two, four and so on up to
+// every core, and all of them have to end with exactly the same positions. The residual, the largest
+// stretch of a segment after the last timestep, tells whether the speed cost some accuracy. The
+// threads split the segments of that one tether: a sail has no other tethers to spread over them.
+
+use std::time::{ Duration, Instant };
+
+use bevy::prelude::*;
+use bevy::math::DVec3;
+use uom::si::f64 as quantities;
+use uom::si::*;
+
+use crate::{ physics, resources, scenario, simulation, spacecraft };
+use resources::ConstraintSolver;
+
+const WARMUP_STEPS: usize = 5;
+const TIMED_STEPS:  usize = 20;
+const ITERATIONS:   i32   = 60;
+
+const BENCHMARK_SCENARIO: &str = r#"
+    [spacecraft]
+    rpm             = "5 rpm"
+    wire_potential  = "20 kV"
+    wire_resolution = "100 /m"
+
+    [simulation]
+    iteration_mode  = "fixed"
+"#;
+
+pub fn run_benchmark (elements: usize) -> Result<(), String> {
+
+    let cores = simulation::red_black::available_threads();
+
+    let mut cases = vec![(ConstraintSolver::GaussSeidel, 1)];
+    let mut threads = 1;
+    while threads < cores {
+        cases.push((ConstraintSolver::RedBlack, threads));
+        threads *= 2;
+    }
+    cases.push((ConstraintSolver::RedBlack, cores));
+
+    println!("Benchmark: {} elements, {} timesteps of {} iterations, {} cores", elements, TIMED_STEPS, ITERATIONS, cores);
+    println!("{:<24}{:>8}{:>12}{:>10}{:>12}  Same as 1 thread", "Solver", "Threads", "ms/step", "Speed-up", "Residual");
+
+    let mut serial_time                     = None;
+    let mut reference: Option<Vec<DVec3>>   = None;
+
+    for (solver, threads) in cases {
+
+        let (time, positions, residual) = time_solver(elements, solver, threads)?;
+
+        let milliseconds    = time.as_secs_f64() * 1000.0 / TIMED_STEPS as f64;
+        let serial          = *serial_time.get_or_insert(milliseconds);
+
+        let same = match solver {
+            ConstraintSolver::RedBlack => match &reference {
+                Some(reference) => if *reference == positions { "yes" } else { "NO" },
+                None            => { reference = Some(positions); "-" },
+            },
+            _ => "",
+        };
+
+        println!("{:<24}{:>8}{:>12.2}{:>10.2}{:>12.2e}  {}", solver.name(), threads, milliseconds, serial / milliseconds, residual, same);
+    }
+
+    return Ok(());
+}
+
+/// Runs the timesteps with one solver, and returns how long the timed ones took, where the
+/// elements ended up and the residual.
+
+fn time_solver (elements: usize, solver: ConstraintSolver, threads: usize) -> Result<(Duration, Vec<DVec3>, f64), String> {
+
+    let mut scenario = scenario::parse_scenario(BENCHMARK_SCENARIO).map_err(|error| error.message)?;
+
+    scenario.spacecraft.wire_length     = quantities::Length::new::<length::meter>(elements as f64 / scenario.spacecraft.wire_resolution.value);
+    scenario.simulation.constraint_solver = solver;
+    scenario.simulation.iterations      = ITERATIONS;
+    scenario.simulation.threads         = threads;
+
+    let mut app = simulation::headless_app(scenario);
+
+    // Spawns the E-sail
+    app.update();
+
+    lay_out_straight(&mut app.world);
+
+    for _ in 0..WARMUP_STEPS {
+        app.update();
+    }
+
+    let start = Instant::now();
+
+    for _ in 0..TIMED_STEPS {
+        app.update();
+    }
+
+    let time = start.elapsed();
+
+    let esail = app.world.query::<&spacecraft::esail::ESail>().single(&app.world);
+
+    let positions = esail.elements.iter()
+        .map(|entity| app.world.get::<physics::verlet_object::VerletObject>(*entity).expect("No sail element found").current_coordinates.to_dvec3())
+        .collect();
+
+    let residual = app.world.resource::<resources::SimulationParameters>().constraint_residual;
+
+    return Ok((time, positions, residual));
+}
+
+/// Deploys the whole tether at once, along x from the reel and at rest.
+
+fn lay_out_straight (world: &mut World) {
+
+    let segment_length = world.resource::<spacecraft::SpacecraftParameters>().segment_length().get::<length::meter>();
+
+    let mut esail = world.query::<&mut spacecraft::esail::ESail>().single_mut(world);
+    esail.deploy_esail(usize::MAX);
+
+    let origin          = esail.origin.to_dvec3();
+    let elements        = esail.elements.clone();
+    let first_deployed  = elements.len() - esail.deployed_elements.len();
+
+    for (index, entity) in elements.iter().enumerate().skip(first_deployed) {
+
+        let position = physics::vector3::PositionVector::from_dvec3(origin + DVec3::X * segment_length * (index + 1 - first_deployed) as f64);
+
+        let mut verlet_object = world.get_mut::<physics::verlet_object::VerletObject>(*entity).expect("No sail element found");
+        verlet_object.current_coordinates   = position;
+        verlet_object.previous_coordinates  = position;
+    }
+}
@@ -1,4 +1,6 @@
 use bevy::prelude::*;
+use bevy::math::DQuat;
+use crate::physics;
 use uom::si::f64 as quantities;  
 use uom::si::electric_potential::volt;
 use uom::si::*;
@@ -23,8 +25,17 @@ impl Default for ElectricallyCharged {
  
 #[derive(Component)]
 pub struct Position (
-    pub Vec<quantities::Length>,
+    pub physics::vector3::PositionVector,
 );
 
+/// Pose of a rigid body, which tethers can be attached to. Nothing integrates it yet, and the
+/// tethers attached to it don't move it.
+
+#[derive(Component)]
+pub struct RigidBody {
+    pub position:       physics::vector3::PositionVector,
+    pub orientation:    DQuat,
+}
+
 // pub struct rotation
 
@@ -1,9 +1,14 @@
 use bevy::prelude::*;
 use bevy::window::PrimaryWindow;
 
-use uom::si::length::meter;
+const SLACK_COLOR:  Color = Color::rgb(1.0, 0.5, 0.0);
+const LINK_COLOR:   Color = Color::rgb(0.2, 0.9, 0.3);
+const THRUST_COLOR: Color = Color::rgb(1.0, 0.2, 0.2);
+const THRUST_SCALE: f64   = 50.0;   // Meters of arrow per newton
 
-use crate::{ physics, spacecraft, resources };
+
+use crate::{ components, physics, spacecraft, resources };
+use physics::constraint_graph::Constraint;
 
 pub mod camera;
 mod lights;
@@ -28,7 +33,10 @@ impl Plugin for GraphicsPlugin {
                 Update, (
                     gizmo_visibility,
                     update_transform_verlets,
-                    update_rotation_axes
+                    update_rotation_axes,
+                    draw_slack_segments,
+                    draw_graph_constraints,
+                    draw_thrust,
                 )
             )
         ;
@@ -65,16 +73,14 @@ fn gizmo_visibility (
 
 
 /// Updates the transform of the verlet objects after the simulation, so that the graphics get updated.
-fn update_transform_verlets (
+pub fn update_transform_verlets (
     mut verlet_query: Query<(&physics::verlet_object::VerletObject, &mut Transform)>,
     simulation_parameters:  Res<resources::SimulationParameters>,
 ){
     
     for (verlet_object, mut transform) in verlet_query.iter_mut() {
-        // Should I use get<meter> in these cases?
-        transform.translation.x = verlet_object.current_coordinates.0[0].get::<meter>() as f32 * simulation_parameters.pixels_per_meter as f32;
-        transform.translation.y = verlet_object.current_coordinates.0[1].get::<meter>() as f32 * simulation_parameters.pixels_per_meter as f32;
-        transform.translation.z = verlet_object.current_coordinates.0[2].get::<meter>() as f32 * simulation_parameters.pixels_per_meter as f32;
+        let coordinates_in_meters = verlet_object.current_coordinates.to_dvec3();
+        transform.translation = coordinates_in_meters.as_vec3() * simulation_parameters.pixels_per_meter as f32;
 
         //println!("Transform X: {}", transform.translation.x);
     }
@@ -82,6 +88,92 @@ fn update_transform_verlets (
 
 
 
+/// Draws a line over every slack segment of the tether, so that buckling shows up.
+
+fn draw_slack_segments (
+    mut gizmos:             Gizmos,
+    esail_query:            Query<&spacecraft::esail::ESail>,
+    verlet_query:           Query<&physics::verlet_object::VerletObject>,
+    simulation_parameters:  Res<resources::SimulationParameters>,
+) {
+
+    let Ok(esail) = esail_query.get_single() else { return };
+
+    let scale = simulation_parameters.pixels_per_meter as f32;
+
+    for (preceding, element) in esail.elements.iter().zip(esail.elements.iter().skip(1)) {
+
+        let (Ok(preceding), Ok(element)) = (verlet_query.get(*preceding), verlet_query.get(*element)) else { continue };
+
+        if element.is_slack {
+            gizmos.line(
+                preceding.current_coordinates.to_dvec3().as_vec3() * scale,
+                element.current_coordinates.to_dvec3().as_vec3() * scale,
+                SLACK_COLOR,
+            );
+        }
+    }
+}
+
+
+
+/// Draws the links of the constraint graph, which the loop wires of a Heytether aren't: distance
+/// constraints as a line between their elements, anchors and attachments as a line from the element
+/// to its point.
+
+fn draw_graph_constraints (
+    mut gizmos:             Gizmos,
+    esail_query:            Query<&spacecraft::esail::ESail>,
+    verlet_query:           Query<&physics::verlet_object::VerletObject>,
+    body_query:             Query<&components::RigidBody>,
+    simulation_parameters:  Res<resources::SimulationParameters>,
+) {
+
+    let Ok(esail) = esail_query.get_single() else { return };
+
+    let scale       = simulation_parameters.pixels_per_meter as f32;
+    let position    = |element: usize| verlet_query.get(esail.elements[element]).ok().map(|verlet_object| verlet_object.current_coordinates.to_dvec3());
+
+    for constraint in esail.graph.links() {
+
+        let ends = match *constraint {
+            Constraint::Distance { first, second, .. }      => position(first).zip(position(second)),
+            Constraint::Anchor { element, position: point } => position(element).map(|element| (element, point)),
+            Constraint::Attachment { element, body, offset } => position(element).zip(
+                body_query.get(body).ok().map(|body| body.position.to_dvec3() + body.orientation * offset)),
+        };
+
+        if let Some((start, end)) = ends {
+            gizmos.line(start.as_vec3() * scale, end.as_vec3() * scale, LINK_COLOR);
+        }
+    }
+}
+
+
+
+/// Thrust of the remote units, as an arrow from the unit.
+
+fn draw_thrust (
+    mut gizmos:             Gizmos,
+    remote_unit_query:      Query<(&spacecraft::remote_unit::RemoteUnit, &physics::verlet_object::VerletObject)>,
+    simulation_parameters:  Res<resources::SimulationParameters>,
+) {
+
+    let scale = simulation_parameters.pixels_per_meter as f32;
+
+    for (remote_unit, verlet_object) in remote_unit_query.iter() {
+
+        let start   = verlet_object.current_coordinates.to_dvec3();
+        let end     = start + remote_unit.thrust.to_dvec3() * THRUST_SCALE;
+
+        if end != start {
+            gizmos.line(start.as_vec3() * scale, end.as_vec3() * scale, THRUST_COLOR);
+        }
+    }
+}
+
+
+
 fn update_rotation_axes (
     mut axes_query:         Query<&mut Transform, (With<spacecraft::axes::Axes>, Without<spacecraft::body::SatelliteBody>)>,   
     satellite_query:    Query<&Transform, (With<spacecraft::body::SatelliteBody>, Without<spacecraft::axes::Axes>)>,
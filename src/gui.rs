@@ -1,10 +1,16 @@
 use bevy::prelude::*;
 use bevy_egui::{egui, EguiContexts};
 
-use crate::{ resources, solar_wind, spacecraft };
+use crate::{ graphics, physics, recorder, resources, scenario, simulation, snapshot, solar_wind, spacecraft };
 
 use uom::si::*;
 
+mod modes;
+mod plots;
+mod remote_unit;
+mod spin;
+mod tip_spectrum;
+
 const MAX_VOLTAGE:  f64 = 30.0e3;   // Volts
 const MAX_RPM:      f64 = 5.0;      // rpm
 
@@ -13,9 +19,19 @@ pub struct GUIPlugin;
 impl Plugin for GUIPlugin {
     fn build(&self, app: &mut App) {
         app
+            .insert_resource(plots::PlotHistory::default())
+            .insert_resource(modes::ModeViewer::default())
+            .insert_resource(tip_spectrum::SpectrumViewer::default())
             .add_systems(
-                Update, 
-                Self::sidebar
+                Update, (
+                    Self::sidebar,
+                    plots::sample_plot_history,
+                    modes::modes_window,
+                    modes::animate_mode.after(graphics::update_transform_verlets),
+                    tip_spectrum::spectrum_window,
+                    remote_unit::remote_unit_window,
+                    spin::spin_window,
+                )
             )
         ;
     }
@@ -29,6 +45,17 @@ impl GUIPlugin {
         mut sim_params:             ResMut<resources::SimulationParameters>,
         solar_wind:                 ResMut<solar_wind::SolarWind>, 
         mut spacecraft_parameters:  ResMut<spacecraft::SpacecraftParameters>,
+        mut drag_params:            ResMut<physics::coulomb_drag::CoulombDragParameters>,
+        mut scenario_state:         ResMut<scenario::ScenarioState>,
+        mut scenario_requests:      EventWriter<scenario::ScenarioRequest>,
+        mut snapshot_state:         ResMut<snapshot::SnapshotState>,
+        mut snapshot_requests:      EventWriter<snapshot::SnapshotRequest>,
+        timeline:                   Res<simulation::timeline::Timeline>,
+        monitor:                    Res<simulation::convergence::ConvergenceMonitor>,
+        mut recorder:               ResMut<recorder::Recorder>,
+        esail_query:                Query<&spacecraft::esail::ESail>,
+        verlet_query:               Query<&physics::verlet_object::VerletObject>,
+        mut plot_history:           ResMut<plots::PlotHistory>,
         ) {
 
         egui::SidePanel::left("side_panel")
@@ -38,7 +65,11 @@ impl GUIPlugin {
             ui.label("SPACECRAFT");
 
             ui.horizontal(|ui| { ui.label("Spacecraft rotation"); });
-            ui.add(egui::Slider::new(&mut spacecraft_parameters.rpm.value, 0.0..=MAX_RPM).text("rpm"));
+            // uom keeps frequencies in Hz, the slider works in rpm
+            let mut rpm = spacecraft_parameters.rpm.get::<frequency::cycle_per_minute>();
+            if ui.add(egui::Slider::new(&mut rpm, 0.0..=MAX_RPM).text("rpm")).changed() {
+                spacecraft_parameters.rpm = uom::si::f64::Frequency::new::<frequency::cycle_per_minute>(rpm);
+            }
 
             ui.horizontal(|ui| { ui.label("Wire potential V_0"); });
             ui.add(egui::Slider::new(&mut spacecraft_parameters.wire_potential.value, 0.0..=MAX_VOLTAGE).text("V"));
@@ -63,10 +94,143 @@ impl GUIPlugin {
             ui.separator();
 
 
+            ui.label("COULOMB DRAG");
+
+            egui::ComboBox::from_label("Force model")
+                .selected_text(drag_params.model.name())
+                .show_ui(ui, |ui| {
+                    for model in physics::coulomb_drag::ForceModel::ALL {
+                        ui.selectable_value(&mut drag_params.model, model, model.name());
+                    }
+                });
+
+            // Side-by-side comparison of every model for the current wire and solar wind
+            egui::Grid::new("force_model_comparison").striped(true).show(ui, |ui| {
+
+                ui.label("Model");
+                ui.label("nN/m");
+                ui.label("Wire total (µN)");
+                ui.end_row();
+
+                // Toivanen & Janhunen only differs from Janhunen 2009 on a wire slanted to the wind
+                for model in physics::coulomb_drag::ForceModel::ALL.into_iter().filter(|model| *model != physics::coulomb_drag::ForceModel::ToivanenJanhunen) {
+
+                    let force_per_meter = model.force_per_meter(&solar_wind, &spacecraft_parameters, &drag_params, spacecraft_parameters.effective_wire_radius());
+                    let total_force     = force_per_meter * spacecraft_parameters.wire_length;
+
+                    let name = if model == drag_params.model { format!("▶ {}", model.name()) } else { model.name().to_string() };
+
+                    ui.label(name);
+                    ui.label(format!("{:.2}", force_per_meter.value * 1.0e9));
+                    ui.label(format!("{:.4}", total_force.get::<force::micronewton>()));
+                    ui.end_row();
+                }
+            });
+
+            ui.label("Toivanen & Janhunen: as Janhunen 2009, with only the wind across the wire");
+
+            ui.horizontal(|ui| {
+                ui.label("K coefficient");
+                ui.add(egui::DragValue::new(&mut drag_params.K).speed(0.01).clamp_range(0.0..=10.0));
+            });
+
+            ui.horizontal(|ui| {
+                if ui.add_enabled(drag_params.k_point.is_none(), egui::Button::new("Compute K (Monte Carlo)")).clicked() {
+                    drag_params.compute_k(&solar_wind, &spacecraft_parameters);
+                }
+                if ui.add_enabled(drag_params.k_point.is_some(), egui::Button::new("Use the empirical K")).clicked() {
+                    drag_params.forget_k();
+                }
+            });
+
+            match drag_params.monte_carlo {
+                Some(result) if drag_params.computing_k()   => ui.label(format!("K = {:.3} ± {:.3} ({} protons absorbed), used instead until the run for the current wire and wind is done", result.K, result.standard_error, result.absorbed)),
+                Some(result)                                => ui.label(format!("K = {:.3} ± {:.3} ({} protons absorbed), used instead", result.K, result.standard_error, result.absorbed)),
+                None if drag_params.computing_k()           => ui.label("Tracing protons..."),
+                None if drag_params.k_point.is_some()       => ui.label("Not computed for this wire and wind (needs a positive wire potential)"),
+                None                                        => ui.label("Not computed"),
+            };
+
+            if ui.button("Solve sheath").clicked() {
+                drag_params.solve_sheath(&solar_wind, &spacecraft_parameters);
+            }
+
+            let analytic_r_0 = physics::coulomb_drag::potential_cutoff_distance(solar_wind.n_0, solar_wind.T_e);
+            ui.label(format!("Analytic r_0: {:.2} m", analytic_r_0.get::<length::meter>()));
+
+            let sheath_summary = drag_params.sheath.as_ref().map(|sheath| format!(
+                "Effective r_0: {:.2} m\nSheath radius: {:.2} m\nStopping distance: {:.2} m\n{} iterations{}",
+                sheath.effective_sheath_radius.get::<length::meter>(),
+                sheath.sheath_radius.get::<length::meter>(),
+                sheath.stopping_distance.get::<length::meter>(),
+                sheath.iterations,
+                if sheath.converged { "" } else { " (not converged)" },
+            ));
+
+            match sheath_summary {
+                Some(summary) => {
+                    ui.label(summary);
+                    ui.checkbox(&mut drag_params.use_sheath, "Use solved sheath radius");
+                },
+                None => { ui.label("No sheath solved (needs a wire potential above the proton energy)"); },
+            }
+
+            ui.separator();
+
+
             ui.label("SIMULATION");
 
             ui.horizontal(|ui| { ui.label("Constraint iterations per timestep"); });
-            ui.add(egui::Slider::new(&mut sim_params.iterations, 1..=1000).text("Iterations"));
+            egui::ComboBox::from_label("Constraint solver")
+                .selected_text(sim_params.constraint_solver.name())
+                .show_ui(ui, |ui| {
+                    for solver in resources::ConstraintSolver::ALL {
+                        ui.selectable_value(&mut sim_params.constraint_solver, solver, solver.name());
+                    }
+                });
+            egui::ComboBox::from_label("Iteration mode")
+                .selected_text(sim_params.iteration_mode.name())
+                .show_ui(ui, |ui| {
+                    for mode in resources::IterationMode::ALL {
+                        ui.selectable_value(&mut sim_params.iteration_mode, mode, mode.name());
+                    }
+                });
+
+            let iterations_label = match sim_params.iteration_mode {
+                resources::IterationMode::Fixed     => "Iterations",
+                resources::IterationMode::Residual  => "Most iterations",
+            };
+            ui.add(egui::Slider::new(&mut sim_params.iterations, 1..=1000).text(iterations_label));
+
+            if sim_params.constraint_solver == resources::ConstraintSolver::RedBlack {
+                let cores = simulation::red_black::available_threads();
+                ui.add(egui::Slider::new(&mut sim_params.threads, 0..=cores).text("Threads (0: all)"));
+            }
+
+            if sim_params.iteration_mode == resources::IterationMode::Residual {
+                ui.add(egui::Slider::new(&mut sim_params.iteration_tolerance, 1.0e-7..=1.0e-2).logarithmic(true).text("Tolerance"));
+            }
+
+            ui.label(format!("Last step: {} iterations, residual {:.2e}", sim_params.iterations_used, sim_params.constraint_residual));
+
+            if let Ok(esail) = esail_query.get_single() {
+
+                let graph = &esail.graph;
+
+                if graph.loops > 0 {
+                    ui.label(format!("{}: {} loop wires, {} wires broken, severed at {} segments",
+                        spacecraft_parameters.tether_type.name(), graph.loops,
+                        graph.broken.iter().filter(|broken| **broken).count(), graph.severed_segments()));
+                }
+
+                if !graph.links().is_empty() {
+                    ui.label(format!("Constraint graph: {} links (drawn in green)", graph.links().len()));
+                }
+
+                if !graph.is_chain() && sim_params.constraint_solver != resources::ConstraintSolver::GaussSeidel {
+                    ui.label("Not a plain chain, solved by Gauss–Seidel");
+                }
+            }
 
 
             ui.horizontal(|ui| { 
@@ -81,12 +245,132 @@ impl GUIPlugin {
                 ui.checkbox(&mut sim_params.axes_visibility, "Show axes");
             });
 
+            ui.label(format!("Simulated time: {:.2} s", sim_params.simulated_time.get::<time::second>()));
+
+            ui.label(format!("Slack segments: {} (drawn in orange)", sim_params.slack_segments));
+
+            match monitor.steady_at {
+                Some(time) => ui.label(format!("Steady state since t = {:.2} s", time.get::<time::second>())),
+                None => ui.label(format!(
+                    "Settling: {:.2e} m/s, force change {:.2e}/s, residual {:.2e}",
+                    monitor.max_speed.get::<velocity::meter_per_second>(), monitor.force_change, monitor.residual)),
+            };
+
+            if !timeline.actions.is_empty() {
+                ui.label("Timeline");
+                for scheduled in timeline.actions.iter() {
+                    let marker = if scheduled.finished { "✔" } else { "•" };
+                    ui.label(format!("{} {}", marker, scheduled.describe()));
+                }
+            }
+
+            ui.separator();
+
+            ui.label("SCENARIO");
+
+            ui.horizontal(|ui| { ui.label("File"); });
+            ui.text_edit_singleline(&mut scenario_state.path);
+
+            ui.horizontal(|ui| {
+                if ui.button("Load").clicked() {
+                    scenario_requests.send(scenario::ScenarioRequest::Load);
+                }
+                if ui.button("Save").clicked() {
+                    scenario_requests.send(scenario::ScenarioRequest::Save);
+                }
+            });
+
+            if !scenario_state.status.is_empty() {
+                ui.label(&scenario_state.status);
+            }
+
+            ui.separator();
+
+            ui.label("SNAPSHOT");
+
+            ui.horizontal(|ui| { ui.label("File"); });
+            ui.text_edit_singleline(&mut snapshot_state.path);
+
+            ui.horizontal(|ui| {
+                if ui.button("Load").clicked() {
+                    snapshot_requests.send(snapshot::SnapshotRequest::Load);
+                }
+                if ui.button("Save").clicked() {
+                    snapshot_requests.send(snapshot::SnapshotRequest::Save);
+                }
+            });
+
+            if !snapshot_state.status.is_empty() {
+                ui.label(&snapshot_state.status);
+            }
+
+            ui.separator();
+
+            ui.label("RECORDER");
+
+            ui.add_enabled_ui(!recorder.is_recording(), |ui| {
+
+                ui.horizontal(|ui| { ui.label("File"); });
+                ui.text_edit_singleline(&mut recorder.settings.path);
+
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_source("record_format")
+                        .selected_text(format!("{:?}", recorder.settings.format))
+                        .show_ui(ui, |ui| {
+                            for format in recorder::RecordFormat::ALL.iter().copied() {
+                                ui.selectable_value(&mut recorder.settings.format, format, format!("{:?}", format));
+                            }
+                        });
+                    egui::ComboBox::from_id_source("record_layout")
+                        .selected_text(format!("{:?}", recorder.settings.layout))
+                        .show_ui(ui, |ui| {
+                            for layout in recorder::RecordLayout::ALL {
+                                ui.selectable_value(&mut recorder.settings.layout, layout, format!("{:?}", layout));
+                            }
+                        });
+                });
+
+                ui.add(egui::Slider::new(&mut recorder.settings.decimation, 1..=1000).text("Timesteps per sample"));
+            });
+
+            if recorder.is_recording() {
+                if ui.button("Stop recording").clicked() {
+                    recorder.stop();
+                }
+            } else if ui.button("Start recording").clicked() {
+                let run_parameters = scenario::scenario_to_string(
+                    &spacecraft_parameters, &solar_wind, &sim_params, &drag_params, &timeline, &monitor.settings, Some(&recorder.settings));
+                recorder.start(&run_parameters);
+            }
+
+            if !recorder.status.is_empty() {
+                ui.label(&recorder.status);
+            }
+
             ui.separator();
 
             ui.label("RESULTS");
             ui.horizontal(|ui| { 
                 ui.label( format!("Total coulomb force: "));
             });
+
+            // Tension of every deployed segment, from the reel outwards
+            let tensions: Vec<f64> = match esail_query.get_single() {
+                Ok(esail) => esail.deployed_elements.iter()
+                    .map(|entity| verlet_query.get(*entity).expect("No sail element found").tension.get::<force::newton>())
+                    .collect(),
+                Err(_) => Vec::new(),
+            };
+
+            if let (Some(reel), Some(tip)) = (tensions.first(), tensions.last()) {
+
+                ui.label(format!("Tension at the reel: {:.3e} N", reel));
+                ui.label(format!("Tension at the tip: {:.3e} N", tip));
+            }
+
+            ui.separator();
+
+            ui.collapsing("PLOTS", |ui| plot_history.show(ui));
         });
     }
 }
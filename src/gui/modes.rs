@@ -0,0 +1,170 @@
+// Window with the natural frequencies of the tether (see physics::modal_analysis), and the mode
+// shapes animated on top of the 3D view.
+//
+// The animation only moves what's drawn, the simulation doesn't notice. It goes at one cycle per
+// second whatever the frequency of the mode, since it's there to see the shape.
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::{ components, physics, resources, spacecraft };
+use physics::modal_analysis::{ ModalAnalysis, ModeFamily };
+
+use uom::si::*;
+
+const MAX_MODES:        usize   = 20;
+const MAX_AMPLITUDE:    f64     = 0.2;      // Meters
+const ANIMATION_RATE:   f64     = 1.0;      // Cycles per second of real time
+
+#[derive(Resource)]
+pub struct ModeViewer {
+    pub modes:      usize,                          // Modes of each family
+    pub follow:     bool,                           // Analyse again every frame
+    pub amplitude:  f64,                            // Largest displacement of the animation, in meters
+    pub animated:   Option<(ModeFamily, usize)>,
+    analysis:       Option<ModalAnalysis>,
+    analysed:       bool,                           // Whether there has been an analysis at all
+}
+
+impl Default for ModeViewer {
+    fn default() -> Self {
+        ModeViewer {
+            modes:      5,
+            follow:     false,
+            amplitude:  0.05,
+            animated:   None,
+            analysis:   None,
+            analysed:   false,
+        }
+    }
+}
+
+
+
+pub fn modes_window (
+    mut egui_ctx:   EguiContexts,
+    mut viewer:     ResMut<ModeViewer>,
+    sim_params:     Res<resources::SimulationParameters>,
+    craft_params:   Res<spacecraft::SpacecraftParameters>,
+    esail_query:    Query<&spacecraft::esail::ESail>,
+    verlet_query:   Query<&physics::verlet_object::VerletObject>,
+    mass_query:     Query<&components::Mass>,
+    ) {
+
+    let viewer = &mut *viewer;
+
+    let mut analyse = viewer.follow;
+
+    egui::Window::new("MODES")
+        .default_open(false)
+        .show(egui_ctx.ctx_mut(), |ui| {
+
+            ui.horizontal(|ui| {
+                analyse |= ui.button("Analyse").clicked();
+                ui.checkbox(&mut viewer.follow, "Keep updating");
+            });
+
+            ui.add(egui::Slider::new(&mut viewer.modes, 1..=MAX_MODES).text("modes"));
+
+            let Some(analysis) = &viewer.analysis else {
+                if viewer.analysed {
+                    ui.label("Nothing deployed, or the tether is cut");
+                }
+                return;
+            };
+
+            let spin = analysis.spin_frequency.get::<frequency::hertz>();
+
+            ui.label(format!("Spin: {:.4} Hz", spin));
+            ui.label("Click a frequency to animate its mode");
+
+            egui::Grid::new("mode_grid").striped(true).show(ui, |ui| {
+
+                ui.label("Mode");
+                for family in ModeFamily::ALL {
+                    ui.label(format!("{} (Hz)", family.name()));
+                    ui.label("× spin");
+                }
+                ui.end_row();
+
+                let rows = analysis.in_plane.len().max(analysis.out_of_plane.len());
+
+                for index in 0..rows {
+
+                    ui.label(format!("{}", index + 1));
+
+                    for family in ModeFamily::ALL {
+
+                        let Some(mode) = analysis.modes(family).get(index) else {
+                            ui.label("");
+                            ui.label("");
+                            continue;
+                        };
+
+                        let frequency   = mode.frequency.get::<frequency::hertz>();
+                        let text        = if mode.unstable { "unstable".to_string() } else { format!("{:.4}", frequency) };
+                        let selected    = viewer.animated == Some((family, index));
+
+                        if ui.selectable_label(selected, text).clicked() {
+                            viewer.animated = if selected { None } else { Some((family, index)) };
+                        }
+
+                        ui.label(if spin > 0.0 && !mode.unstable { format!("{:.2}", frequency / spin) } else { "-".to_string() });
+                    }
+                    ui.end_row();
+                }
+            });
+
+            // The constraints don't care, but following a mode in time takes Ω dt well below 2
+            let highest = analysis.highest_frequency.get::<frequency::hertz>();
+            ui.label(format!("Highest mode: {:.3} Hz, Ω·dt = {:.3}", highest, 2.0 * std::f64::consts::PI * highest * sim_params.timestep));
+
+            ui.add(egui::Slider::new(&mut viewer.amplitude, 0.0..=MAX_AMPLITUDE).text("amplitude (m)"));
+        });
+
+    if analyse {
+
+        viewer.analysed = true;
+        viewer.analysis = esail_query.get_single().ok().and_then(|esail| {
+            physics::modal_analysis::analyse(esail, &verlet_query, &mass_query, &craft_params, viewer.modes)
+        });
+
+        // The mode may not exist anymore
+        if let Some((family, index)) = viewer.animated {
+            if viewer.analysis.as_ref().map_or(true, |analysis| index >= analysis.modes(family).len()) {
+                viewer.animated = None;
+            }
+        }
+    }
+}
+
+/// Moves the drawn elements along the shape of the animated mode. Runs after the transforms have
+/// been set from the simulation.
+
+pub fn animate_mode (
+    viewer:                 Res<ModeViewer>,
+    time:                   Res<Time>,
+    sim_params:             Res<resources::SimulationParameters>,
+    esail_query:            Query<&spacecraft::esail::ESail>,
+    mut transform_query:    Query<&mut Transform>,
+    ) {
+
+    let (Some((family, index)), Some(analysis)) = (viewer.animated, &viewer.analysis) else { return };
+
+    let Some(mode) = analysis.modes(family).get(index) else { return };
+
+    let Ok(esail) = esail_query.get_single() else { return };
+
+    // Deployed since the analysis, the shape doesn't fit anymore
+    if mode.shape.len() != esail.deployed_elements.len() {
+        return;
+    }
+
+    let phase = (2.0 * std::f64::consts::PI * ANIMATION_RATE * time.elapsed_seconds_f64()).sin();
+
+    for (entity, displacement) in esail.deployed_elements.iter().zip(&mode.shape) {
+        if let Ok(mut transform) = transform_query.get_mut(*entity) {
+            transform.translation += (*displacement * viewer.amplitude * phase).as_vec3() * sim_params.pixels_per_meter as f32;
+        }
+    }
+}
@@ -0,0 +1,218 @@
+// Live plots for the sidebar: rolling time series of the whole sail, and profiles along the tether.
+//
+// Everything is sampled once per frame from the state the simulation left, and kept in
+// PlotHistory, so pausing freezes the plots while the simulation goes on. The plots can be zoomed
+// with ctrl + scroll and dragged around, double click goes back to the automatic bounds.
+
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy::math::DVec3;
+use bevy_egui::egui;
+use egui_plot::{ Line, Plot, PlotPoints };
+
+use crate::{ components, physics, resources, spacecraft };
+
+use uom::si::*;
+
+const PLOT_HEIGHT:      f32 = 120.0;
+const MAX_HISTORY:      f64 = 600.0;    // Seconds of simulated time that can be kept
+
+#[derive(Resource)]
+pub struct PlotHistory {
+    pub paused:         bool,
+    pub history:        f64,                    // Seconds of simulated time shown in the time series
+    time_series:        Vec<TimeSeries>,
+    profiles:           Vec<Profile>,
+    last_time:          Option<f64>,            // Simulated time of the last sample, in seconds
+}
+
+impl Default for PlotHistory {
+    fn default() -> Self {
+        PlotHistory {
+            paused:         false,
+            history:        60.0,
+            time_series:    vec![
+                TimeSeries::new("Thrust", "µN"),
+                TimeSeries::new("Spin rate", "rpm"),
+                TimeSeries::new("Tip deflection", "°"),
+                TimeSeries::new("Max tension", "N"),
+                TimeSeries::new("Kinetic energy", "J"),
+            ],
+            profiles:       vec![
+                Profile::new("Coulomb drag", "µN"),
+                Profile::new("Deflection angle", "°"),
+                Profile::new("Tension", "N"),
+            ],
+            last_time:      None,
+        }
+    }
+}
+
+struct TimeSeries {
+    name:   &'static str,
+    unit:   &'static str,
+    points: VecDeque<[f64; 2]>,     // [simulated time (s), value]
+}
+
+impl TimeSeries {
+    fn new (name: &'static str, unit: &'static str) -> Self {
+        return TimeSeries { name: name, unit: unit, points: VecDeque::new() };
+    }
+}
+
+struct Profile {
+    name:   &'static str,
+    unit:   &'static str,
+    points: Vec<[f64; 2]>,          // [distance along the tether (m), value]
+}
+
+impl Profile {
+    fn new (name: &'static str, unit: &'static str) -> Self {
+        return Profile { name: name, unit: unit, points: Vec::new() };
+    }
+}
+
+
+
+impl PlotHistory {
+
+    pub fn clear (&mut self) {
+        for series in self.time_series.iter_mut() {
+            series.points.clear();
+        }
+        for profile in self.profiles.iter_mut() {
+            profile.points.clear();
+        }
+        self.last_time = None;
+    }
+
+    /// Draws the controls and every plot.
+
+    pub fn show (&mut self, ui: &mut egui::Ui) {
+
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut self.paused, "Pause plots");
+            if ui.button("Clear").clicked() {
+                self.clear();
+            }
+        });
+
+        ui.add(egui::Slider::new(&mut self.history, 1.0..=MAX_HISTORY).logarithmic(true).text("s shown"));
+
+        for series in self.time_series.iter() {
+            ui.label(format!("{} ({})", series.name, series.unit));
+            Plot::new(series.name)
+                .height(PLOT_HEIGHT)
+                .allow_zoom(true)
+                .allow_drag(true)
+                .show(ui, |plot_ui| plot_ui.line(Line::new(PlotPoints::new(series.points.iter().copied().collect()))));
+        }
+
+        for profile in self.profiles.iter() {
+            ui.label(format!("{} ({}) along the tether (m)", profile.name, profile.unit));
+            Plot::new(profile.name)
+                .height(PLOT_HEIGHT)
+                .allow_zoom(true)
+                .allow_drag(true)
+                .include_y(0.0)
+                .show(ui, |plot_ui| plot_ui.line(Line::new(PlotPoints::new(profile.points.clone()))));
+        }
+    }
+
+    fn push (&mut self, time: f64, values: [f64; 5]) {
+
+        for (series, value) in self.time_series.iter_mut().zip(values) {
+
+            series.points.push_back([time, value]);
+
+            while series.points.front().map_or(false, |point| point[0] < time - self.history) {
+                series.points.pop_front();
+            }
+        }
+    }
+}
+
+
+
+/// Takes a new sample of the plotted quantities, if the simulation moved since the last one.
+
+pub fn sample_plot_history (
+    mut history:    ResMut<PlotHistory>,
+    sim_params:     Res<resources::SimulationParameters>,
+    craft_params:   Res<spacecraft::SpacecraftParameters>,
+    esail_query:    Query<&spacecraft::esail::ESail>,
+    verlet_query:   Query<&mut physics::verlet_object::VerletObject>,
+    mass_query:     Query<&components::Mass>,
+    ) {
+
+    let time = sim_params.simulated_time.get::<time::second>();
+
+    // Time going backwards means that the simulation was restarted
+    if history.last_time.map_or(false, |last_time| time < last_time) {
+        history.clear();
+    }
+
+    if history.paused || history.last_time == Some(time) {
+        return;
+    }
+
+    let Ok(esail) = esail_query.get_single() else { return };
+
+    history.last_time = Some(time);
+
+    let segment_length  = craft_params.segment_length().get::<length::meter>();
+    let timestep        = sim_params.timestep_s.get::<time::second>();
+
+    // Deployed elements are the last ones in ESail::elements
+    let first_deployed = esail.elements.len() - esail.deployed_elements.len();
+
+    let mut forces          = Vec::with_capacity(esail.deployed_elements.len());
+    let mut tensions        = Vec::with_capacity(esail.deployed_elements.len());
+    let mut max_tension     = 0.0_f64;
+    let mut kinetic_energy  = 0.0;
+
+    for (index, entity) in esail.deployed_elements.iter().enumerate() {
+
+        let verlet_object   = verlet_query.get(*entity).expect("No sail element found");
+        let distance        = (index + 1) as f64 * segment_length;
+        let tension         = verlet_object.tension.get::<force::newton>();
+
+        forces.push([distance, verlet_object.coulomb_force.to_dvec3().length() * 1.0e6]);
+        tensions.push([distance, tension]);
+        max_tension = max_tension.max(tension);
+
+        // Velocity in the rotating frame, the one the simulation works in
+        if let Ok(mass) = mass_query.get(*entity) {
+            let velocity = (verlet_object.current_coordinates - verlet_object.previous_coordinates).to_dvec3() / timestep;
+            kinetic_energy += 0.5 * mass.0.get::<mass::kilogram>() * velocity.length_squared();
+        }
+    }
+
+    // The angle needs two segments, so it starts at the third deployed element
+    let deflections: Vec<[f64; 2]> = (first_deployed + 2..esail.elements.len()).map(|index| {
+        let distance = (index - first_deployed + 1) as f64 * segment_length;
+        [distance, esail.deflection_angle(index, &verlet_query).get::<angle::degree>()]
+    }).collect();
+
+    // Angle of the tip away from the radial direction, which is x
+    let tip_deflection = match esail.deployed_elements.last() {
+        Some(tip) => {
+            let tip_position = (verlet_query.get(*tip).expect("No sail element found").current_coordinates - esail.origin).to_dvec3();
+            if tip_position.length() > 0.0 { tip_position.angle_between(DVec3::X).to_degrees() } else { 0.0 }
+        },
+        None => 0.0,
+    };
+
+    history.push(time, [
+        esail.total_force.to_dvec3().length() * 1.0e6,
+        craft_params.rpm.get::<frequency::cycle_per_minute>(),
+        tip_deflection,
+        max_tension,
+        kinetic_energy,
+    ]);
+
+    history.profiles[0].points = forces;
+    history.profiles[1].points = deflections;
+    history.profiles[2].points = tensions;
+}
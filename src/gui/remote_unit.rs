@@ -0,0 +1,83 @@
+// Window with the remote unit at the tip of the tether (see spacecraft/remote_unit.rs): what it
+// weighs, its thrusters, and what they're told to do.
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::{ physics, spacecraft };
+use spacecraft::remote_unit::{ RemoteUnit, ThrustCommand, ThrusterType };
+
+use uom::si::f64 as quantities;
+use uom::si::*;
+
+const MIN_THRUST:   f64 = 1.0e-3;   // Millinewtons
+const MAX_THRUST:   f64 = 10.0;     // Millinewtons
+const MAX_GAIN:     f64 = 10.0;     // Per second
+
+pub fn remote_unit_window (
+    mut egui_ctx:       EguiContexts,
+    mut craft_params:   ResMut<spacecraft::SpacecraftParameters>,
+    esail_query:        Query<&spacecraft::esail::ESail>,
+    remote_unit_query:  Query<&RemoteUnit>,
+    verlet_query:       Query<&physics::verlet_object::VerletObject>,
+    ) {
+
+    let Ok(esail) = esail_query.get_single() else { return };
+    let Some(entity) = esail.elements.last() else { return };
+    let Ok(remote_unit) = remote_unit_query.get(*entity) else { return };
+
+    egui::Window::new("REMOTE UNIT")
+        .default_open(false)
+        .show(egui_ctx.ctx_mut(), |ui| {
+
+            let mass        = remote_unit.element_mass(&craft_params);
+            let propellant  = remote_unit.propellant;
+            let settings    = &craft_params.remote_unit;
+
+            ui.label(format!("Mass: {:.2} g, dry {:.2} g", mass.get::<mass::gram>(), settings.dry_mass.get::<mass::gram>()));
+            ui.label(format!("Propellant: {:.3} of {:.3} g", propellant.get::<mass::gram>(), settings.propellant.get::<mass::gram>()));
+            ui.label(format!("Auxiliary tether on the reel: {} m", settings.auxiliary_tether.get::<length::meter>()));
+
+            // Rocket equation, with what's left
+            let exhaust_velocity    = settings.thruster.specific_impulse() * 9.806_65;
+            let delta_v             = exhaust_velocity * (mass / (mass - propellant)).value.ln();
+            ui.label(format!("Δv left: {:.2} m/s", delta_v));
+
+            ui.separator();
+
+            let settings = &mut craft_params.remote_unit;
+
+            egui::ComboBox::from_label("Thruster")
+                .selected_text(settings.thruster.name())
+                .show_ui(ui, |ui| {
+                    for thruster in ThrusterType::ALL {
+                        ui.selectable_value(&mut settings.thruster, thruster, thruster.name());
+                    }
+                });
+
+            let mut max_thrust = settings.max_thrust.get::<force::millinewton>();
+            if ui.add(egui::Slider::new(&mut max_thrust, MIN_THRUST..=MAX_THRUST).logarithmic(true).text("mN at most")).changed() {
+                settings.max_thrust = quantities::Force::new::<force::millinewton>(max_thrust);
+            }
+
+            let mut gain = settings.damping_gain.get::<frequency::hertz>();
+            if ui.add(egui::Slider::new(&mut gain, 0.0..=MAX_GAIN).text("/s damping gain")).changed() {
+                settings.damping_gain = quantities::Frequency::new::<frequency::hertz>(gain);
+            }
+
+            ui.horizontal(|ui| {
+                for command in ThrustCommand::ALL {
+                    ui.radio_value(&mut settings.command, command, command.name());
+                }
+            });
+
+            ui.separator();
+
+            let thrust = remote_unit.thrust.norm().get::<force::millinewton>();
+            ui.label(format!("Thrust: {:.4} mN", thrust));
+
+            if let Ok(verlet_object) = verlet_query.get(*entity) {
+                ui.label(format!("Tension at the unit: {:.4} mN", verlet_object.tension.get::<force::millinewton>()));
+            }
+        });
+}
@@ -0,0 +1,53 @@
+// Window with the spin of the spacecraft (see simulation/spin.rs): how fast it spins, the angular
+// momentum and inertia behind it, and the torques changing it. The rpm slider of the side panel
+// still sets the spin rate, from where a free spacecraft carries on.
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+
+use crate::{ components, physics, simulation, spacecraft };
+use spacecraft::SpinMode;
+
+use uom::si::*;
+
+pub fn spin_window (
+    mut egui_ctx:       EguiContexts,
+    mut craft_params:   ResMut<spacecraft::SpacecraftParameters>,
+    spin_state:         Res<simulation::spin::SpinState>,
+    esail_query:        Query<(&spacecraft::esail::ESail, &spacecraft::reel::Reel)>,
+    verlet_query:       Query<&physics::verlet_object::VerletObject>,
+    mass_query:         Query<&components::Mass>,
+    ) {
+
+    let Ok((esail, reel)) = esail_query.get_single() else { return };
+
+    egui::Window::new("SPIN")
+        .default_open(false)
+        .show(egui_ctx.ctx_mut(), |ui| {
+
+            ui.horizontal(|ui| {
+                for mode in SpinMode::ALL {
+                    ui.radio_value(&mut craft_params.spin_mode, mode, mode.name());
+                }
+            });
+
+            ui.label(format!("Spin rate: {:.4} rpm, {:.5} rad/s",
+                craft_params.rpm.get::<frequency::cycle_per_minute>(), craft_params.angular_velocity().get::<frequency::hertz>()));
+
+            ui.label(format!("Angular momentum: {:.4e} kg m²/s", spin_state.angular_momentum.get::<angular_momentum::kilogram_square_meter_per_second>()));
+            ui.label(format!("Moment of inertia: {:.4e} kg m²", spin_state.moment_of_inertia.get::<moment_of_inertia::kilogram_square_meter>()));
+
+            ui.separator();
+
+            let properties  = esail.mass_properties(&craft_params, reel, &verlet_query, &mass_query);
+            let stowed      = reel.stowed / (craft_params.segment_mass() / craft_params.segment_length());
+
+            ui.label(format!("Mass: {:.4} kg", properties.mass.get::<mass::kilogram>()));
+            ui.label(format!("Wire on the reel: {:.2} m, {:.4} g", stowed.get::<length::meter>(), reel.stowed.get::<mass::gram>()));
+
+            ui.separator();
+
+            ui.label(format!("Torque of the Coulomb drag: {:.4e} N m", spin_state.drag_torque.get::<torque::newton_meter>()));
+            ui.label(format!("Torque of the thrusters: {:.4e} N m", spin_state.thrust_torque.get::<torque::newton_meter>()));
+        });
+}
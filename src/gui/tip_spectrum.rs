@@ -0,0 +1,134 @@
+// Window with the spectrum of the tip motion (see spectrum.rs): the periodogram of both components,
+// their dominant frequencies and damping ratios, and the export to CSV.
+
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContexts};
+use egui_plot::{ Legend, Line, Plot, PlotPoints, VLine };
+
+use crate::{ spacecraft, spectrum };
+use spectrum::{ TipMotion, TipSpectrum };
+
+use uom::si::f64 as quantities;
+use uom::si::*;
+
+const PLOT_HEIGHT:  f32 = 160.0;
+const MAX_HISTORY:  f64 = 600.0;    // Seconds
+
+#[derive(Resource)]
+pub struct SpectrumViewer {
+    pub follow:     bool,           // Analyse again every frame
+    pub path:       String,
+    pub status:     String,
+    spectrum:       Option<TipSpectrum>,
+}
+
+impl Default for SpectrumViewer {
+    fn default() -> Self {
+        SpectrumViewer {
+            follow:     false,
+            path:       String::from("esme_tip_spectrum.csv"),
+            status:     String::new(),
+            spectrum:   None,
+        }
+    }
+}
+
+
+
+pub fn spectrum_window (
+    mut egui_ctx:       EguiContexts,
+    mut viewer:         ResMut<SpectrumViewer>,
+    mut tip_history:    ResMut<spectrum::TipHistory>,
+    craft_params:       Res<spacecraft::SpacecraftParameters>,
+    ) {
+
+    let SpectrumViewer { follow, path, status, spectrum } = &mut *viewer;
+
+    let mut analyse = *follow;
+
+    egui::Window::new("TIP SPECTRUM")
+        .default_open(false)
+        .show(egui_ctx.ctx_mut(), |ui| {
+
+            let mut history = tip_history.duration.get::<time::second>();
+            if ui.add(egui::Slider::new(&mut history, 10.0..=MAX_HISTORY).logarithmic(true).text("s kept")).changed() {
+                tip_history.duration = quantities::Time::new::<time::second>(history);
+            }
+
+            ui.label(format!("{} samples over {:.1} s", tip_history.sample_count(), tip_history.span().get::<time::second>()));
+
+            ui.horizontal(|ui| {
+                analyse |= ui.button("Analyse").clicked();
+                ui.checkbox(follow, "Keep updating");
+                if ui.button("Clear").clicked() {
+                    tip_history.clear();
+                    *spectrum = None;
+                }
+            });
+
+            let Some(spectrum) = spectrum.as_ref() else { return };
+
+            let spin = craft_params.rpm.get::<frequency::hertz>();
+
+            ui.label(format!("Resolution {:.4} Hz, spin {:.4} Hz", spectrum.resolution().get::<frequency::hertz>(), spin));
+
+            // Logarithmic densities, spread over many decades. Zeros are left out.
+            ui.label("log₁₀ power spectral density (m²/Hz) against frequency (Hz)");
+            Plot::new("tip_spectrum")
+                .height(PLOT_HEIGHT)
+                .legend(Legend::default())
+                .allow_zoom(true)
+                .allow_drag(true)
+                .show(ui, |plot_ui| {
+                    for motion in TipMotion::ALL {
+                        let points: Vec<[f64; 2]> = spectrum.frequencies.iter().zip(spectrum.density(motion))
+                            .skip(1)
+                            .filter(|(_, density)| **density > 0.0)
+                            .map(|(frequency, density)| [*frequency, density.log10()])
+                            .collect();
+                        plot_ui.line(Line::new(PlotPoints::new(points)).name(motion.name()));
+                    }
+                    if spin > 0.0 {
+                        plot_ui.vline(VLine::new(spin).name("Spin"));
+                    }
+                });
+
+            egui::Grid::new("peak_grid").striped(true).show(ui, |ui| {
+
+                ui.label("Motion");
+                ui.label("Frequency (Hz)");
+                ui.label("× spin");
+                ui.label("Damping ratio");
+                ui.end_row();
+
+                for motion in TipMotion::ALL {
+                    for peak in spectrum.peaks(motion) {
+                        let frequency = peak.frequency.get::<frequency::hertz>();
+                        ui.label(motion.name());
+                        ui.label(format!("{:.4}", frequency));
+                        ui.label(if spin > 0.0 { format!("{:.2}", frequency / spin) } else { "-".to_string() });
+                        ui.label(if peak.resolved { format!("{:.4}", peak.damping_ratio) } else { format!("< {:.4}", peak.damping_ratio) });
+                        ui.end_row();
+                    }
+                }
+            });
+
+            ui.horizontal(|ui| { ui.label("File"); });
+            ui.text_edit_singleline(path);
+
+            if ui.button("Export").clicked() {
+                *status = match tip_history.export(spectrum, path) {
+                    Ok(paths)   => format!("Written {}", paths.join(", ")),
+                    Err(error)  => format!("Can't write {}: {}", path, error),
+                };
+            }
+
+            if !status.is_empty() {
+                ui.label(status.as_str());
+            }
+        });
+
+    if analyse {
+        *spectrum = tip_history.spectrum();
+    }
+}
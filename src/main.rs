@@ -4,14 +4,20 @@ use bevy::prelude::*;
 use bevy_egui::EguiPlugin;
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
 
+mod benchmark;
 mod components;
 mod graphics;
 mod gui;
 mod physics;
+mod recorder;
 mod resources;
+mod scenario;
 mod simulation;
+mod snapshot;
 mod solar_wind;
 mod spacecraft;
+mod spectrum;
+mod sweep;
 mod user_input;
 
 extern crate uom;
@@ -19,6 +25,60 @@ extern crate uom;
 const BACKGROUND_COLOR: Color = Color::rgb(0.0, 0.0, 0.0);
 
 fn main() {
+
+    // Parameter sweeps run headless and exit, e.g. `cargo run --release -- --sweep sweeps/potential_rpm.toml`
+    if std::env::args().nth(1).as_deref() == Some("--sweep") {
+        let Some(path) = std::env::args().nth(2) else {
+            eprintln!("--sweep needs a sweep file");
+            std::process::exit(1);
+        };
+        if let Err(error) = sweep::run_sweep(&path) {
+            eprintln!("{}: {}", path, error);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // Timing of the constraint solvers, headless, e.g. `cargo run --release -- --benchmark 50000`
+    if std::env::args().nth(1).as_deref() == Some("--benchmark") {
+        let elements = match std::env::args().nth(2).map(|elements| elements.parse::<usize>()) {
+            Some(Ok(elements))  => elements,
+            Some(Err(_))        => {
+                eprintln!("--benchmark takes a number of elements");
+                std::process::exit(1);
+            },
+            None                => 50000,
+        };
+        if let Err(error) = benchmark::run_benchmark(elements) {
+            eprintln!("Benchmark: {}", error);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // An optional scenario file as first argument, e.g. `cargo run -- scenarios/default.toml`
+    let scenario = match std::env::args().nth(1) {
+        Some(path) => match scenario::load_scenario(&path) {
+            Ok(scenario) => scenario,
+            Err(error) => {
+                eprintln!("{}: {}", path, error);
+                std::process::exit(1);
+            },
+        },
+        None => scenario::Scenario{..Default::default()},
+    };
+
+    // Recording from the very first timestep, if the scenario asks for it
+    let mut run_recorder = recorder::Recorder::default();
+    if let Some(settings) = scenario.recorder.clone() {
+        let run_parameters = scenario::scenario_to_string(
+            &scenario.spacecraft, &scenario.solar_wind, &scenario.simulation,
+            &scenario.coulomb_drag, &scenario.timeline, &scenario.convergence, Some(&settings));
+        run_recorder.settings = settings;
+        run_recorder.start(&run_parameters);
+        println!("{}", run_recorder.status);
+    }
+
     App::new()
         .insert_resource(Msaa::Sample4)   // "Multi-Sample Anti-Aliasing"
         .insert_resource(ClearColor(BACKGROUND_COLOR))
@@ -27,11 +87,20 @@ fn main() {
         .add_plugins(graphics::GraphicsPlugin)
         .add_plugins(gui::GUIPlugin)
         .add_plugins(physics::PhysicsPlugin)
+        .add_plugins(recorder::RecorderPlugin)
+        .add_plugins(scenario::ScenarioPlugin)
         .add_plugins(simulation::SimulationPlugin)
+        .add_plugins(snapshot::SnapshotPlugin)
         .add_plugins(spacecraft::SpacecraftPlugin)
+        .add_plugins(spectrum::SpectrumPlugin)
         .add_plugins(user_input::UserInputPlugin)
         .add_plugins(WorldInspectorPlugin::new())
-        .insert_resource(solar_wind::SolarWind{..Default::default()})
-        .insert_resource(resources::SimulationParameters{..Default::default()})
+        .insert_resource(scenario.spacecraft)
+        .insert_resource(scenario.solar_wind)
+        .insert_resource(scenario.simulation)
+        .insert_resource(scenario.coulomb_drag)
+        .insert_resource(scenario.timeline)
+        .insert_resource(simulation::convergence::ConvergenceMonitor::new(scenario.convergence))
+        .insert_resource(run_recorder)
         .run();
 }
@@ -8,9 +8,14 @@ use crate::{ components, resources, spacecraft };
 
 use uom::si::*;
 
-pub mod position_vector;
-pub mod force_vector;
-pub mod acceleration_vector;
+pub mod constraint_graph;
+pub mod coulomb_drag;
+pub mod equilibrium;
+pub mod modal_analysis;
+pub mod sheath_solver;
+pub mod test_particle;
+pub mod tridiagonal;
+pub mod vector3;
 pub mod verlet_object;
 
 // All operations in this plugin should be done in physical units. Get rid of pixels in verlets.
@@ -21,42 +26,39 @@ pub struct PhysicsPlugin;   // Plugins are structs, therefore they can hold data
 impl Plugin for PhysicsPlugin {
     fn build(&self, app: &mut App) {
         app
+            .insert_resource(coulomb_drag::CoulombDragParameters{..Default::default()})
             .add_systems(
-                Update,
-                update_center_of_mass
+                Update, (
+                    update_center_of_mass,
+                    coulomb_drag::update_monte_carlo_k,
+                )
             )
         ;
     }
 }
 
-/// Updates position and visibility of the center of mass
-/// Maybe this should calculate its position, and graphics.rs should update the transform
+/// Updates the position of the center of mass of the spacecraft: the body, the reel and the tether
+/// out of it. The elements still in the reel count as part of the reel, not where they're parked.
 
 fn update_center_of_mass(
-    simulation_parameters:     Res<resources::SimulationParameters>,
-    mass_query:     Query<(&Transform, &components::Mass), Without<spacecraft::center_mass::CenterOfMass>>,
-    mut com_query:  Query<&mut Transform, With<spacecraft::center_mass::CenterOfMass>>, 
+    simulation_parameters:  Res<resources::SimulationParameters>,
+    craft_params:           Res<spacecraft::SpacecraftParameters>,
+    esail_query:            Query<(&spacecraft::esail::ESail, &spacecraft::reel::Reel)>,
+    verlet_query:           Query<&verlet_object::VerletObject>,
+    mass_query:             Query<&components::Mass>,
+    mut com_query:          Query<&mut Transform, With<spacecraft::center_mass::CenterOfMass>>, 
     ){
 
-    let mut total_mass:     f32 = 0.0;  // In this particular case I don't think I should use physical units.
-                                        // Transform will be in pixels, and mass units are cancelled out.
-    let mut center_mass_x:  f32 = 0.0;
-    let mut center_mass_y:  f32 = 0.0;
+    let Ok((esail, reel)) = esail_query.get_single() else { return };
 
-    for (transform, object_mass) in mass_query.iter() {
-        total_mass    += object_mass.0.value as f32; 
-        center_mass_x += transform.translation.x * object_mass.0.value as f32;
-        center_mass_y += transform.translation.y * object_mass.0.value as f32;
-    }
+    let properties = esail.mass_properties(&craft_params, reel, &verlet_query, &mass_query);
 
     if simulation_parameters.debug {
-        println!("Total mass: {} | Center of mass: ({},{})", total_mass, center_mass_x, center_mass_y);
+        println!("Total mass: {} kg | Center of mass: {:?} m", properties.mass.get::<mass::kilogram>(), properties.center_of_mass.to_dvec3());
     }
 
-    //let (mut com_transform, mut com_visibility) = com_query.single_mut();
     let mut com_transform = com_query.single_mut();
 
-    com_transform.translation.x = center_mass_x;
-    com_transform.translation.y = center_mass_y;
-
+    // Transform is in pixels
+    com_transform.translation = properties.center_of_mass.to_dvec3().as_vec3() * simulation_parameters.pixels_per_meter as f32;
 }
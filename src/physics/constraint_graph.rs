@@ -0,0 +1,190 @@
+// Constraint graph of an E-sail: which elements are held together, or held in place, and how.
+//
+// Most of the graph is the tether itself, a chain of segments, element k joined to element k-1. On
+// top of it there can be any number of other constraints:
+//
+//      distance        a line between any two elements, which like the segments pulls but doesn't
+//                      push: branches, tip-to-tip auxiliary lines, the strands of a net, a second
+//                      line along the tether
+//      anchor          an element held at a fixed point
+//      attachment      an element held at a point of a rigid body, given in the frame of the body
+//
+// The loop wires of a Heytether are distance constraints too, one per loop wire between every two
+// bonds. They come right after the segments, and then the links of the scenario. Any of these wires
+// can be broken, by a micrometeoroid say: a broken constraint stays in the graph, so that the others
+// keep their numbers, and it's just skipped. A loop wire is longer than the base wire it spans, so
+// it hangs loose until the base wire between its bonds breaks, and then it takes the load. Only once
+// every wire between two bonds is broken does the tether come apart there.
+//
+// Elements are numbered like in ESail::elements, from the one that always stays in the reel to the
+// remote unit, so that scenario files and snapshots can name them. Rigid bodies are only a pose for now.
+// The spacecraft body is the E-sail entity, which doesn't move in the spinning frame, and the tether
+// doesn't move it either.
+//
+// The segments are always the first constraints, segment k being constraint k - 1. The tridiagonal
+// and red–black solvers only know how to solve a whole chain of segments, so a graph with anything
+// more, or a broken segment, goes to the Gauss–Seidel sweep of simulation/graph_solver.rs, whatever
+// solver is chosen.
+
+use bevy::prelude::*;
+use bevy::math::DVec3;
+use serde::{ Deserialize, Serialize };
+use uom::si::length::meter;
+
+use crate::spacecraft;
+
+/// One constraint, with elements as indices into ESail::elements and lengths in meters.
+
+#[derive(Clone, Copy, Debug)]
+pub enum Constraint {
+    Distance    { first: usize, second: usize, length: f64 },
+    Anchor      { element: usize, position: DVec3 },
+    Attachment  { element: usize, body: Entity, offset: DVec3 },
+}
+
+/// A constraint beyond the segments, as scenario files and snapshots give it, before there's an
+/// E-sail to put it in. Attachments are to the spacecraft body. In meters.
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Link {
+    Distance    { first: usize, second: usize, length: f64 },
+    Anchor      { element: usize, position: [f64; 3] },
+    Attachment  { element: usize, offset: [f64; 3] },
+}
+
+impl Link {
+
+    /// Elements the link holds, to check them against the elements there are.
+
+    pub fn elements (&self) -> Vec<usize> {
+        return match self {
+            Link::Distance { first, second, .. }    => vec![*first, *second],
+            Link::Anchor { element, .. }            => vec![*element],
+            Link::Attachment { element, .. }        => vec![*element],
+        };
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ConstraintGraph {
+    pub constraints:    Vec<Constraint>,
+    pub broken:         Vec<bool>,  // For every constraint
+    pub segments:       usize,      // The first constraints, one per element but the first
+    pub loops:          usize,      // Loop wires of a Heytether, right after the segments
+    pub bond_segments:  usize,      // Segments between two bonds of the loop wires
+}
+
+impl ConstraintGraph {
+
+    /// The segments of a tether of the given number of elements, its loop wires if it's a
+    /// Heytether, and then the links of craft_params, with attachments going to the given body.
+
+    pub fn new (craft_params: &spacecraft::SpacecraftParameters, elements: usize, body: Entity) -> Self {
+
+        let segment_length  = craft_params.segment_length().get::<meter>();
+        let bond_segments   = craft_params.bond_segments();
+
+        let mut constraints: Vec<Constraint> = (1..elements)
+            .map(|index| Constraint::Distance { first: index - 1, second: index, length: segment_length })
+            .collect();
+
+        let segments = constraints.len();
+
+        // Bonds every bond_segments elements from the reel, and at the remote unit
+        for _ in 0..craft_params.loop_filaments() {
+            for first in (0..segments).step_by(bond_segments) {
+                let second = (first + bond_segments).min(segments);
+                constraints.push(Constraint::Distance {
+                    first:  first,
+                    second: second,
+                    length: (second - first) as f64 * segment_length * (1.0 + craft_params.loop_slack),
+                });
+            }
+        }
+
+        let loops = constraints.len() - segments;
+
+        constraints.extend(craft_params.links.iter().map(|link| match *link {
+            Link::Distance { first, second, length }    => Constraint::Distance { first: first, second: second, length: length },
+            Link::Anchor { element, position }          => Constraint::Anchor { element: element, position: DVec3::from_array(position) },
+            Link::Attachment { element, offset }        => Constraint::Attachment { element: element, body: body, offset: DVec3::from_array(offset) },
+        }));
+
+        return ConstraintGraph {
+            broken:         vec![false; constraints.len()],
+            constraints:    constraints,
+            segments:       segments,
+            loops:          loops,
+            bond_segments:  bond_segments,
+        };
+    }
+
+    /// Nothing but the segments of the tether, one after the other, none of them broken.
+
+    pub fn is_chain (&self) -> bool {
+        return self.constraints.len() == self.segments && !self.broken.contains(&true);
+    }
+
+    /// The links of the scenario, after the segments and the loop wires.
+
+    pub fn links (&self) -> &[Constraint] {
+        return &self.constraints[self.segments + self.loops..];
+    }
+
+    /// Breaks one wire of the tether where it spans the given segment: filament 0 is the base wire,
+    /// and the loop wires are numbered from 1. Returns the number of the constraint that broke, or
+    /// None if there's no such wire.
+
+    pub fn break_wire (&mut self, segment: usize, filament: usize) -> Option<usize> {
+
+        if segment == 0 || segment > self.segments {
+            return None;
+        }
+
+        let number = match filament {
+            0 => segment - 1,
+            _ => self.loop_wire(segment, filament - 1)?,
+        };
+
+        self.broken[number] = true;
+
+        return Some(number);
+    }
+
+    /// Segments where every wire is broken, so that the tether has come apart there.
+
+    pub fn severed_segments (&self) -> usize {
+        return (1..=self.segments).filter(|segment| self.wires(*segment) == 0).count();
+    }
+
+    /// Wires of a segment that aren't broken: the base wire, and the loop wires spanning it. All of
+    /// them are charged and drag, taut or not.
+
+    pub fn wires (&self, segment: usize) -> usize {
+
+        let base_wire   = usize::from(!self.broken[segment - 1]);
+        let loop_wires  = (0..self.loop_filaments())
+            .filter_map(|filament| self.loop_wire(segment, filament))
+            .filter(|number| !self.broken[*number])
+            .count();
+
+        return base_wire + loop_wires;
+    }
+
+    /// Loop wires per span between two bonds.
+
+    fn loop_filaments (&self) -> usize {
+        return self.loops / self.segments.div_ceil(self.bond_segments).max(1);
+    }
+
+    /// Constraint of the given loop wire (from 0) that spans a segment, if there's one.
+
+    fn loop_wire (&self, segment: usize, filament: usize) -> Option<usize> {
+
+        let spans   = self.segments.div_ceil(self.bond_segments);
+        let number  = self.segments + filament * spans + (segment - 1) / self.bond_segments;
+
+        return if number < self.segments + self.loops { Some(number) } else { None };
+    }
+}
@@ -0,0 +1,428 @@
+use bevy::prelude::*;
+use bevy::math::DVec3;
+use bevy::tasks::{ block_on, AsyncComputeTaskPool, Task };
+use uom::si::f64 as quantities;
+use uom::si::*;
+use serde::{ Deserialize, Serialize };
+
+use crate::{ resources, solar_wind, spacecraft };
+use super::{ sheath_solver, test_particle };
+use super::vector3::Vector3;
+
+/// Coulomb drag force models from the literature.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ForceModel {
+    Janhunen2007,       // janhunen2007 eq. 8, K * m_p * n_0 * v² * r_s
+    Sanmartin2008,      // Ion-free electron sheath, every proton crossing it is stopped
+    Janhunen2009,       // 0.18 * max(0, V_0 - V_1) * sqrt(ε_0 * P_dyn), fitted to PIC simulations
+    ToivanenJanhunen,   // Same thrust law, but only the wind perpendicular to the wire counts
+}
+
+/// Parameters of the Coulomb drag model that aren't a property of the wire or the solar wind.
+
+#[derive(Resource)]
+#[allow(non_snake_case)]
+pub struct CoulombDragParameters {
+    pub model:          ForceModel,
+    pub K:              f64,    // Empirical coefficient of janhunen2007 eq. 8, unless there's a Monte Carlo one
+    pub monte_carlo:    Option<test_particle::KCoefficient>,    // K computed for the current operating point, if any
+    pub sheath:         Option<sheath_solver::SheathSolution>,  // Last self-consistent sheath, if any
+    pub use_sheath:     bool,   // Use the sheath radius of the solver instead of the analytic r_0
+    pub k_task:         Option<Task<Option<test_particle::KCoefficient>>>,  // Monte Carlo run going on in the background
+    pub k_point:        Option<test_particle::OperatingPoint>,  // What the run going on, or else monte_carlo, is for
+}
+
+impl Default for CoulombDragParameters {
+    fn default() -> Self {
+        CoulombDragParameters {
+            model:          ForceModel::Janhunen2007,
+            K:              3.09,   // From janhunen2007's Monte Carlo sims
+            monte_carlo:    None,
+            sheath:         None,
+            use_sheath:     false,
+            k_task:         None,
+            k_point:        None,
+        }
+    }
+}
+
+
+
+impl CoulombDragParameters {
+
+    /// Starts computing K by tracing test particles for the current wire and solar wind. The run takes
+    /// a while, so it goes to the async compute pool and update_monte_carlo_k picks the result up, and
+    /// keeps K up to date from then on. The last K is kept until the new one is ready.
+
+    pub fn compute_k (
+        &mut self,
+        solar_wind: &solar_wind::SolarWind,
+        spacecraft: &spacecraft::SpacecraftParameters,
+        ) {
+
+        let point = test_particle::OperatingPoint::of(solar_wind, spacecraft);
+
+        self.k_point        = Some(point);
+        self.k_task         = Some(AsyncComputeTaskPool::get().spawn(async move {
+            test_particle::monte_carlo_k(
+                point.wire_potential, point.wire_radius, point.n_0, point.T_e, point.velocity,
+                &test_particle::TestParticleSettings{..Default::default()},
+            )
+        }));
+    }
+
+
+
+    /// Goes back to the empirical K, dropping the Monte Carlo one and any run going on.
+
+    pub fn forget_k (&mut self) {
+        self.monte_carlo    = None;
+        self.k_task         = None;
+        self.k_point        = None;
+    }
+
+
+
+    /// Whether a Monte Carlo run is still going.
+
+    pub fn computing_k (&self) -> bool {
+        return self.k_task.is_some();
+    }
+
+
+
+    /// K used by the drag: the Monte Carlo one if there's one, the empirical one otherwise.
+
+    pub fn k (&self) -> f64 {
+        return self.monte_carlo.map_or(self.K, |result| result.K);
+    }
+
+
+
+    /// Solves the sheath around the wire for the current wire and solar wind.
+
+    pub fn solve_sheath (
+        &mut self,
+        solar_wind: &solar_wind::SolarWind,
+        spacecraft: &spacecraft::SpacecraftParameters,
+        ) {
+
+        self.sheath = sheath_solver::solve_sheath(
+            spacecraft.wire_potential, spacecraft.effective_wire_radius(),
+            solar_wind.n_0, solar_wind.T_e, solar_wind.velocity,
+            &sheath_solver::SheathSolverSettings{..Default::default()},
+        );
+    }
+
+
+
+    /// Distance at which the potential of the wire vanishes. The effective sheath radius of the
+    /// solver if there's one and it's enabled, the analytic r_0 otherwise.
+
+    pub fn cutoff_distance (&self, solar_wind: &solar_wind::SolarWind) -> quantities::Length {
+
+        match (self.use_sheath, &self.sheath) {
+            (true, Some(sheath))    => sheath.effective_sheath_radius,
+            _                       => potential_cutoff_distance(solar_wind.n_0, solar_wind.T_e),
+        }
+    }
+}
+
+
+
+/// Picks up the result of a Monte Carlo run once it's done, and starts another one whenever the wire
+/// or the solar wind have changed since, so that K follows them. A ramp changes them every timestep,
+/// so the drag uses the last K computed until the run for where the ramp has got to is done.
+
+pub fn update_monte_carlo_k (
+    solar_wind:         Res<solar_wind::SolarWind>,
+    craft_params:       Res<spacecraft::SpacecraftParameters>,
+    mut drag_params:    ResMut<CoulombDragParameters>,
+    ) {
+
+    let Some(point) = drag_params.k_point else { return };
+
+    if let Some(task) = drag_params.k_task.as_ref() {
+
+        if !task.is_finished() {
+            return;
+        }
+
+        drag_params.monte_carlo = drag_params.k_task.take().and_then(block_on);
+    }
+
+    if point != test_particle::OperatingPoint::of(&solar_wind, &craft_params) {
+        drag_params.compute_k(&solar_wind, &craft_params);
+    }
+}
+
+
+
+/// Distance r_0 at which the potential of the wire vanishes (twice the electron Debye length).
+
+#[allow(non_snake_case)]
+pub fn potential_cutoff_distance (
+    n_0:    quantities::VolumetricNumberDensity,
+    T_e:    quantities::Energy,
+    ) -> quantities::Length {
+
+    let r0_numerator    = resources::EPSILON_0 * T_e;
+    let r0_denominator  = n_0 * resources::Q_E * resources::Q_E;
+
+    return 2.0 * (r0_numerator / r0_denominator).sqrt();
+}
+
+
+
+/// Stopping distance r_s of the protons: closest distance to the wire that a head-on proton reaches,
+/// for a potential that vanishes at r_0.
+
+pub fn proton_stopping_distance (
+    wire_potential: quantities::ElectricPotential,
+    wire_radius:    quantities::Length,
+    r_0:            quantities::Length,
+    velocity:       quantities::Velocity,
+    ) -> quantities::Length {
+
+    let exp_numerator   = resources::M_PROTON * velocity * velocity * (r_0 / wire_radius).ln();
+    let exp_denominator = resources::Q_E * wire_potential;
+    let exp             = (exp_numerator / exp_denominator).exp();
+    let rs_denominator  = (exp.value - 1.0).sqrt();
+
+    return r_0 / rs_denominator;
+}
+
+
+
+// From janhunen2007, equation 8. Corroborate all the results. And recheck the equations too.
+#[allow(non_snake_case)]
+pub fn coulomb_force_per_meter(
+    solar_wind:         &solar_wind::SolarWind,
+    spacecraft:         &spacecraft::SpacecraftParameters,
+    drag_parameters:    &CoulombDragParameters,
+    wire_radius:        quantities::Length,
+    ) -> quantities::RadiantExposure {    // Radiant exposure is [mass][time]⁻²
+
+    let r_s = proton_stopping_distance(
+        spacecraft.wire_potential, wire_radius,
+        drag_parameters.cutoff_distance(solar_wind), solar_wind.velocity
+    );
+
+    let force_per_unit_length = r_s * drag_parameters.k() * resources::M_PROTON * solar_wind.n_0 * solar_wind.velocity * solar_wind.velocity;
+
+    return force_per_unit_length;
+}
+
+
+
+impl ForceModel {
+
+    pub const ALL: [ForceModel; 4] = [
+        ForceModel::Janhunen2007,
+        ForceModel::Sanmartin2008,
+        ForceModel::Janhunen2009,
+        ForceModel::ToivanenJanhunen,
+    ];
+
+    pub fn name (&self) -> &'static str {
+        match self {
+            ForceModel::Janhunen2007        => "Janhunen 2007",
+            ForceModel::Sanmartin2008       => "Sanmartín et al. 2008",
+            ForceModel::Janhunen2009        => "Janhunen 2009",
+            ForceModel::ToivanenJanhunen    => "Toivanen & Janhunen",
+        }
+    }
+
+
+
+    /// Force per unit length on a wire perpendicular to the solar wind. All the wind is across such a
+    /// wire, so Toivanen & Janhunen is the same as Janhunen 2009 here, and only force_per_meter_vector
+    /// tells them apart. The wire_radius is the effective one, of the filaments there are.
+
+    pub fn force_per_meter (
+        &self,
+        solar_wind:         &solar_wind::SolarWind,
+        spacecraft:         &spacecraft::SpacecraftParameters,
+        drag_parameters:    &CoulombDragParameters,
+        wire_radius:        quantities::Length,
+        ) -> quantities::RadiantExposure {
+
+        return match self {
+            ForceModel::Janhunen2007        => coulomb_force_per_meter(solar_wind, spacecraft, drag_parameters, wire_radius),
+            ForceModel::Sanmartin2008       => sanmartin_force_per_meter(solar_wind, spacecraft, wire_radius),
+            ForceModel::Janhunen2009        => pic_fitted_force_per_meter(spacecraft.wire_potential, solar_wind.n_0, solar_wind.velocity),
+            ForceModel::ToivanenJanhunen    => pic_fitted_force_per_meter(spacecraft.wire_potential, solar_wind.n_0, solar_wind.velocity),
+        };
+    }
+
+
+
+    /// Force per unit length on a piece of wire pointing along wire_direction.
+    /// A zero wire_direction (a wire with no known direction yet) counts as perpendicular to the wind.
+
+    pub fn force_per_meter_vector (
+        &self,
+        solar_wind:         &solar_wind::SolarWind,
+        spacecraft:         &spacecraft::SpacecraftParameters,
+        drag_parameters:    &CoulombDragParameters,
+        wire_direction:     DVec3,
+        wire_radius:        quantities::Length,
+        ) -> Vector3<quantities::RadiantExposure> {
+
+        match self {
+
+            ForceModel::ToivanenJanhunen => {
+
+                // Only the component of the wind perpendicular to the wire pushes it
+                let tangent         = wire_direction.normalize_or_zero();
+                let wind            = solar_wind.direction.normalize() * solar_wind.velocity.get::<velocity::meter_per_second>();
+                let perpendicular   = wind - tangent * wind.dot(tangent);
+                let speed           = quantities::Velocity::new::<velocity::meter_per_second>(perpendicular.length());
+
+                if speed.value == 0.0 {
+                    return Vector3::zero();
+                }
+
+                let magnitude = pic_fitted_force_per_meter(spacecraft.wire_potential, solar_wind.n_0, speed);
+
+                return Vector3::from_direction(magnitude, perpendicular);
+            },
+
+            _ => {
+                let magnitude = self.force_per_meter(solar_wind, spacecraft, drag_parameters, wire_radius);
+                return Vector3::from_direction(magnitude, solar_wind.direction);
+            },
+        }
+    }
+}
+
+
+
+/// Sanmartín et al. 2008: the wire is surrounded by an ion-free sheath whose electron space charge
+/// screens it completely, and every proton that crosses the 2R cross-section of the sheath gives up
+/// its momentum, so dF/dz = 2R m_p n_0 v². See sanmartin_sheath_radius for R. No force without a
+/// sheath.
+
+fn sanmartin_force_per_meter (
+    solar_wind:     &solar_wind::SolarWind,
+    spacecraft:     &spacecraft::SpacecraftParameters,
+    wire_radius:    quantities::Length,
+    ) -> quantities::RadiantExposure {
+
+    return match sanmartin_sheath_radius(spacecraft.wire_potential, wire_radius, solar_wind.n_0) {
+        Some(sheath_radius) => 2.0 * sheath_radius * resources::M_PROTON * solar_wind.n_0 * solar_wind.velocity * solar_wind.velocity,
+        None                => quantities::RadiantExposure::new::<radiant_exposure::joule_per_square_meter>(0.0),
+    };
+}
+
+
+
+/// Radius R of the ion-free sheath of Sanmartín et al. 2008. Poisson's equation with the electrons at
+/// n_0 inside the sheath, and the potential and the field vanishing at R, gives
+///
+///      V_0 = e n_0 (R² (2 ln(R/r_w) - 1) + r_w²) / 4ε_0
+///
+/// which grows monotonically from zero at r_w. None if there's no potential or no plasma, or if no
+/// finite radius gets to V_0.
+
+fn sanmartin_sheath_radius (
+    wire_potential: quantities::ElectricPotential,
+    wire_radius:    quantities::Length,
+    n_0:            quantities::VolumetricNumberDensity,
+    ) -> Option<quantities::Length> {
+
+    let v_0     = wire_potential.get::<electric_potential::volt>();
+    let r_w     = wire_radius.get::<length::meter>();
+    let scale   = (resources::Q_E * n_0 / (4.0 * resources::EPSILON_0)).value;  // V / m²
+
+    if !(v_0 > 0.0 && r_w > 0.0 && scale > 0.0) {
+        return None;
+    }
+
+    let sheath_potential = |radius: f64| scale * (radius * radius * (2.0 * (radius / r_w).ln() - 1.0) + r_w * r_w);
+
+    // Bisect in log space, once the potential at high is past V_0
+    let mut low     = r_w;
+    let mut high    = r_w;
+
+    while sheath_potential(high) < v_0 {
+
+        high *= 10.0;
+
+        if !sheath_potential(high).is_finite() {
+            return None;
+        }
+    }
+
+    for _ in 0..200 {
+        let middle = (low * high).sqrt();
+        if sheath_potential(middle) < v_0 { low = middle; } else { high = middle; }
+    }
+
+    return Some(quantities::Length::new::<length::meter>((low * high).sqrt()));
+}
+
+
+
+/// Thrust law fitted to PIC simulations (janhunen2009, later used by Toivanen & Janhunen):
+/// dF/dz = 0.18 max(0, V_0 - V_1) sqrt(ε_0 P_dyn), with V_1 = m_p v² / 2e and P_dyn = m_p n_0 v².
+
+fn pic_fitted_force_per_meter (
+    wire_potential: quantities::ElectricPotential,
+    n_0:            quantities::VolumetricNumberDensity,
+    velocity:       quantities::Velocity,
+    ) -> quantities::RadiantExposure {
+
+    let v_1                 = resources::M_PROTON * velocity * velocity / (2.0 * resources::Q_E);
+    let dynamic_pressure    = resources::M_PROTON * n_0 * velocity * velocity;
+    let effective_potential = (wire_potential - v_1).max(quantities::ElectricPotential::new::<electric_potential::volt>(0.0));
+
+    return 0.18 * effective_potential * (resources::EPSILON_0 * dynamic_pressure).sqrt();
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn volts (value: f64) -> quantities::ElectricPotential {
+        return quantities::ElectricPotential::new::<electric_potential::volt>(value);
+    }
+
+    #[test]
+    fn sanmartin_sheath_reaches_the_wire_potential () {
+
+        let solar_wind  = solar_wind::SolarWind{..Default::default()};
+        let spacecraft  = spacecraft::SpacecraftParameters{ wire_potential: volts(20.0e3), ..Default::default() };
+        let r_w         = spacecraft.effective_wire_radius();
+
+        let radius = sanmartin_sheath_radius(spacecraft.wire_potential, r_w, solar_wind.n_0).expect("No sheath").get::<length::meter>();
+
+        // Over a hundred meters at 20 kV in the default wind, far beyond a million wire radii
+        assert!(radius > 100.0 && radius < 200.0, "Sheath radius {} m", radius);
+
+        let scale       = (resources::Q_E * solar_wind.n_0 / (4.0 * resources::EPSILON_0)).value;
+        let r_w         = r_w.get::<length::meter>();
+        let potential   = scale * (radius * radius * (2.0 * (radius / r_w).ln() - 1.0) + r_w * r_w);
+        assert!((potential / 20.0e3 - 1.0).abs() < 1.0e-9, "{} V at the sheath radius", potential);
+
+        let force       = sanmartin_force_per_meter(&solar_wind, &spacecraft, spacecraft.effective_wire_radius());
+        let expected    = 2.0 * radius * (resources::M_PROTON * solar_wind.n_0 * solar_wind.velocity * solar_wind.velocity).value;
+        assert!((force.value / expected - 1.0).abs() < 1.0e-9);
+    }
+
+    #[test]
+    fn no_sanmartin_sheath_without_potential_or_plasma () {
+
+        let r_w = quantities::Length::new::<length::micrometer>(10.0);
+        let n_0 = quantities::VolumetricNumberDensity::new::<volumetric_number_density::per_cubic_centimeter>(7.0);
+
+        assert!(sanmartin_sheath_radius(volts(0.0), r_w, n_0).is_none());
+        assert!(sanmartin_sheath_radius(volts(20.0e3), r_w, n_0 * 0.0).is_none());
+        assert!(sanmartin_sheath_radius(volts(f64::INFINITY), r_w, n_0).is_none());
+    }
+}
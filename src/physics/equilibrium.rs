@@ -0,0 +1,470 @@
+// Steady state shape of a spinning tether under a uniform Coulomb drag, to check the Verlet model
+// against.
+//
+// Everything happens in the frame that spins with the sail, in the plane that contains the spin axis
+// and the tether. With ρ the distance to the spin axis, h the height along it and s the arc length
+// from the root, a tether of linear density λ spinning at ω, pushed by f newtons per meter along the
+// spin axis (the solar wind is perpendicular to the spin plane), is in equilibrium when
+//
+//      dρ/ds = T_ρ / |T|,      dh/ds = T_h / |T|,      dT_ρ/ds = -λ ω² ρ,      dT_h/ds = -f
+//
+// T being the tension (the pull of the outer part of the tether on the inner one). The root is fixed,
+// and at the tip the tension holds the tip mass: T_ρ = M ω² ρ and T_h = F, the drag on the tip.
+//
+// T_h doesn't depend on the shape, T_h(s) = F + f (L - s), so the only unknown of the boundary value
+// problem is the radial tension at the root. Shooting: integrate from the root with a guess (RK4),
+// and bisect on the guess until the tip condition holds.
+//
+// The tip error is negative for no tension at all and positive for the tension of a straight tether,
+// but it can cross zero more than once in between: a tether can also hang along the spin axis, with
+// hardly any radial tension, if the drag is strong enough. The sail spins stretched out, which is the
+// solution with the largest tension, so the guesses go down from the straight tether until the error
+// changes sign, and the bisection starts there.
+
+use uom::si::f64 as quantities;
+use uom::si::*;
+
+use crate::{ solar_wind, spacecraft };
+use super::coulomb_drag;
+
+pub struct EquilibriumProblem {
+    pub length:             quantities::Length,
+    pub linear_density:     quantities::LinearMassDensity,
+    pub tip_mass:           quantities::Mass,
+    pub tip_force:          quantities::Force,          // Drag on the tip, along the spin axis
+    pub force_per_meter:    quantities::RadiantExposure, // Drag along the spin axis, negative if against it
+    pub angular_velocity:   quantities::Frequency,
+    pub root_radius:        quantities::Length,         // Distance from the spin axis to the root
+    pub root_height:        quantities::Length,         // Along the spin axis
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ShapePoint {
+    pub arc_length: quantities::Length,
+    pub radius:     quantities::Length,     // Distance to the spin axis
+    pub height:     quantities::Length,     // Along the spin axis
+    pub tension:    quantities::Force,
+}
+
+#[derive(Debug, Clone)]
+pub struct EquilibriumShape {
+    pub points:     Vec<ShapePoint>,        // From the root to the tip, evenly spaced
+    pub tip_error:  quantities::Force,      // Radial tension at the tip minus what the tip mass needs
+}
+
+impl EquilibriumProblem {
+
+    /// The problem that the Verlet model solves: the deployed tether, the drag of the force model
+    /// on a wire perpendicular to the wind, and the root at the E-sail origin.
+    ///
+    /// Every element of the Verlet model stands for the half segments on either side of it, with
+    /// their mass and drag, and the remote unit at the tip only for the half segment before it. That
+    /// much is the continuous tether, so what's left at the tip is the remote unit as it starts,
+    /// and no drag.
+
+    pub fn from_parameters (
+        craft_params:       &spacecraft::SpacecraftParameters,
+        solar_wind:         &solar_wind::SolarWind,
+        drag_params:        &coulomb_drag::CoulombDragParameters,
+        deployed_length:    quantities::Length,
+        ) -> Self {
+
+        let rotation_axis   = craft_params.rotation_axis.normalize();
+        let root            = craft_params.esail_origin.to_dvec3();
+        let root_height     = root.dot(rotation_axis);
+        let root_radius     = (root - rotation_axis * root_height).length();
+
+        let force_per_meter = drag_params.model.force_per_meter(solar_wind, craft_params, drag_params, craft_params.effective_wire_radius())
+            * solar_wind.direction.normalize().dot(rotation_axis);
+
+        return EquilibriumProblem {
+            length:             deployed_length,
+            linear_density:     craft_params.segment_mass() / craft_params.segment_length(),
+            tip_mass:           craft_params.remote_unit.mass(),
+            tip_force:          quantities::Force::new::<force::newton>(0.0),
+            force_per_meter:    force_per_meter,
+            angular_velocity:   craft_params.angular_velocity(),
+            root_radius:        quantities::Length::new::<length::meter>(root_radius),
+            root_height:        quantities::Length::new::<length::meter>(root_height),
+        };
+    }
+
+    /// Shape and tension of the tether in equilibrium, at steps + 1 points from the root to the tip.
+
+    pub fn solve (&self, steps: usize) -> EquilibriumShape {
+
+        let omega_squared   = self.angular_velocity.value * self.angular_velocity.value;
+        let stiffness       = self.linear_density.value * omega_squared;    // Centrifugal force per meter, per meter from the axis
+        let tip_stiffness   = self.tip_mass.value * omega_squared;
+
+        let tip_error = |points: &[[f64; 4]]| {
+            let tip = points.last().expect("No points");
+            tip[2] - tip_stiffness * tip[0]
+        };
+
+        // Straight tether, radially out: as much radial tension as the root can ever need
+        let farthest        = self.root_radius.value + self.length.value;
+        let straight        = (stiffness * self.length.value + tip_stiffness) * farthest;
+        let mut high        = straight;
+        let mut low         = 0.0;
+
+        const GUESSES: usize = 100;
+
+        for guess in (0..GUESSES).rev() {
+
+            let tension = straight * guess as f64 / GUESSES as f64;
+
+            if tip_error(&self.integrate(tension, steps)) < 0.0 {
+                low = tension;
+                break;
+            }
+
+            high = tension;
+        }
+
+        // Bisection until the bracket stops shrinking
+        for _ in 0..200 {
+
+            let middle = 0.5 * (low + high);
+
+            if middle <= low || middle >= high {
+                break;
+            }
+
+            if tip_error(&self.integrate(middle, steps)) < 0.0 {
+                low = middle;
+            } else {
+                high = middle;
+            }
+        }
+
+        let points = self.integrate(0.5 * (low + high), steps);
+
+        return EquilibriumShape {
+            tip_error:  quantities::Force::new::<force::newton>(tip_error(&points)),
+            points:     points.iter().map(|[radius, height, radial_tension, arc_length]| {
+                let axial_tension = self.axial_tension(*arc_length);
+                ShapePoint {
+                    arc_length: quantities::Length::new::<length::meter>(*arc_length),
+                    radius:     quantities::Length::new::<length::meter>(*radius),
+                    height:     quantities::Length::new::<length::meter>(*height),
+                    tension:    quantities::Force::new::<force::newton>(radial_tension.hypot(axial_tension)),
+                }
+            }).collect(),
+        };
+    }
+
+    fn axial_tension (&self, arc_length: f64) -> f64 {
+        return self.tip_force.value + self.force_per_meter.value * (self.length.value - arc_length);
+    }
+
+    /// Integrates from the root with the given radial tension there. Returns [ρ, h, T_ρ, s] at every step.
+
+    fn integrate (&self, root_radial_tension: f64, steps: usize) -> Vec<[f64; 4]> {
+
+        let stiffness   = self.linear_density.value * self.angular_velocity.value * self.angular_velocity.value;
+        let step        = self.length.value / steps as f64;
+
+        // Derivative of [ρ, h, T_ρ] at arc length s
+        let derivative = |state: [f64; 3], arc_length: f64| -> [f64; 3] {
+            let axial_tension   = self.axial_tension(arc_length);
+            let tension         = state[2].hypot(axial_tension);
+            // At a free end the tension goes to zero, and the tether points along the load there
+            let (radial, axial) = if tension > 0.0 {
+                (state[2] / tension, axial_tension / tension)
+            } else {
+                let load = (stiffness * state[0]).hypot(self.force_per_meter.value);
+                if load > 0.0 { (stiffness * state[0] / load, self.force_per_meter.value / load) } else { (1.0, 0.0) }
+            };
+            [radial, axial, -stiffness * state[0]]
+        };
+
+        let shifted = |state: [f64; 3], slope: [f64; 3], amount: f64| -> [f64; 3] {
+            [state[0] + slope[0] * amount, state[1] + slope[1] * amount, state[2] + slope[2] * amount]
+        };
+
+        let mut state   = [self.root_radius.value, self.root_height.value, root_radial_tension];
+        let mut points  = vec![[state[0], state[1], state[2], 0.0]];
+
+        for index in 0..steps {
+
+            let arc_length = index as f64 * step;
+
+            let k1 = derivative(state, arc_length);
+            let k2 = derivative(shifted(state, k1, 0.5 * step), arc_length + 0.5 * step);
+            let k3 = derivative(shifted(state, k2, 0.5 * step), arc_length + 0.5 * step);
+            let k4 = derivative(shifted(state, k3, step), arc_length + step);
+
+            for component in 0..3 {
+                state[component] += step / 6.0 * (k1[component] + 2.0 * k2[component] + 2.0 * k3[component] + k4[component]);
+            }
+
+            points.push([state[0], state[1], state[2], arc_length + step]);
+        }
+
+        return points;
+    }
+}
+
+impl EquilibriumShape {
+
+    /// The shape at some arc length from the root, interpolated between the solved points.
+
+    pub fn at (&self, arc_length: quantities::Length) -> ShapePoint {
+
+        let first   = self.points.first().expect("Empty shape");
+        let last    = self.points.last().expect("Empty shape");
+
+        if self.points.len() < 2 || arc_length <= first.arc_length {
+            return *first;
+        }
+
+        if arc_length >= last.arc_length {
+            return *last;
+        }
+
+        let step    = (last.arc_length - first.arc_length).value / (self.points.len() - 1) as f64;
+        let index   = (((arc_length - first.arc_length).value / step) as usize).min(self.points.len() - 2);
+
+        let (before, after) = (&self.points[index], &self.points[index + 1]);
+        let fraction        = ((arc_length - before.arc_length).value / step).clamp(0.0, 1.0);
+
+        return ShapePoint {
+            arc_length: arc_length,
+            radius:     before.radius   + (after.radius  - before.radius)  * fraction,
+            height:     before.height   + (after.height  - before.height)  * fraction,
+            tension:    before.tension  + (after.tension - before.tension) * fraction,
+        };
+    }
+
+    pub fn tip (&self) -> ShapePoint {
+        return *self.points.last().expect("Empty shape");
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::{ physics, resources, scenario, simulation, solar_wind, spacecraft };
+    use bevy::prelude::App;
+
+    fn problem (force_per_meter: f64, angular_velocity: f64) -> EquilibriumProblem {
+        return EquilibriumProblem {
+            length:             quantities::Length::new::<length::meter>(1.0),
+            linear_density:     quantities::LinearMassDensity::new::<linear_mass_density::kilogram_per_meter>(1.0e-6),
+            tip_mass:           quantities::Mass::new::<mass::kilogram>(1.0e-5),
+            tip_force:          quantities::Force::new::<force::newton>(0.0),
+            force_per_meter:    quantities::RadiantExposure::new::<radiant_exposure::joule_per_square_meter>(force_per_meter),
+            angular_velocity:   quantities::Frequency::new::<frequency::hertz>(angular_velocity),
+            root_radius:        quantities::Length::new::<length::meter>(0.1),
+            root_height:        quantities::Length::new::<length::meter>(0.0),
+        };
+    }
+
+    #[test]
+    fn straight_without_drag () {
+
+        let problem = problem(0.0, 1.0);
+        let shape   = problem.solve(1000);
+
+        assert!(shape.tip_error.value.abs() < 1.0e-15);
+
+        let tip = shape.tip();
+        assert!((tip.radius.value - 1.1).abs() < 1.0e-9, "Tip at {} m from the axis", tip.radius.value);
+        assert!(tip.height.value.abs() < 1.0e-9);
+
+        // Root tension: the tip mass at 1.1 m, and the tether from 0.1 m to 1.1 m
+        let expected = 1.0e-5 * 1.1 + 1.0e-6 * (1.1 * 1.1 - 0.1 * 0.1) / 2.0;
+        assert!((shape.points[0].tension.value - expected).abs() < 1.0e-9 * expected);
+    }
+
+    #[test]
+    fn hangs_along_the_wind_without_spin () {
+
+        let shape = problem(-1.0e-7, 0.0).solve(1000);
+
+        let tip = shape.tip();
+        assert!((tip.radius.value - 0.1).abs() < 1.0e-9);
+        assert!((tip.height.value + 1.0).abs() < 1.0e-9);
+    }
+
+    #[test]
+    fn tip_mass_angle_under_light_drag () {
+
+        // With a heavy tip and a weightless tether, the tension is the centrifugal force of the tip,
+        // and the drag bends the tether by f (L - s) / T. That adds up to f L² / 2T at the tip.
+        let mut problem = problem(-1.0e-8, 1.0);
+        problem.linear_density = quantities::LinearMassDensity::new::<linear_mass_density::kilogram_per_meter>(1.0e-12);
+
+        let shape   = problem.solve(1000);
+        let tip     = shape.tip();
+
+        let expected = (-0.5e-8_f64).atan2(1.0e-5 * tip.radius.value);
+        let angle    = tip.height.value.atan2(tip.radius.value - 0.1);
+
+        assert!((angle - expected).abs() < 1.0e-3 * expected.abs(), "Angle {} instead of {}", angle, expected);
+    }
+
+    /// Checks every deployed element against the equilibrium, and the tension of the segment before
+    /// it against the one given, in the order of the deployed elements. Returns the equilibrium.
+
+    fn assert_on_equilibrium (app: &mut App, tensions: &[quantities::Force]) -> EquilibriumShape {
+
+        let deployed = app.world.query::<&spacecraft::esail::ESail>().single(&app.world).deployed_elements.clone();
+
+        let craft_params    = app.world.resource::<spacecraft::SpacecraftParameters>();
+        let segment_length  = craft_params.segment_length();
+
+        let shape = EquilibriumProblem::from_parameters(
+            craft_params,
+            app.world.resource::<solar_wind::SolarWind>(),
+            app.world.resource::<physics::coulomb_drag::CoulombDragParameters>(),
+            segment_length * deployed.len() as f64,
+        ).solve(2000);
+
+        let rotation_axis = craft_params.rotation_axis.normalize();
+
+        for (index, (entity, tension)) in deployed.iter().zip(tensions).enumerate() {
+
+            let element     = app.world.get::<physics::verlet_object::VerletObject>(*entity).expect("No sail element found");
+            let position    = element.current_coordinates.to_dvec3();
+            let height      = position.dot(rotation_axis);
+            let radius      = (position - rotation_axis * height).length();
+
+            let expected    = shape.at(segment_length * (index + 1) as f64);
+            assert!((radius - expected.radius.value).abs() < 5.0e-3, "Element {}: {} m from the axis instead of {}", index, radius, expected.radius.value);
+            assert!((height - expected.height.value).abs() < 5.0e-3, "Element {}: {} m high instead of {}", index, height, expected.height.value);
+
+            // Tension of the segment before the element, so halfway to the preceding one
+            let expected_tension = shape.at(segment_length * (index as f64 + 0.5)).tension;
+            assert!((*tension - expected_tension).abs() < expected_tension * 0.02, "Element {}: tension {} N instead of {}", index, tension.value, expected_tension.value);
+        }
+
+        return shape;
+    }
+
+    /// Tension of every deployed element, in order.
+
+    fn tensions (app: &mut App) -> Vec<quantities::Force> {
+
+        let deployed = app.world.query::<&spacecraft::esail::ESail>().single(&app.world).deployed_elements.clone();
+
+        return deployed.iter()
+            .map(|entity| app.world.get::<physics::verlet_object::VerletObject>(*entity).expect("No sail element found").tension)
+            .collect();
+    }
+
+    #[test]
+    fn settled_verlet_simulation_matches () {
+
+        // The tether is deployed without potential and the potential comes in slowly, so that the
+        // tether isn't left swinging. It's deployed slowly too, or the Coriolis force would take the
+        // spin out of the wire faster than the tension can give it back. It still trails while it
+        // pays out, and swings about once it stops, which nothing but the thrusters damp: a remote
+        // unit light enough next to the wire for the drag to bend the tether.
+        let scenario = scenario::parse_scenario(r#"
+            [spacecraft]
+            rpm             = "5 rpm"
+            wire_length     = "1 m"
+            wire_potential  = "0 kV"
+
+            [remote_unit]
+            dry_mass            = "0.1 mg"
+            propellant          = "0.1 mg"
+            auxiliary_tether    = "0 m"
+            command             = "damping"
+            damping_gain        = "1 /s"
+
+            [convergence]
+            velocity_tolerance  = "2 mm/s"
+
+            [[timeline]]
+            action  = "deploy"
+            start   = "0 s"
+            length  = "1 m"
+            speed   = "0.01 m/s"
+
+            [[timeline]]
+            action      = "ramp_potential"
+            start       = "100 s"
+            target      = "20 kV"
+            duration    = "100 s"
+        "#).expect("Invalid scenario");
+
+        let mut app = simulation::headless_app(scenario);
+
+        while !app.world.resource::<simulation::convergence::ConvergenceMonitor>().is_steady() {
+            assert!(app.world.resource::<resources::SimulationParameters>().simulated_time.get::<time::second>() < 600.0, "Never settled");
+            app.update();
+        }
+
+        let tensions    = tensions(&mut app);
+        let shape       = assert_on_equilibrium(&mut app, &tensions);
+
+        // Strong enough a drag to make the test mean something
+        assert!(shape.tip().height.value < -0.2);
+    }
+
+    #[test]
+    fn remote_unit_tension_matches () {
+
+        // A remote unit some thousands of times heavier than a segment, which the sweeps alone
+        // hardly move: its pull has to come out all along the tether. The wire keeps ringing under
+        // that much tension, so the tension is averaged over a while instead of waiting for it to
+        // settle. The thrusters damp the swinging the deployment leaves, with a gain of about twice its
+        // frequency ω √(root radius / length): more only slows it down, and the tridiagonal solver doesn't
+        // damp anything on its own.
+        for solver in [resources::ConstraintSolver::GaussSeidel, resources::ConstraintSolver::RedBlack, resources::ConstraintSolver::Tridiagonal] {
+
+            let mut scenario = scenario::parse_scenario(r#"
+                [spacecraft]
+                rpm             = "5 rpm"
+                spin_mode       = "held"
+                wire_length     = "1 m"
+                wire_potential  = "20 kV"
+
+                [remote_unit]
+                dry_mass            = "1 g"
+                propellant          = "1 mg"
+                auxiliary_tether    = "0 m"
+                thruster            = "ionic"
+                command             = "damping"
+                damping_gain        = "0.3 /s"
+
+                [[timeline]]
+                action  = "deploy"
+                start   = "0 s"
+                length  = "1 m"
+                speed   = "0.01 m/s"
+            "#).expect("Invalid scenario");
+
+            scenario.simulation.constraint_solver = solver;
+
+            let mut app = simulation::headless_app(scenario);
+
+            let time = |app: &App| app.world.resource::<resources::SimulationParameters>().simulated_time.get::<time::second>();
+
+            while time(&app) < 150.0 {
+                app.update();
+            }
+
+            let mut sums    = tensions(&mut app);
+            let mut samples = 1.0;
+
+            while time(&app) < 170.0 {
+                app.update();
+                for (sum, tension) in sums.iter_mut().zip(tensions(&mut app)) {
+                    *sum += tension;
+                }
+                samples += 1.0;
+            }
+
+            let averages: Vec<quantities::Force> = sums.into_iter().map(|sum| sum / samples).collect();
+
+            assert_on_equilibrium(&mut app, &averages);
+        }
+    }
+}
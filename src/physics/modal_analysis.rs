@@ -0,0 +1,390 @@
+// Natural frequencies and mode shapes of the deployed tether, linearised around its current state.
+//
+// The tether is a chain of point masses (the segment masses, and the remote unit at the tip) joined by
+// segments that carry the tensions of the simulation. It spins with the sail, and in the rotating
+// frame every element can move sideways in two directions:
+//
+//  - In-plane, along the spin: perpendicular to the plane that contains the spin axis and the element.
+//  - Out-of-plane: in that plane, perpendicular to the tether. For a tether in the spin plane that's
+//    along the spin axis.
+//
+// Small sideways displacements u_k of element k follow
+//
+//      m_k ü_k = T_k (u_k-1 - u_k) / l_k + T_k+1 (u_k+1 - u_k) / l_k+1 + m_k ω² c_k u_k
+//
+// with T_k and l_k the tension and length of the segment before the element. The last term is the
+// centrifugal force, which pushes the element further away: c_k = 1 in-plane, and out-of-plane the
+// square of how much the direction of motion points away from the axis. The root is held by the reel.
+// Coriolis couples sideways motion with motion along the tether, which the segments don't allow, so
+// it's left out.
+//
+// That's K u = Ω² M u, with K tridiagonal and M diagonal, so D = M^-1/2 K M^-1/2 is a symmetric
+// tridiagonal matrix. Its eigenvalues are found one by one with Sturm sequences and bisection, and
+// the mode shapes with inverse iteration (Thomas algorithm). A negative eigenvalue is an unstable
+// mode, a compressed tether for example.
+
+use bevy::prelude::*;
+use bevy::math::DVec3;
+
+use uom::si::f64 as quantities;
+use uom::si::*;
+
+use crate::{ components, spacecraft };
+use super::{ tridiagonal, verlet_object };
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ModeFamily {
+    InPlane,
+    OutOfPlane,
+}
+
+impl ModeFamily {
+
+    pub const ALL: [ModeFamily; 2] = [ModeFamily::InPlane, ModeFamily::OutOfPlane];
+
+    pub fn name (&self) -> &'static str {
+        match self {
+            ModeFamily::InPlane     => "In-plane",
+            ModeFamily::OutOfPlane  => "Out-of-plane",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Mode {
+    pub frequency:  quantities::Frequency,  // Natural frequency, in Hz (not radians per second). Zero if unstable.
+    pub unstable:   bool,                   // Negative stiffness: the mode grows instead of oscillating
+    pub shape:      Vec<DVec3>,             // Displacement of every deployed element, the largest one being 1 m
+}
+
+#[derive(Clone, Debug)]
+pub struct ModalAnalysis {
+    pub in_plane:           Vec<Mode>,      // Lowest modes first
+    pub out_of_plane:       Vec<Mode>,
+    pub highest_frequency:  quantities::Frequency,  // Of all the modes, not only the ones kept
+    pub spin_frequency:     quantities::Frequency,
+}
+
+impl ModalAnalysis {
+
+    pub fn modes (&self, family: ModeFamily) -> &Vec<Mode> {
+        match family {
+            ModeFamily::InPlane     => &self.in_plane,
+            ModeFamily::OutOfPlane  => &self.out_of_plane,
+        }
+    }
+}
+
+
+
+/// Lowest modes of both families for the deployed part of the tether, or None if there's nothing
+/// deployed or the tether is cut (and so not held by the reel anymore).
+
+pub fn analyse (
+    esail:          &spacecraft::esail::ESail,
+    verlet_query:   &Query<&verlet_object::VerletObject>,
+    mass_query:     &Query<&components::Mass>,
+    craft_params:   &spacecraft::SpacecraftParameters,
+    modes:          usize,
+    ) -> Option<ModalAnalysis> {
+
+    if esail.deployed_elements.is_empty() || esail.is_cut {
+        return None;
+    }
+
+    // The root is the last element still in the reel, or the origin if there's none left
+    let first_deployed  = esail.elements.len() - esail.deployed_elements.len();
+    let root            = if first_deployed > 0 {
+        verlet_query.get(esail.elements[first_deployed - 1]).expect("No sail element found").current_coordinates.to_dvec3()
+    } else {
+        esail.origin.to_dvec3()
+    };
+
+    let positions: Vec<DVec3> = esail.deployed_elements.iter()
+        .map(|entity| verlet_query.get(*entity).expect("No sail element found").current_coordinates.to_dvec3())
+        .collect();
+
+    let masses: Vec<f64> = esail.deployed_elements.iter()
+        .map(|entity| mass_query.get(*entity).expect("No mass found").0.get::<mass::kilogram>())
+        .collect();
+
+    let angular_velocity = craft_params.angular_velocity().value;
+
+    let spin_axis = craft_params.rotation_axis.normalize();
+
+    let tensions: Vec<f64> = esail.deployed_elements.iter()
+        .map(|entity| verlet_query.get(*entity).expect("No sail element found").tension.get::<force::newton>())
+        .collect();
+
+    let families = ModeFamily::ALL.map(|family| {
+        chain_modes(family, root, &positions, &masses, &tensions, spin_axis, angular_velocity, modes)
+    });
+
+    let [(in_plane, highest_in_plane), (out_of_plane, highest_out_of_plane)] = families;
+
+    return Some(ModalAnalysis {
+        in_plane:           in_plane,
+        out_of_plane:       out_of_plane,
+        highest_frequency:  frequency_of(highest_in_plane.max(highest_out_of_plane)),
+        spin_frequency:     craft_params.rpm,
+    });
+}
+
+/// Modes of one family, and the highest eigenvalue of all (for the timestep).
+
+fn chain_modes (
+    family:             ModeFamily,
+    root:               DVec3,
+    positions:          &[DVec3],
+    masses:             &[f64],
+    tensions:           &[f64],
+    spin_axis:          DVec3,
+    angular_velocity:   f64,
+    modes:              usize,
+    ) -> (Vec<Mode>, f64) {
+
+    let n = positions.len();
+
+    // Direction in which every element moves, for this family
+    let directions: Vec<DVec3> = (0..n).map(|index| {
+
+        let previous    = if index > 0 { positions[index - 1] } else { root };
+        let along       = (positions[index] - previous).normalize_or_zero();
+
+        let from_axis   = positions[index] - spin_axis * positions[index].dot(spin_axis);
+        let outwards    = if from_axis.length() > 0.0 { from_axis.normalize() } else { along };
+
+        let in_plane    = spin_axis.cross(outwards).normalize_or_zero();
+
+        match family {
+            ModeFamily::InPlane     => in_plane,
+            ModeFamily::OutOfPlane  => in_plane.cross(along).normalize_or_zero(),
+        }
+    }).collect();
+
+    // Stiffness of every segment against sideways motion, T / l
+    let segment_stiffness: Vec<f64> = (0..n).map(|index| {
+        let previous = if index > 0 { positions[index - 1] } else { root };
+        let length   = (positions[index] - previous).length();
+        if length > 0.0 { tensions[index] / length } else { 0.0 }
+    }).collect();
+
+    let mut diagonal        = vec![0.0; n];
+    let mut off_diagonal    = vec![0.0; n];    // Between element k and k + 1, the last one unused
+
+    for index in 0..n {
+
+        let outer_stiffness = if index + 1 < n { segment_stiffness[index + 1] } else { 0.0 };
+
+        let from_axis       = positions[index] - spin_axis * positions[index].dot(spin_axis);
+        let away_from_axis  = if from_axis.length() > 0.0 { directions[index].dot(from_axis.normalize()) } else { 0.0 };
+        let centrifugal     = match family {
+            ModeFamily::InPlane     => masses[index] * angular_velocity * angular_velocity,
+            ModeFamily::OutOfPlane  => masses[index] * angular_velocity * angular_velocity * away_from_axis * away_from_axis,
+        };
+
+        diagonal[index] = (segment_stiffness[index] + outer_stiffness - centrifugal) / masses[index];
+
+        if index + 1 < n {
+            off_diagonal[index] = -outer_stiffness / (masses[index] * masses[index + 1]).sqrt();
+        }
+    }
+
+    let eigenvalues: Vec<f64> = (0..modes.min(n)).map(|index| eigenvalue(&diagonal, &off_diagonal, index)).collect();
+
+    let highest = eigenvalue(&diagonal, &off_diagonal, n - 1);
+
+    let modes = eigenvalues.iter().map(|value| {
+
+        // Back from the mass-weighted coordinates to displacements
+        let vector = eigenvector(&diagonal, &off_diagonal, *value);
+        let displacements: Vec<f64> = vector.iter().zip(masses).map(|(component, mass)| component / mass.sqrt()).collect();
+
+        let largest = displacements.iter().fold(0.0_f64, |largest, displacement| largest.max(displacement.abs()));
+        let scale   = if largest > 0.0 { 1.0 / largest } else { 0.0 };
+
+        Mode {
+            frequency:  frequency_of(*value),
+            unstable:   *value < 0.0,
+            shape:      displacements.iter().zip(&directions).map(|(displacement, direction)| *direction * (displacement * scale)).collect(),
+        }
+    }).collect();
+
+    return (modes, highest);
+}
+
+fn frequency_of (eigenvalue: f64) -> quantities::Frequency {
+    return quantities::Frequency::new::<frequency::hertz>(eigenvalue.max(0.0).sqrt() / (2.0 * std::f64::consts::PI));
+}
+
+/// Number of eigenvalues below x (Sturm sequence of the symmetric tridiagonal matrix).
+
+fn eigenvalues_below (diagonal: &[f64], off_diagonal: &[f64], x: f64) -> usize {
+
+    let mut count   = 0;
+    let mut pivot   = 1.0;
+
+    for index in 0..diagonal.len() {
+
+        let coupling = if index > 0 { off_diagonal[index - 1] } else { 0.0 };
+
+        pivot = diagonal[index] - x - if index > 0 { coupling * coupling / pivot } else { 0.0 };
+
+        if pivot == 0.0 {
+            pivot = f64::EPSILON * (diagonal[index].abs() + x.abs()).max(f64::MIN_POSITIVE);
+        }
+
+        if pivot < 0.0 {
+            count += 1;
+        }
+    }
+
+    return count;
+}
+
+/// The index-th eigenvalue, from the lowest, by bisection between the Gershgorin bounds.
+
+fn eigenvalue (diagonal: &[f64], off_diagonal: &[f64], index: usize) -> f64 {
+
+    let n = diagonal.len();
+
+    let radius = |row: usize| {
+        (if row > 0 { off_diagonal[row - 1].abs() } else { 0.0 }) + (if row + 1 < n { off_diagonal[row].abs() } else { 0.0 })
+    };
+
+    let mut low     = (0..n).map(|row| diagonal[row] - radius(row)).fold(f64::INFINITY, f64::min);
+    let mut high    = (0..n).map(|row| diagonal[row] + radius(row)).fold(f64::NEG_INFINITY, f64::max);
+
+    for _ in 0..200 {
+
+        let middle = 0.5 * (low + high);
+
+        if middle <= low || middle >= high {
+            break;
+        }
+
+        if eigenvalues_below(diagonal, off_diagonal, middle) > index {
+            high = middle;
+        } else {
+            low = middle;
+        }
+    }
+
+    return 0.5 * (low + high);
+}
+
+/// Eigenvector of an eigenvalue, by inverse iteration: solving (D - λ) x_new = x a few times.
+
+fn eigenvector (diagonal: &[f64], off_diagonal: &[f64], eigenvalue: f64) -> Vec<f64> {
+
+    let n = diagonal.len();
+
+    // Slightly off the eigenvalue, or the matrix would be singular
+    let scale   = diagonal.iter().fold(0.0_f64, |largest, value| largest.max(value.abs())).max(f64::MIN_POSITIVE);
+    let shift   = eigenvalue + 1.0e-10 * scale;
+
+    let shifted: Vec<f64>   = diagonal.iter().map(|value| value - shift).collect();
+    let lower: Vec<f64>     = (0..n).map(|row| if row > 0 { off_diagonal[row - 1] } else { 0.0 }).collect();
+
+    let mut vector = vec![1.0; n];
+
+    for _ in 0..3 {
+
+        let solution = tridiagonal::solve(&lower, &shifted, off_diagonal, &vector);
+
+        let norm = solution.iter().map(|component| component * component).sum::<f64>().sqrt();
+
+        if !(norm > 0.0 && norm.is_finite()) {
+            break;
+        }
+
+        vector = solution.iter().map(|component| component / norm).collect();
+    }
+
+    return vector;
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    use std::f64::consts::PI;
+
+    /// Elements along x every segment_length from the root at the origin, spinning around z.
+
+    fn along_x (elements: usize, segment_length: f64) -> Vec<DVec3> {
+        return (1..=elements).map(|index| DVec3::X * segment_length * index as f64).collect();
+    }
+
+    #[test]
+    fn string_with_uniform_tension () {
+
+        // Not spinning, held at the root and free at the tip: Ω_k² = 4 T / (m l) sin²((2k - 1) π / (2 (2n + 1)))
+        let (n, tension, mass, segment_length) = (50, 0.02_f64, 1.0e-4, 0.2);
+
+        let positions   = along_x(n, segment_length);
+        let masses      = vec![mass; n];
+        let tensions    = vec![tension; n];
+
+        for family in ModeFamily::ALL {
+
+            let (modes, highest) = chain_modes(family, DVec3::ZERO, &positions, &masses, &tensions, DVec3::Z, 0.0, 5);
+
+            for (index, mode) in modes.iter().enumerate() {
+
+                let angle       = (2 * index + 1) as f64 * PI / (2 * (2 * n + 1)) as f64;
+                let expected    = (4.0 * tension / (mass * segment_length)).sqrt() * angle.sin() / (2.0 * PI);
+
+                let frequency = mode.frequency.get::<frequency::hertz>();
+                assert!((frequency - expected).abs() < 1.0e-9 * expected, "{} mode {}: {} Hz instead of {} Hz", family.name(), index, frequency, expected);
+                assert!(!mode.unstable);
+            }
+
+            let angle = (2 * n - 1) as f64 * PI / (2 * (2 * n + 1)) as f64;
+            let expected_highest = 4.0 * tension / (mass * segment_length) * angle.sin().powi(2);
+            assert!((highest - expected_highest).abs() < 1.0e-9 * expected_highest, "{}: highest eigenvalue {} instead of {}", family.name(), highest, expected_highest);
+        }
+    }
+
+    #[test]
+    fn spinning_string () {
+
+        // With the root on the axis and no tip mass the tension is μω²(L² - r²)/2, and the modes are the
+        // odd Legendre polynomials P_j(r/L): Ω² = ω² j (j + 1) / 2 out of the plane, and one ω² less in it
+        let (n, mass, segment_length, angular_velocity) = (400, 1.0e-4, 0.05, 0.3_f64);
+
+        let positions   = along_x(n, segment_length);
+
+        // Every element has the mass of the half segments on both sides, so the tip only one half
+        let masses: Vec<f64> = (0..n).map(|index| if index + 1 < n { mass } else { mass / 2.0 }).collect();
+
+        // A segment pulls with the centrifugal force of everything beyond it
+        let tensions: Vec<f64> = (0..n).map(|index| {
+            (index..n).map(|outer| masses[outer] * angular_velocity * angular_velocity * positions[outer].x).sum()
+        }).collect();
+
+        for (family, shift) in [(ModeFamily::OutOfPlane, 0.0), (ModeFamily::InPlane, 1.0)] {
+
+            let (modes, _) = chain_modes(family, DVec3::ZERO, &positions, &masses, &tensions, DVec3::Z, angular_velocity, 3);
+
+            for (mode, degree) in modes.iter().zip([1.0_f64, 3.0, 5.0]) {
+
+                let expected    = angular_velocity * (degree * (degree + 1.0) / 2.0 - shift).sqrt() / (2.0 * PI);
+                let frequency   = mode.frequency.get::<frequency::hertz>();
+
+                assert!((frequency - expected).abs() < 1.0e-3 * angular_velocity, "{} P{}: {} Hz instead of {} Hz", family.name(), degree, frequency, expected);
+            }
+
+            // Tilting the whole tether out of the plane swings it back at the spin rate, and turning it
+            // in the plane costs nothing
+            let rigid_shape = &modes[0].shape;
+            for (index, displacement) in rigid_shape.iter().enumerate() {
+                let expected = (index + 1) as f64 / n as f64;
+                assert!((displacement.length() - expected).abs() < 1.0e-6, "{} element {}: {} m instead of {} m", family.name(), index, displacement.length(), expected);
+            }
+        }
+    }
+}
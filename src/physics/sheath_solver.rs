@@ -0,0 +1,408 @@
+// Self-consistent electrostatic sheath around one wire, solving the Poisson–Boltzmann equation
+//
+//      ∇²φ = -e (n_i(φ) - n_e(φ)) / ε_0
+//
+// on a 2D cross-section perpendicular to the wire. The solar wind flows along +x.
+//
+// The grid is polar and logarithmic in r (u = ln r), so that it goes from the 10 µm of the wire to
+// hundreds of meters with a reasonable number of nodes. In those coordinates ∇²φ = (φ_uu + φ_θθ) / r².
+//
+// Densities:
+//  - Electrons are a Maxwellian at T_e. Below zero potential they follow Boltzmann. Above it there's
+//    no trapped population (janhunen2007), so only the untrapped, accelerated electrons remain.
+//  - Protons are a cold beam. They thin out as they climb the potential and can't be where eφ is
+//    above their kinetic energy. Downstream of the region they can't cross there's a wake with no
+//    protons, which is what makes the problem 2D.
+//
+// Each outer iteration solves every radial line at once (Thomas algorithm on the Newton-linearised
+// equation), with the angular neighbours taken from the previous line, and then corrects the
+// angle-averaged potential. Boundary conditions: the wire potential at r_w, zero at the edge of the
+// domain.
+
+use uom::si::f64 as quantities;
+use uom::si::*;
+
+use crate::resources;
+use super::{ coulomb_drag, tridiagonal };
+
+use std::f64::consts::PI;
+
+pub struct SheathSolverSettings {
+    pub radial_nodes:       usize,
+    pub angular_nodes:      usize,
+    pub domain_size:        f64,    // Outer radius of the grid, in units of the analytic r_0
+    pub tolerance:          f64,    // Largest potential change in an iteration, relative to the wire potential
+    pub max_iterations:     usize,
+}
+
+impl Default for SheathSolverSettings {
+    fn default() -> Self {
+        SheathSolverSettings {
+            radial_nodes:       240,
+            angular_nodes:      32,
+            domain_size:        10.0,
+            tolerance:          1.0e-7,
+            max_iterations:     2000,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SheathSolution {
+    pub radii:                      Vec<quantities::Length>,
+    pub average_potential:          Vec<quantities::ElectricPotential>,    // Averaged over the angle
+    pub upstream_potential:         Vec<quantities::ElectricPotential>,    // Along -x, facing the solar wind
+    pub downstream_potential:       Vec<quantities::ElectricPotential>,    // Along +x, in the wake
+    pub stopping_distance:          quantities::Length,     // Where the average eφ equals the proton kinetic energy
+    pub sheath_radius:              quantities::Length,     // Where the average eφ drops to T_e
+    pub effective_sheath_radius:    quantities::Length,     // r_0 that gives the same stopping distance with the analytic potential
+    pub iterations:                 usize,
+    pub converged:                  bool,
+}
+
+
+
+/// Solves the sheath for the given wire and solar wind. Returns None if the wire potential can't
+/// stop the protons, since then there's no sheath to speak of.
+
+#[allow(non_snake_case)]
+pub fn solve_sheath (
+    wire_potential: quantities::ElectricPotential,
+    wire_radius:    quantities::Length,
+    n_0:            quantities::VolumetricNumberDensity,
+    T_e:            quantities::Energy,
+    velocity:       quantities::Velocity,
+    settings:       &SheathSolverSettings,
+    ) -> Option<SheathSolution> {
+
+    let plasma = Plasma {
+        n_0:                n_0.get::<volumetric_number_density::per_cubic_meter>(),
+        electron_potential: (T_e / resources::Q_E).get::<electric_potential::volt>(),
+        kinetic_potential:  (0.5 * resources::M_PROTON * velocity * velocity / resources::Q_E).get::<electric_potential::volt>(),
+        charge_over_epsilon: (resources::Q_E / resources::EPSILON_0).value,
+    };
+
+    let v_0 = wire_potential.get::<electric_potential::volt>();
+
+    if v_0 <= plasma.kinetic_potential || settings.radial_nodes < 3 || settings.angular_nodes < 3 {
+        return None;
+    }
+
+    let r_0     = coulomb_drag::potential_cutoff_distance(n_0, T_e).get::<length::meter>();
+    let r_w     = wire_radius.get::<length::meter>();
+    let r_max   = settings.domain_size * r_0;
+
+    let n_r     = settings.radial_nodes;
+    let n_theta = settings.angular_nodes;
+    let du      = (r_max / r_w).ln() / (n_r - 1) as f64;
+    let dtheta  = 2.0 * PI / n_theta as f64;
+
+    let radii: Vec<f64>     = (0..n_r).map(|i| r_w * (i as f64 * du).exp()).collect();
+    let angles: Vec<f64>    = (0..n_theta).map(|j| j as f64 * dtheta).collect();
+
+    // Initial guess: the analytic potential
+    let analytic_normalisation = (1.0 + (r_0 / r_w).powi(2)).ln();
+    let mut potential: Vec<Vec<f64>> = (0..n_theta).map(|_| {
+        radii.iter().map(|r| v_0 * (1.0 + (r_0 / r).powi(2)).ln() / analytic_normalisation).collect()
+    }).collect();
+
+    for line in potential.iter_mut() {
+        line[0]         = v_0;
+        line[n_r - 1]   = 0.0;
+    }
+
+    let mut iterations  = 0;
+    let mut converged   = false;
+    let interior        = n_r - 2;
+
+    while iterations < settings.max_iterations && !converged {
+
+        iterations += 1;
+
+        let wake_half_width = forbidden_radius(&potential, &radii, plasma.kinetic_potential);
+        let in_wake = |i: usize, j: usize| {
+            radii[i] * angles[j].cos() > 0.0 && (radii[i] * angles[j].sin()).abs() < wake_half_width
+        };
+
+        let mut largest_change: f64 = 0.0;
+
+        // Radial line relaxation
+        for j in 0..n_theta {
+
+            let previous_line   = &potential[(j + n_theta - 1) % n_theta];
+            let next_line       = &potential[(j + 1) % n_theta];
+
+            let lower           = vec![1.0 / (du * du); interior];
+            let upper           = vec![1.0 / (du * du); interior];
+            let mut diagonal    = vec![0.0; interior];
+            let mut rhs         = vec![0.0; interior];
+
+            for k in 0..interior {
+
+                let i   = k + 1;
+                let phi = potential[j][i];
+
+                let (rho, rho_slope) = plasma.linearised_charge(phi, in_wake(i, j));
+                let source = radii[i] * radii[i] * plasma.charge_over_epsilon;
+
+                diagonal[k] = -2.0 / (du * du) - 2.0 / (dtheta * dtheta) + source * rho_slope;
+                rhs[k]      = -(previous_line[i] + next_line[i]) / (dtheta * dtheta) - source * (rho - rho_slope * phi);
+            }
+
+            // Dirichlet boundaries go to the right hand side
+            rhs[0]              -= lower[0] * potential[j][0];
+            rhs[interior - 1]   -= upper[interior - 1] * potential[j][n_r - 1];
+
+            let new_line = tridiagonal::solve(&lower, &diagonal, &upper, &rhs);
+
+            for k in 0..interior {
+                largest_change = largest_change.max((new_line[k] - potential[j][k + 1]).abs());
+                potential[j][k + 1] = new_line[k];
+            }
+        }
+
+        // The line relaxation barely moves errors that are uniform in angle, since every line sees
+        // them in its neighbours too. Correct the angle-averaged residual directly.
+        let lower           = vec![1.0 / (du * du); interior];
+        let upper           = vec![1.0 / (du * du); interior];
+        let mut diagonal    = vec![-2.0 / (du * du); interior];
+        let mut rhs         = vec![0.0; interior];
+
+        for k in 0..interior {
+
+            let i       = k + 1;
+            let source  = radii[i] * radii[i] * plasma.charge_over_epsilon;
+
+            for j in 0..n_theta {
+
+                let phi = potential[j][i];
+                let (rho, rho_slope) = plasma.linearised_charge(phi, in_wake(i, j));
+
+                let residual = (potential[j][i + 1] - 2.0 * phi + potential[j][i - 1]) / (du * du)
+                    + (potential[(j + 1) % n_theta][i] - 2.0 * phi + potential[(j + n_theta - 1) % n_theta][i]) / (dtheta * dtheta)
+                    + source * rho;
+
+                rhs[k]      -= residual / n_theta as f64;
+                diagonal[k] += source * rho_slope / n_theta as f64;
+            }
+        }
+
+        let correction = tridiagonal::solve(&lower, &diagonal, &upper, &rhs);
+
+        for line in potential.iter_mut() {
+            for k in 0..interior {
+                line[k + 1] += correction[k];
+            }
+        }
+
+        let largest_correction = correction.iter().fold(0.0_f64, |largest, value| largest.max(value.abs()));
+
+        converged = largest_change.max(largest_correction) < settings.tolerance * v_0;
+    }
+
+    let average: Vec<f64> = (0..n_r).map(|i| {
+        potential.iter().map(|line| line[i]).sum::<f64>() / n_theta as f64
+    }).collect();
+
+    let stopping_distance   = radius_where(&average, &radii, plasma.kinetic_potential)?;
+    let sheath_radius       = radius_where(&average, &radii, plasma.electron_potential).unwrap_or(r_max);
+
+    // r_0 of the analytic potential that stops the protons at the same distance. The analytic potential
+    // at a given radius grows with r_0, so bisect (in log space, since it spans orders of magnitude).
+    let analytic_potential = |cutoff: f64| {
+        v_0 * (1.0 + (cutoff / stopping_distance).powi(2)).ln() / (1.0 + (cutoff / r_w).powi(2)).ln()
+    };
+
+    let mut low     = stopping_distance * 1.0e-3;
+    let mut high    = r_max * 1.0e3;
+
+    for _ in 0..200 {
+        let middle = (low * high).sqrt();
+        if analytic_potential(middle) < plasma.kinetic_potential { low = middle; } else { high = middle; }
+    }
+
+    let upstream_index      = n_theta / 2;  // θ = π
+    let volts               = |values: &Vec<f64>| values.iter().map(|v| quantities::ElectricPotential::new::<electric_potential::volt>(*v)).collect();
+
+    return Some(SheathSolution {
+        radii:                      radii.iter().map(|r| quantities::Length::new::<length::meter>(*r)).collect(),
+        average_potential:          volts(&average),
+        upstream_potential:         volts(&potential[upstream_index]),
+        downstream_potential:       volts(&potential[0]),
+        stopping_distance:          quantities::Length::new::<length::meter>(stopping_distance),
+        sheath_radius:              quantities::Length::new::<length::meter>(sheath_radius),
+        effective_sheath_radius:    quantities::Length::new::<length::meter>((low * high).sqrt()),
+        iterations:                 iterations,
+        converged:                  converged,
+    });
+}
+
+
+
+/// Solar wind plasma, with potentials in volts and densities per cubic meter.
+
+struct Plasma {
+    n_0:                    f64,
+    electron_potential:     f64,    // T_e / e
+    kinetic_potential:      f64,    // m v² / 2e, the potential that stops a proton
+    charge_over_epsilon:    f64,
+}
+
+impl Plasma {
+
+    /// Charge density and its derivative with respect to the potential, for the Newton linearisation.
+    /// The derivative is kept non-positive, which keeps the linear systems diagonally dominant.
+    fn linearised_charge (&self, potential: f64, in_wake: bool) -> (f64, f64) {
+
+        let step    = 1.0e-3 * self.electron_potential;
+        let rho     = self.charge_density(potential, in_wake);
+        let slope   = (self.charge_density(potential + step, in_wake) - self.charge_density(potential - step, in_wake)) / (2.0 * step);
+
+        return (rho, slope.min(0.0));
+    }
+
+    /// Net charge density divided by e, in particles per cubic meter.
+    fn charge_density (&self, potential: f64, in_wake: bool) -> f64 {
+
+        let ions = if in_wake {
+            0.0
+        } else if potential > 0.0 {
+            self.n_0 * (1.0 - potential / self.kinetic_potential).max(0.0).sqrt()
+        } else {
+            self.n_0
+        };
+
+        let chi = potential / self.electron_potential;
+
+        let electrons = if chi < 0.0 {
+            self.n_0 * chi.exp()
+        } else {
+            self.n_0 * (scaled_erfc(chi.sqrt()) + 2.0 * (chi / PI).sqrt())
+        };
+
+        return ions - electrons;
+    }
+}
+
+
+
+/// Largest radius at which any line of the grid reaches the given potential. Interpolated, so that
+/// the wake doesn't jump from node to node between iterations.
+
+fn forbidden_radius (potential: &[Vec<f64>], radii: &[f64], threshold: f64) -> f64 {
+
+    return potential.iter()
+                    .filter_map(|line| radius_where(line, radii, threshold))
+                    .fold(0.0, f64::max);
+}
+
+
+
+/// Outermost radius at which a decreasing profile crosses the threshold, interpolated in log r.
+
+fn radius_where (profile: &[f64], radii: &[f64], threshold: f64) -> Option<f64> {
+
+    let i = profile.iter().rposition(|phi| *phi >= threshold)?;
+
+    if i + 1 >= profile.len() {
+        return Some(radii[i]);
+    }
+
+    let fraction = (profile[i] - threshold) / (profile[i] - profile[i + 1]);
+
+    return Some((radii[i].ln() + fraction * (radii[i + 1].ln() - radii[i].ln())).exp());
+}
+
+
+
+/// exp(x²) erfc(x) for x >= 0, which doesn't overflow for large x.
+/// Chebyshev fit from Numerical Recipes, fractional error below 1.2e-7.
+
+fn scaled_erfc (x: f64) -> f64 {
+
+    let t = 1.0 / (1.0 + 0.5 * x);
+
+    let polynomial = -1.26551223 + t * (1.00002368 + t * (0.37409196 + t * (0.09678418
+        + t * (-0.18628806 + t * (0.27886807 + t * (-1.13520398 + t * (1.48851587
+        + t * (-0.82215223 + t * 0.17087277))))))));
+
+    return t * polynomial.exp();
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::solar_wind;
+
+    const WIRE_RADIUS: f64 = 10.0e-6;
+
+    /// Sheath of a lone wire at the given potential in the default solar wind, and the analytic r_0.
+
+    fn lone_wire (kilovolts: f64) -> (SheathSolution, f64) {
+
+        let solar_wind = solar_wind::SolarWind{..Default::default()};
+
+        let sheath = solve_sheath(
+            quantities::ElectricPotential::new::<electric_potential::kilovolt>(kilovolts),
+            quantities::Length::new::<length::meter>(WIRE_RADIUS),
+            solar_wind.n_0, solar_wind.T_e, solar_wind.velocity,
+            &SheathSolverSettings{..Default::default()},
+        ).expect("No sheath");
+
+        let r_0 = coulomb_drag::potential_cutoff_distance(solar_wind.n_0, solar_wind.T_e).get::<length::meter>();
+
+        return (sheath, r_0);
+    }
+
+    #[test]
+    fn low_potential_matches_analytic () {
+
+        // Close to the wire the potential goes like ln r whatever the plasma does, and at 1 kV the
+        // sheath is close to the Debye shielding that the analytic r_0 comes from.
+        let (sheath, r_0) = lone_wire(1.0);
+        let v_0 = 1000.0;
+
+        assert!(sheath.converged);
+
+        for (radius, potential) in sheath.radii.iter().zip(&sheath.average_potential) {
+
+            let r = radius.get::<length::meter>();
+            if r > 0.1 * r_0 {
+                break;
+            }
+
+            let analytic = v_0 * (1.0 + (r_0 / r).powi(2)).ln() / (1.0 + (r_0 / WIRE_RADIUS).powi(2)).ln();
+            assert!((potential.value - analytic).abs() < 0.01 * v_0, "{} V at {} m instead of {}", potential.value, r, analytic);
+        }
+
+        let effective = sheath.effective_sheath_radius.get::<length::meter>();
+        assert!((effective / r_0 - 1.0).abs() < 0.15, "Effective r_0 {} m instead of {}", effective, r_0);
+    }
+
+    #[test]
+    fn high_potential_widens_the_sheath () {
+
+        let (sheath, r_0) = lone_wire(20.0);
+
+        assert!(sheath.converged);
+
+        let volts: Vec<f64> = sheath.average_potential.iter().map(|potential| potential.value).collect();
+        assert_eq!(volts[0], 20.0e3);
+        assert_eq!(*volts.last().unwrap(), 0.0);
+        // It can overshoot a little below zero past the sheath, where the electrons outnumber the protons
+        let sheath_nodes = volts.iter().take_while(|volt| **volt > 0.0).count();
+        assert!(volts[..sheath_nodes].windows(2).all(|pair| pair[1] <= pair[0]), "Potential not decreasing away from the wire");
+
+        // The wake has no protons to shield the wire, so the potential reaches further upstream
+        let middle = volts.len() * 9 / 10;
+        assert!(sheath.upstream_potential[middle] > sheath.downstream_potential[middle]);
+
+        // Ion-free, the sheath is wider than the Debye shielding, but not by orders of magnitude
+        let effective = sheath.effective_sheath_radius.get::<length::meter>();
+        assert!(effective > r_0 && effective < 2.0 * r_0, "Effective r_0 {} m, analytic {}", effective, r_0);
+        assert!(sheath.stopping_distance < sheath.sheath_radius);
+    }
+}
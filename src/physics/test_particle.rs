@@ -0,0 +1,307 @@
+// Test-particle calculation of the empirical K coefficient of janhunen2007 eq. 8.
+//
+// Solar wind protons are traced one by one through the static potential of a single charged wire,
+// in the plane perpendicular to it. The wire is along z, the solar wind flows along +x, and every
+// proton enters with an impact parameter b (its y coordinate far upstream). The momentum that the
+// protons lose along x is the momentum that the wire gains, so the drag per unit length is
+//
+//      dF/dz = n_0 * m_p * v² * ∫ (1 - v_x,final / v) db
+//
+// and comparing with eq. 8 (dF/dz = K * m_p * n_0 * v² * r_s) gives K = ∫ (1 - v_x,final / v) db / r_s.
+// The integral over b is done by Monte Carlo, sampling b uniformly.
+
+use uom::si::f64 as quantities;
+use uom::si::*;
+use bevy::math::DVec2;
+
+use crate::{ resources, solar_wind, spacecraft };
+use super::coulomb_drag;
+
+/// Settings of the Monte Carlo integration.
+
+pub struct TestParticleSettings {
+    pub particles:          usize,  // Number of protons traced
+    pub domain_size:        f64,    // Half-width of the simulated box, in units of r_0
+    pub max_steps:          usize,  // Protons still inside the box after this many steps count as trapped
+    pub seed:               u64,
+}
+
+impl Default for TestParticleSettings {
+    fn default() -> Self {
+        TestParticleSettings {
+            particles:          10000,
+            domain_size:        8.0,
+            max_steps:          200_000,
+            seed:               1,
+        }
+    }
+}
+
+/// Result of a Monte Carlo run.
+
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Copy)]
+pub struct KCoefficient {
+    pub K:                  f64,
+    pub standard_error:     f64,    // Statistical error of K
+    pub force_per_meter:    quantities::RadiantExposure,
+    pub absorbed:           usize,  // Protons that hit the wire or got trapped. They give all their momentum.
+}
+
+
+
+/// Wire and solar wind a K is computed for. K is only good for the operating point it came from.
+
+#[allow(non_snake_case)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OperatingPoint {
+    pub wire_potential: quantities::ElectricPotential,
+    pub wire_radius:    quantities::Length,     // Effective radius, with the loop wires
+    pub n_0:            quantities::VolumetricNumberDensity,
+    pub T_e:            quantities::Energy,
+    pub velocity:       quantities::Velocity,
+}
+
+impl OperatingPoint {
+
+    /// The current one.
+    pub fn of (solar_wind: &solar_wind::SolarWind, spacecraft: &spacecraft::SpacecraftParameters) -> Self {
+        return OperatingPoint {
+            wire_potential: spacecraft.wire_potential,
+            wire_radius:    spacecraft.effective_wire_radius(),
+            n_0:            solar_wind.n_0,
+            T_e:            solar_wind.T_e,
+            velocity:       solar_wind.velocity,
+        };
+    }
+}
+
+
+
+/// Computes K by tracing solar wind protons around a wire at the given potential.
+/// Returns None if the wire is not positively charged, since then there's no stopping distance to
+/// normalise with.
+
+#[allow(non_snake_case)]
+pub fn monte_carlo_k (
+    wire_potential: quantities::ElectricPotential,
+    wire_radius:    quantities::Length,
+    n_0:            quantities::VolumetricNumberDensity,
+    T_e:            quantities::Energy,
+    velocity:       quantities::Velocity,
+    settings:       &TestParticleSettings,
+    ) -> Option<KCoefficient> {
+
+    let r_0 = coulomb_drag::potential_cutoff_distance(n_0, T_e);
+    let r_s = coulomb_drag::proton_stopping_distance(wire_potential, wire_radius, r_0, velocity);
+
+    if !(r_s.value.is_finite() && r_s.value > 0.0) || settings.particles == 0 {
+        return None;
+    }
+
+    let wire = WirePotential {
+        v_0:                wire_potential.get::<electric_potential::volt>(),
+        r_0:                r_0.get::<length::meter>(),
+        r_w:                wire_radius.get::<length::meter>(),
+        charge_to_mass:     (resources::Q_E / resources::M_PROTON).value,
+    };
+
+    let v_inf       = velocity.get::<velocity::meter_per_second>();
+    let half_width  = settings.domain_size * wire.r_0;
+
+    let mut random  = XorShift::new(settings.seed);
+    let mut sum     = 0.0;
+    let mut sum_sq  = 0.0;
+    let mut absorbed = 0;
+
+    for _ in 0..settings.particles {
+
+        let impact_parameter = half_width * (2.0 * random.next_f64() - 1.0);
+
+        let momentum_loss = match trace_proton(&wire, impact_parameter, v_inf, half_width, settings.max_steps) {
+            Some(final_velocity_x)  => 1.0 - final_velocity_x / v_inf,
+            None                    => { absorbed += 1; 1.0 },
+        };
+
+        sum     += momentum_loss;
+        sum_sq  += momentum_loss * momentum_loss;
+    }
+
+    let samples     = settings.particles as f64;
+    let mean        = sum / samples;
+    let variance    = (sum_sq / samples - mean * mean).max(0.0);
+
+    // ∫ db over [-half_width, half_width] is the mean times the width of the interval
+    let integral        = 2.0 * half_width * mean;
+    let integral_error  = 2.0 * half_width * (variance / samples).sqrt();
+
+    let r_s_meters  = r_s.get::<length::meter>();
+    let integral    = quantities::Length::new::<length::meter>(integral);
+
+    return Some(KCoefficient {
+        K:                  integral.value / r_s_meters,
+        standard_error:     integral_error / r_s_meters,
+        force_per_meter:    integral * resources::M_PROTON * n_0 * velocity * velocity,
+        absorbed,
+    });
+}
+
+
+
+/// Janhunen's potential around the wire, V(r) = V_0 ln(1 + (r_0/r)²) / ln(1 + (r_0/r_w)²).
+/// Everything in SI units.
+
+struct WirePotential {
+    v_0:            f64,
+    r_0:            f64,
+    r_w:            f64,
+    charge_to_mass: f64,
+}
+
+impl WirePotential {
+
+    fn potential (&self, r: f64) -> f64 {
+        let normalisation = (1.0 + (self.r_0 / self.r_w).powi(2)).ln();
+        return self.v_0 * (1.0 + (self.r_0 / r).powi(2)).ln() / normalisation;
+    }
+
+    /// Acceleration of a proton at a given position, -(q/m) ∇V.
+    fn acceleration (&self, position: DVec2) -> DVec2 {
+        let r = position.length();
+        let normalisation = (1.0 + (self.r_0 / self.r_w).powi(2)).ln();
+        let magnitude = self.charge_to_mass * self.v_0 / normalisation * 2.0 * self.r_0 * self.r_0 / (r * (r * r + self.r_0 * self.r_0));
+        return position / r * magnitude;
+    }
+}
+
+
+
+/// Traces one proton across the box with velocity Verlet, and returns its x velocity at infinity.
+/// Returns None if the proton hits the wire or doesn't leave the box.
+
+fn trace_proton (
+    wire:               &WirePotential,
+    impact_parameter:   f64,
+    v_inf:              f64,
+    half_width:         f64,
+    max_steps:          usize,
+    ) -> Option<f64> {
+
+    let mut position = DVec2::new(-half_width, impact_parameter);
+
+    // The proton enters the box already slowed down by the potential, so that its speed at infinity is v_inf
+    let entry_energy = v_inf * v_inf - 2.0 * wire.charge_to_mass * wire.potential(position.length());
+    let mut velocity = DVec2::new(entry_energy.max(0.0).sqrt(), 0.0);
+
+    let mut acceleration = wire.acceleration(position);
+
+    for _ in 0..max_steps {
+
+        let r = position.length();
+
+        if r < wire.r_w {
+            return None;
+        }
+
+        if position.x > half_width || position.y.abs() > 2.0 * half_width || (position.x < -half_width && velocity.x < 0.0) {
+
+            // Speed at infinity from energy conservation, keeping the direction of the velocity
+            let speed_at_infinity = (velocity.length_squared() + 2.0 * wire.charge_to_mass * wire.potential(r)).sqrt();
+
+            return Some(velocity.normalize_or_zero().x * speed_at_infinity);
+        }
+
+        // Small steps where the field changes fast (close to the wire) or the proton turns around
+        let speed   = velocity.length().max(0.1 * v_inf);
+        let dt      = (0.01 * r / speed).min(0.01 * (r / acceleration.length()).sqrt());
+
+        position        += velocity * dt + acceleration * (0.5 * dt * dt);
+        let new_acceleration = wire.acceleration(position);
+        velocity        += (acceleration + new_acceleration) * (0.5 * dt);
+        acceleration    = new_acceleration;
+    }
+
+    return None;
+}
+
+
+
+/// Small xorshift64* generator, so that runs are reproducible without pulling in a dependency.
+
+struct XorShift ( u64 );
+
+impl XorShift {
+
+    fn new (seed: u64) -> Self {
+        return Self(seed.max(1));
+    }
+
+    /// Uniform in [0, 1)
+    fn next_f64 (&mut self) -> f64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        let value = self.0.wrapping_mul(0x2545_F491_4F6C_DD1D);
+        return (value >> 11) as f64 / (1u64 << 53) as f64;
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    /// K for the default wire at 20 kV in the default solar wind.
+
+    fn default_k (settings: &TestParticleSettings) -> Option<KCoefficient> {
+
+        let solar_wind  = solar_wind::SolarWind{..Default::default()};
+        let spacecraft  = spacecraft::SpacecraftParameters{..Default::default()};
+
+        return monte_carlo_k(
+            quantities::ElectricPotential::new::<electric_potential::kilovolt>(20.0), spacecraft.effective_wire_radius(),
+            solar_wind.n_0, solar_wind.T_e, solar_wind.velocity, settings,
+        );
+    }
+
+    #[test]
+    fn k_within_janhunens_range () {
+
+        // janhunen2007 got K = 3.09 from its own Monte Carlo runs
+        let result = default_k(&TestParticleSettings{ particles: 2000, ..Default::default() }).expect("No K for a positive wire");
+
+        assert!(result.K > 0.0 && result.force_per_meter.value > 0.0);
+        assert!((result.K - 3.09).abs() < 3.0 * result.standard_error, "K = {} ± {}", result.K, result.standard_error);
+        assert!(result.standard_error < 0.1 * result.K);
+    }
+
+    #[test]
+    fn reproducible_for_a_seed () {
+
+        let settings = TestParticleSettings{ particles: 200, ..Default::default() };
+
+        let first   = default_k(&settings).expect("No K for a positive wire");
+        let second  = default_k(&settings).expect("No K for a positive wire");
+        let other   = default_k(&TestParticleSettings{ seed: 2, ..settings }).expect("No K for a positive wire");
+
+        assert_eq!(first.K, second.K);
+        assert_eq!(first.standard_error, second.standard_error);
+        assert_ne!(first.K, other.K);
+    }
+
+    #[test]
+    fn none_without_positive_potential () {
+
+        let solar_wind  = solar_wind::SolarWind{..Default::default()};
+        let spacecraft  = spacecraft::SpacecraftParameters{..Default::default()};
+
+        let result = monte_carlo_k(
+            quantities::ElectricPotential::new::<electric_potential::volt>(0.0), spacecraft.effective_wire_radius(),
+            solar_wind.n_0, solar_wind.T_e, solar_wind.velocity, &TestParticleSettings{..Default::default()},
+        );
+
+        assert!(result.is_none());
+    }
+}
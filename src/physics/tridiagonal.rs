@@ -0,0 +1,77 @@
+/// Solves a tridiagonal system with the Thomas algorithm, in O(n).
+///
+/// Row i reads lower[i] * x[i-1] + diagonal[i] * x[i] + upper[i] * x[i+1] = rhs[i], so lower[0] and
+/// upper[n-1] are ignored. The matrix should be diagonally dominant (or symmetric positive
+/// definite), since there's no pivoting.
+
+pub fn solve (
+    lower:      &[f64],
+    diagonal:   &[f64],
+    upper:      &[f64],
+    rhs:        &[f64],
+    ) -> Vec<f64> {
+
+    let n = diagonal.len();
+
+    let mut modified_upper  = vec![0.0; n];
+    let mut modified_rhs    = vec![0.0; n];
+
+    if n == 0 {
+        return modified_rhs;
+    }
+
+    // Forward sweep
+    modified_upper[0]   = upper[0] / diagonal[0];
+    modified_rhs[0]     = rhs[0] / diagonal[0];
+
+    for i in 1..n {
+        let denominator     = diagonal[i] - lower[i] * modified_upper[i - 1];
+        modified_upper[i]   = if i < n - 1 { upper[i] / denominator } else { 0.0 };
+        modified_rhs[i]     = (rhs[i] - lower[i] * modified_rhs[i - 1]) / denominator;
+    }
+
+    // Back substitution
+    let mut solution = modified_rhs;
+
+    for i in (0..n - 1).rev() {
+        solution[i] -= modified_upper[i] * solution[i + 1];
+    }
+
+    return solution;
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn solves_a_known_system () {
+
+        // Diagonally dominant but not symmetric, the right hand side made from a known solution
+        let lower       = [0.0, -1.0, 0.5, 2.0, -0.3];
+        let diagonal    = [4.0, 5.0, -3.0, 6.0, 2.0];
+        let upper       = [1.0, 2.0, -1.0, 1.5, 0.0];
+        let expected    = [1.0, -2.0, 3.0, 0.5, -4.0];
+
+        let rhs: Vec<f64> = (0..5).map(|i| {
+            (if i > 0 { lower[i] * expected[i - 1] } else { 0.0 })
+                + diagonal[i] * expected[i]
+                + if i < 4 { upper[i] * expected[i + 1] } else { 0.0 }
+        }).collect();
+
+        let solution = solve(&lower, &diagonal, &upper, &rhs);
+
+        for (i, (x, expected)) in solution.iter().zip(expected).enumerate() {
+            assert!((x - expected).abs() < 1.0e-12, "x[{}] = {} instead of {}", i, x, expected);
+        }
+    }
+
+    #[test]
+    fn sizes_of_one_and_none () {
+        assert_eq!(solve(&[0.0], &[4.0], &[0.0], &[2.0]), vec![0.5]);
+        assert!(solve(&[], &[], &[], &[]).is_empty());
+    }
+}
@@ -0,0 +1,300 @@
+use uom::si::f64 as quantities;
+use uom::si::{ Dimension, SI };
+use uom::lib::marker::PhantomData;
+use bevy::math::DVec3;
+
+use std::ops::{ Add, AddAssign, Div, Mul, Neg, Sub, SubAssign };
+
+/// uom quantity of any dimension, stored in SI base units as f64.
+pub type Quantity<D> = uom::si::Quantity<D, SI<f64>, f64>;
+
+pub type PositionVector      = Vector3<quantities::Length>;
+pub type VelocityVector      = Vector3<quantities::Velocity>;
+pub type AccelerationVector  = Vector3<quantities::Acceleration>;
+pub type ForceVector         = Vector3<quantities::Force>;
+
+/// Fixed-size 3D vector of uom quantities.
+/// Arithmetic between vectors and scalars follows the arithmetic of the quantities themselves, so
+/// a ForceVector divided by a Mass is an AccelerationVector, and anything else is a compile error.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Vector3<T> ( pub [T; 3] );
+
+
+impl<T: Copy> Vector3<T> {
+
+    pub fn new (x: T, y: T, z: T) -> Self {
+        return Self([x, y, z]);
+    }
+
+    pub fn x (&self) -> T {
+        return self.0[0];
+    }
+
+    pub fn y (&self) -> T {
+        return self.0[1];
+    }
+
+    pub fn z (&self) -> T {
+        return self.0[2];
+    }
+
+
+
+    /// Dot product. The output has whatever units the product of both quantities has.
+
+    pub fn dot<U, O> (&self, other: &Vector3<U>) -> O
+        where T: Mul<U, Output = O>, U: Copy, O: Add<Output = O> {
+
+        return self.x() * other.x() + self.y() * other.y() + self.z() * other.z();
+    }
+
+
+
+    /// Cross product. The output has whatever units the product of both quantities has.
+
+    pub fn cross<U, O> (&self, other: &Vector3<U>) -> Vector3<O>
+        where T: Mul<U, Output = O>, U: Copy, O: Sub<Output = O> + Copy {
+
+        let x = self.y() * other.z() - self.z() * other.y();
+        let y = self.z() * other.x() - self.x() * other.z();
+        let z = self.x() * other.y() - self.y() * other.x();
+
+        return Vector3::new(x, y, z);
+    }
+}
+
+
+impl<D: Dimension + ?Sized> Vector3<Quantity<D>> {
+
+    /// Vector with all components set to zero.
+
+    pub fn zero () -> Self {
+        return Self::from_dvec3(DVec3::ZERO);
+    }
+
+
+
+    /// Builds a vector from a DVec3 whose components are in SI base units.
+
+    pub fn from_dvec3 (vector: DVec3) -> Self {
+        return Self::new(quantity(vector.x), quantity(vector.y), quantity(vector.z));
+    }
+
+
+
+    /// Components in SI base units (meters for a PositionVector, newtons for a ForceVector, ...)
+
+    pub fn to_dvec3 (&self) -> DVec3 {
+        return DVec3::new(self.0[0].value, self.0[1].value, self.0[2].value);
+    }
+
+
+
+    /// Vector of the given magnitude along a direction vector. The direction doesn't need to be
+    /// normalised. A zero direction gives a zero vector instead of NaN.
+
+    pub fn from_direction (magnitude: Quantity<D>, direction: DVec3) -> Self {
+        return Self::from_dvec3(direction.normalize_or_zero() * magnitude.value);
+    }
+
+
+
+    /// Magnitude of the vector, in the same units as its components.
+
+    pub fn norm (&self) -> Quantity<D> {
+        return quantity(self.to_dvec3().length());
+    }
+
+
+
+    /// Unit vector along this one. Dimensionless, so returned as a DVec3.
+    /// Zero vectors return zero instead of NaN.
+
+    pub fn direction (&self) -> DVec3 {
+        return self.to_dvec3().normalize_or_zero();
+    }
+
+
+
+    /// Vector with the same direction and a norm of one base unit.
+
+    pub fn normalize (&self) -> Self {
+        return Self::from_dvec3(self.direction());
+    }
+
+
+
+    /// Angle between two vectors of any units.
+
+    pub fn angle_between<E: Dimension + ?Sized> (&self, other: &Vector3<Quantity<E>>) -> quantities::Angle {
+        let angle = self.to_dvec3().angle_between(other.to_dvec3());
+        return quantities::Angle::new::<uom::si::angle::radian>(angle);
+    }
+}
+
+
+impl PositionVector {
+
+    /// Vector going from point a to point b.
+
+    pub fn from_a_to_b (point_a: Self, point_b: Self) -> Self {
+        return point_b - point_a;
+    }
+}
+
+
+/// Builds a quantity from a value in SI base units.
+/// Generic over the dimension, which Quantity::new can't do.
+
+fn quantity<D: Dimension + ?Sized> (value: f64) -> Quantity<D> {
+    return Quantity { dimension: PhantomData, units: PhantomData, value };
+}
+
+
+impl<T: Add<Output = T> + Copy> Add for Vector3<T> {
+    type Output = Self;
+
+    fn add (self, other: Self) -> Self {
+        return Self::new(self.x() + other.x(), self.y() + other.y(), self.z() + other.z());
+    }
+}
+
+impl<T: Sub<Output = T> + Copy> Sub for Vector3<T> {
+    type Output = Self;
+
+    fn sub (self, other: Self) -> Self {
+        return Self::new(self.x() - other.x(), self.y() - other.y(), self.z() - other.z());
+    }
+}
+
+impl<T: Neg<Output = T> + Copy> Neg for Vector3<T> {
+    type Output = Self;
+
+    fn neg (self) -> Self {
+        return Self::new(-self.x(), -self.y(), -self.z());
+    }
+}
+
+impl<T: AddAssign + Copy> AddAssign for Vector3<T> {
+    fn add_assign (&mut self, other: Self) {
+        for (component, other_component) in self.0.iter_mut().zip(other.0) {
+            *component += other_component;
+        }
+    }
+}
+
+impl<T: SubAssign + Copy> SubAssign for Vector3<T> {
+    fn sub_assign (&mut self, other: Self) {
+        for (component, other_component) in self.0.iter_mut().zip(other.0) {
+            *component -= other_component;
+        }
+    }
+}
+
+/// Vector times scalar: f64, or any quantity (acceleration * time = velocity, and so on).
+impl<T, R, O> Mul<R> for Vector3<T>
+    where T: Mul<R, Output = O> + Copy, R: Copy, O: Copy {
+    type Output = Vector3<O>;
+
+    fn mul (self, value: R) -> Vector3<O> {
+        return Vector3::new(self.x() * value, self.y() * value, self.z() * value);
+    }
+}
+
+/// Vector over scalar: f64, or any quantity (force / mass = acceleration, and so on).
+impl<T, R, O> Div<R> for Vector3<T>
+    where T: Div<R, Output = O> + Copy, R: Copy, O: Copy {
+    type Output = Vector3<O>;
+
+    fn div (self, value: R) -> Vector3<O> {
+        return Vector3::new(self.x() / value, self.y() / value, self.z() / value);
+    }
+}
+
+impl<T: Add<Output = T> + Copy> std::iter::Sum for Vector3<T> where Self: Default {
+    fn sum<I: Iterator<Item = Self>> (iter: I) -> Self {
+        return iter.fold(Self::default(), |total, vector| total + vector);
+    }
+}
+
+impl<D: Dimension + ?Sized> Default for Vector3<Quantity<D>> {
+    fn default () -> Self {
+        return Self::zero();
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use uom::si::{ angle, energy, force, length, mass, time };
+
+    fn meters (x: f64, y: f64, z: f64) -> PositionVector {
+        return PositionVector::from_dvec3(DVec3::new(x, y, z));
+    }
+
+    #[test]
+    fn arithmetic_by_components () {
+
+        let a = meters(1.0, 2.0, 3.0);
+        let b = meters(-4.0, 0.5, 2.0);
+
+        assert_eq!((a + b).to_dvec3(), DVec3::new(-3.0, 2.5, 5.0));
+        assert_eq!((a - b).to_dvec3(), DVec3::new(5.0, 1.5, 1.0));
+        assert_eq!((-a).to_dvec3(), DVec3::new(-1.0, -2.0, -3.0));
+        assert_eq!((a * 2.0).to_dvec3(), DVec3::new(2.0, 4.0, 6.0));
+        assert_eq!((a / 2.0).to_dvec3(), DVec3::new(0.5, 1.0, 1.5));
+
+        let mut c = a;
+        c += b;
+        c -= a;
+        assert_eq!(c, b);
+
+        assert_eq!([a, b].into_iter().sum::<PositionVector>(), a + b);
+    }
+
+    #[test]
+    fn products_carry_units () {
+
+        let force   = ForceVector::from_dvec3(DVec3::new(2.0, 0.0, 0.0));
+        let arm     = meters(0.0, 3.0, 0.0);
+
+        // Force over mass is an acceleration, times a time a velocity
+        let mass            = quantities::Mass::new::<mass::kilogram>(4.0);
+        let acceleration: AccelerationVector    = force / mass;
+        let velocity: VelocityVector            = acceleration * quantities::Time::new::<time::second>(2.0);
+        assert_eq!(velocity.to_dvec3(), DVec3::new(1.0, 0.0, 0.0));
+
+        // Work along a displacement, torque about an arm
+        let work: quantities::Energy = force.dot(&meters(5.0, 1.0, 0.0));
+        assert_eq!(work.get::<energy::joule>(), 10.0);
+
+        let torque = arm.cross(&force);
+        assert_eq!(torque.to_dvec3(), DVec3::new(0.0, 0.0, -6.0));
+    }
+
+    #[test]
+    fn norms_and_directions () {
+
+        let a = meters(3.0, 0.0, 4.0);
+
+        assert_eq!(a.norm().get::<length::meter>(), 5.0);
+        assert!(a.direction().abs_diff_eq(DVec3::new(0.6, 0.0, 0.8), 1.0e-15));
+        assert!((a.normalize().norm().get::<length::meter>() - 1.0).abs() < 1.0e-15);
+
+        let along = ForceVector::from_direction(quantities::Force::new::<force::newton>(10.0), DVec3::new(0.0, 2.0, 0.0));
+        assert_eq!(along.to_dvec3(), DVec3::new(0.0, 10.0, 0.0));
+
+        // Zero vectors give zero, not NaN
+        assert_eq!(PositionVector::zero().direction(), DVec3::ZERO);
+        assert_eq!(ForceVector::from_direction(quantities::Force::new::<force::newton>(1.0), DVec3::ZERO), ForceVector::zero());
+
+        let angle = meters(1.0, 0.0, 0.0).angle_between(&ForceVector::from_dvec3(DVec3::new(0.0, 1.0, 0.0)));
+        assert!((angle.get::<angle::radian>() - std::f64::consts::FRAC_PI_2).abs() < 1.0e-15);
+
+        assert_eq!(PositionVector::from_a_to_b(meters(1.0, 1.0, 1.0), meters(2.0, 3.0, 4.0)), meters(1.0, 2.0, 3.0));
+    }
+}
@@ -0,0 +1,251 @@
+// Records the state of the simulation to a file, for post-processing.
+//
+// Every recorded timestep is a Sample: the simulated time, the total thrust, the center of mass,
+// the spin rate, and the position, velocity and force of every element. Samples are taken inside
+// the simulation loop, one every `decimation` timesteps, so a recording doesn't depend on the
+// frame rate.
+
+use bevy::prelude::*;
+use serde::{ Deserialize, Serialize };
+use uom::si::f64 as quantities;
+
+use crate::{ components, physics, resources, spacecraft };
+use physics::vector3::{ ForceVector, PositionVector, VelocityVector };
+
+#[cfg(feature = "columnar")]
+mod columnar;
+mod writer;
+
+pub struct RecorderPlugin;
+
+impl Plugin for RecorderPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(Recorder{..Default::default()})
+            .add_systems(
+                Last,
+                stop_recording_on_exit
+            )
+        ;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordFormat {
+    Csv,
+    Binary,     // Little-endian, see recorder/writer.rs for the layout
+    #[cfg(feature = "columnar")]
+    ArrowIpc,   // Arrow IPC stream. Columnar formats are always in the long layout, see recorder/columnar.rs
+    #[cfg(feature = "columnar")]
+    Parquet,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordLayout {
+    Wide,   // One row per sample, with columns for every element
+    Long,   // One row per sample, element and variable: time, element, variable, value
+}
+
+impl RecordFormat {
+    pub const ALL: &'static [RecordFormat] = &[
+        RecordFormat::Csv,
+        RecordFormat::Binary,
+        #[cfg(feature = "columnar")]
+        RecordFormat::ArrowIpc,
+        #[cfg(feature = "columnar")]
+        RecordFormat::Parquet,
+    ];
+}
+
+impl RecordLayout {
+    pub const ALL: [RecordLayout; 2] = [RecordLayout::Wide, RecordLayout::Long];
+}
+
+#[derive(Clone)]
+pub struct RecorderSettings {
+    pub path:       String,
+    pub format:     RecordFormat,
+    pub layout:     RecordLayout,
+    pub decimation: u32,    // One sample every this many timesteps
+}
+
+impl Default for RecorderSettings {
+    fn default() -> Self {
+        RecorderSettings {
+            path:       String::from("esme_run.csv"),
+            format:     RecordFormat::Csv,
+            layout:     RecordLayout::Wide,
+            decimation: 10,
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct Recorder {
+    pub settings:   RecorderSettings,
+    pub status:     String,
+    writer:         Option<writer::RecordWriter>,
+    steps:          u64,    // Timesteps since the recording started
+    samples:        u64,    // Samples written
+}
+
+/// State of the whole simulation at one timestep.
+
+pub struct Sample {
+    pub time:           quantities::Time,
+    pub thrust:         ForceVector,    // Sum of the Coulomb drag on every element
+    pub center_of_mass: PositionVector,
+    pub spin_rate:      quantities::Frequency,  // Angular velocity, in rad/s
+    pub iterations:     i32,    // Constraint iterations of the timestep
+    pub residual:       f64,    // Largest relative stretch of a segment after them
+    pub elements:       Vec<ElementSample>,
+}
+
+pub struct ElementSample {
+    pub position:       PositionVector,
+    pub velocity:       VelocityVector,
+    pub force:          ForceVector,
+    pub is_deployed:    bool,
+}
+
+
+
+impl Recorder {
+
+    pub fn is_recording (&self) -> bool {
+        return self.writer.is_some();
+    }
+
+    /// Opens the file and starts recording from the next timestep. The run parameters (the
+    /// scenario, as TOML) are stored along with the data when the format allows it.
+
+    pub fn start (&mut self, run_parameters: &str) {
+
+        self.stop();
+
+        match writer::RecordWriter::create(&self.settings, run_parameters) {
+            Ok(writer) => {
+                self.writer     = Some(writer);
+                self.steps      = 0;
+                self.samples    = 0;
+                self.status     = format!("Recording to {}", self.settings.path);
+            },
+            Err(error) => {
+                self.status = format!("Can't write {}: {}", self.settings.path, error);
+            },
+        }
+    }
+
+    /// Flushes and closes the file.
+
+    pub fn stop (&mut self) {
+
+        if let Some(writer) = self.writer.take() {
+            self.status = match writer.finish() {
+                Ok(())      => format!("{} samples written to {}", self.samples, self.settings.path),
+                Err(error)  => format!("Error closing {}: {}", self.settings.path, error),
+            };
+        }
+    }
+
+    /// Called by the simulation after every timestep. Writes a sample if one is due.
+
+    pub fn record_step (
+        &mut self,
+        sim_params:     &resources::SimulationParameters,
+        craft_params:   &spacecraft::SpacecraftParameters,
+        esail:          &spacecraft::esail::ESail,
+        reel:           &spacecraft::reel::Reel,
+        verlet_query:   &Query<&mut physics::verlet_object::VerletObject>,
+        mass_query:     &Query<&components::Mass>,
+        ) {
+
+        if self.writer.is_none() {
+            return;
+        }
+
+        let due = self.steps % self.settings.decimation.max(1) as u64 == 0;
+        self.steps += 1;
+
+        if !due {
+            return;
+        }
+
+        let sample = Sample::collect(sim_params, craft_params, esail, reel, verlet_query, mass_query);
+
+        let result = self.writer.as_mut().expect("No writer").write(&sample);
+
+        match result {
+            Ok(())      => self.samples += 1,
+            Err(error)  => {
+                // Better to stop than to keep a half-written file growing
+                self.writer = None;
+                self.status = format!("Recording stopped, error writing {}: {}", self.settings.path, error);
+            },
+        }
+    }
+}
+
+
+
+impl Sample {
+
+    pub fn collect (
+        sim_params:     &resources::SimulationParameters,
+        craft_params:   &spacecraft::SpacecraftParameters,
+        esail:          &spacecraft::esail::ESail,
+        reel:           &spacecraft::reel::Reel,
+        verlet_query:   &Query<&mut physics::verlet_object::VerletObject>,
+        mass_query:     &Query<&components::Mass>,
+        ) -> Self {
+
+        let mut elements = Vec::with_capacity(esail.elements.len());
+
+        // The deployed elements are the last ones
+        let first_deployed = esail.elements.len() - esail.deployed_elements.len();
+
+        for (index, entity) in esail.elements.iter().enumerate() {
+
+            let verlet_object = verlet_query.get(*entity).expect("No sail element found");
+
+            // Verlet doesn't store velocities, this is the one implied by the last step
+            let velocity: VelocityVector = (verlet_object.current_coordinates - verlet_object.previous_coordinates) / sim_params.timestep_s;
+
+            elements.push(ElementSample {
+                position:       verlet_object.current_coordinates,
+                velocity:       velocity,
+                force:          verlet_object.current_force,
+                is_deployed:    index >= first_deployed,
+            });
+        }
+
+        let mass_properties = esail.mass_properties(craft_params, reel, &verlet_query.to_readonly(), mass_query);
+
+        return Sample {
+            time:           sim_params.simulated_time,
+            thrust:         esail.total_force,
+            center_of_mass: mass_properties.center_of_mass,
+            spin_rate:      craft_params.angular_velocity(),
+            iterations:     sim_params.iterations_used,
+            residual:       sim_params.constraint_residual,
+            elements:       elements,
+        };
+    }
+}
+
+
+
+/// Closes the file properly when the window is closed while recording.
+
+fn stop_recording_on_exit (
+    mut recorder:   ResMut<Recorder>,
+    exit_events:    EventReader<bevy::app::AppExit>,
+    ) {
+
+    if !exit_events.is_empty() && recorder.is_recording() {
+        recorder.stop();
+        println!("{}", recorder.status);
+    }
+}
@@ -0,0 +1,169 @@
+// Columnar output, as an Arrow IPC stream or a Parquet file, for runs too big for CSV. Only built
+// with the "columnar" feature: cargo run --features columnar
+//
+// The table is always tidy, one row per sample and element, whatever layout is selected:
+//      time_s, element, x_m, ..., fz_N, distance_m, speed_m_s, force_N, thrust_x_N, ..., constraint_residual, deployed
+// Global quantities are repeated on every element of a sample, which costs next to nothing in
+// Parquet. The scenario that produced the run is stored as TOML in the schema metadata, under
+// "esme.scenario".
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{ self, BufWriter };
+use std::sync::{ Arc, Mutex };
+
+use arrow::array::{ ArrayRef, BooleanBuilder, Float64Builder, UInt32Builder };
+use arrow::datatypes::{ DataType, Field, Schema };
+use arrow::ipc::writer::StreamWriter;
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+
+use super::{ RecordFormat, Sample };
+use super::writer::{ element_values, global_values, GLOBAL_VARIABLES, ELEMENT_VARIABLES };
+
+const DERIVED_VARIABLES: [&str; 3] = ["distance_m", "speed_m_s", "force_N"];
+
+const ROWS_PER_BATCH: usize = 65536;
+
+enum Sink {
+    Ipc(StreamWriter<BufWriter<File>>),
+    Parquet(Mutex<ArrowWriter<File>>),     // The Parquet writer isn't Sync, and resources must be
+}
+
+pub struct ColumnarWriter {
+    sink:       Sink,
+    schema:     Arc<Schema>,
+    time:       Float64Builder,
+    element:    UInt32Builder,
+    deployed:   BooleanBuilder,
+    values:     Vec<Float64Builder>,    // Element variables (without deployed), derived variables, global variables
+    rows:       usize,                  // Rows waiting in the builders
+}
+
+impl ColumnarWriter {
+
+    pub fn create (path: &str, format: RecordFormat, run_parameters: &str) -> io::Result<Self> {
+
+        let mut fields = vec![
+            Field::new("time_s",    DataType::Float64, false),
+            Field::new("element",   DataType::UInt32,  false),
+        ];
+        fields.extend(value_names().iter().map(|name| Field::new(*name, DataType::Float64, false)));
+        fields.push(Field::new("deployed", DataType::Boolean, false));
+
+        let metadata = HashMap::from([
+            (String::from("esme.scenario"), run_parameters.to_string()),
+            (String::from("esme.version"),  env!("CARGO_PKG_VERSION").to_string()),
+        ]);
+
+        let schema  = Arc::new(Schema::new_with_metadata(fields, metadata));
+        let file    = File::create(path)?;
+
+        let sink = match format {
+            RecordFormat::ArrowIpc => Sink::Ipc(StreamWriter::try_new(BufWriter::new(file), &schema).map_err(to_io_error)?),
+            RecordFormat::Parquet  => {
+                let properties = WriterProperties::builder().set_compression(Compression::SNAPPY).build();
+                Sink::Parquet(Mutex::new(ArrowWriter::try_new(file, schema.clone(), Some(properties)).map_err(to_io_error)?))
+            },
+            _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, "not a columnar format")),
+        };
+
+        return Ok(ColumnarWriter {
+            sink:       sink,
+            schema:     schema,
+            time:       Float64Builder::new(),
+            element:    UInt32Builder::new(),
+            deployed:   BooleanBuilder::new(),
+            values:     value_names().iter().map(|_| Float64Builder::new()).collect(),
+            rows:       0,
+        });
+    }
+
+    pub fn write (&mut self, sample: &Sample) -> io::Result<()> {
+
+        let globals = global_values(sample);
+
+        for (index, element) in sample.elements.iter().enumerate() {
+
+            let values = element_values(element);
+
+            self.time.append_value(sample.time.value);
+            self.element.append_value(index as u32);
+            self.deployed.append_value(element.is_deployed);
+
+            let derived = [
+                element.position.to_dvec3().length(),
+                element.velocity.to_dvec3().length(),
+                element.force.to_dvec3().length(),
+            ];
+
+            // Element values without the last one, which is the deployed flag
+            let row = values[..ELEMENT_VARIABLES.len() - 1].iter().chain(derived.iter()).chain(globals.iter());
+
+            for (builder, value) in self.values.iter_mut().zip(row) {
+                builder.append_value(*value);
+            }
+
+            self.rows += 1;
+        }
+
+        if self.rows >= ROWS_PER_BATCH {
+            self.flush_batch()?;
+        }
+
+        return Ok(());
+    }
+
+    pub fn finish (mut self) -> io::Result<()> {
+
+        self.flush_batch()?;
+
+        return match self.sink {
+            Sink::Ipc(mut writer)   => writer.finish().map_err(to_io_error),
+            Sink::Parquet(writer)   => writer.into_inner().expect("Parquet writer poisoned").close().map(|_| ()).map_err(to_io_error),
+        };
+    }
+
+    fn flush_batch (&mut self) -> io::Result<()> {
+
+        if self.rows == 0 {
+            return Ok(());
+        }
+
+        let mut columns: Vec<ArrayRef> = vec![
+            Arc::new(self.time.finish()),
+            Arc::new(self.element.finish()),
+        ];
+        columns.extend(self.values.iter_mut().map(|builder| Arc::new(builder.finish()) as ArrayRef));
+        columns.push(Arc::new(self.deployed.finish()));
+
+        let batch = RecordBatch::try_new(self.schema.clone(), columns).map_err(to_io_error)?;
+
+        match &mut self.sink {
+            Sink::Ipc(writer)       => writer.write(&batch).map_err(to_io_error)?,
+            Sink::Parquet(writer)   => writer.get_mut().expect("Parquet writer poisoned").write(&batch).map_err(to_io_error)?,
+        }
+
+        self.rows = 0;
+
+        return Ok(());
+    }
+}
+
+
+
+/// Names of the Float64 columns after time and element, in order.
+
+fn value_names () -> Vec<&'static str> {
+    return ELEMENT_VARIABLES[..ELEMENT_VARIABLES.len() - 1].iter()
+        .chain(DERIVED_VARIABLES.iter())
+        .chain(GLOBAL_VARIABLES.iter())
+        .copied()
+        .collect();
+}
+
+fn to_io_error<E: std::error::Error + Send + Sync + 'static> (error: E) -> io::Error {
+    return io::Error::new(io::ErrorKind::Other, error);
+}
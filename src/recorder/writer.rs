@@ -0,0 +1,242 @@
+// Writes samples as CSV or as a compact binary file, or hands them to the columnar writer.
+// Everything in SI units.
+//
+// Wide layout: one row per sample.
+//      time_s, thrust_x_N, ..., constraint_residual, e0_x_m, e0_y_m, ..., e0_deployed, e1_x_m, ...
+//
+// Long layout: one row per sample, element and variable. Global quantities have no element.
+//      time_s, element, variable, value
+//
+// Binary files are little-endian:
+//      magic       8 bytes, "ESMEREC1"
+//      layout      u8, 0 = wide, 1 = long
+//      names       u32 count, then for every name a u16 byte length and the UTF-8 bytes.
+//                  Column names for the wide layout, variable names for the long layout.
+//      rows        wide: one f64 per column
+//                  long: f64 time, i32 element (-1 for global quantities), u16 variable index, f64 value
+
+use std::fs::File;
+use std::io::{ self, BufWriter, Write };
+
+use super::{ ElementSample, RecordFormat, RecordLayout, RecorderSettings, Sample };
+
+const MAGIC: &[u8; 8] = b"ESMEREC1";
+
+pub const GLOBAL_VARIABLES: [&str; 9] = [
+    "thrust_x_N", "thrust_y_N", "thrust_z_N",
+    "com_x_m", "com_y_m", "com_z_m",
+    "spin_rate_rad_s",
+    "iterations", "constraint_residual",
+];
+
+pub const ELEMENT_VARIABLES: [&str; 10] = [
+    "x_m", "y_m", "z_m",
+    "vx_m_s", "vy_m_s", "vz_m_s",
+    "fx_N", "fy_N", "fz_N",
+    "deployed",
+];
+
+pub enum RecordWriter {
+    Rows(RowWriter),
+    #[cfg(feature = "columnar")]
+    Columnar(super::columnar::ColumnarWriter),
+}
+
+impl RecordWriter {
+
+    /// Creates the file. The run parameters only go into formats that can hold metadata.
+
+    #[cfg_attr(not(feature = "columnar"), allow(unused_variables))]
+    pub fn create (settings: &RecorderSettings, run_parameters: &str) -> io::Result<Self> {
+
+        return match settings.format {
+            RecordFormat::Csv       => Ok(RecordWriter::Rows(RowWriter::create(settings, false)?)),
+            RecordFormat::Binary    => Ok(RecordWriter::Rows(RowWriter::create(settings, true)?)),
+            #[cfg(feature = "columnar")]
+            RecordFormat::ArrowIpc | RecordFormat::Parquet =>
+                Ok(RecordWriter::Columnar(super::columnar::ColumnarWriter::create(&settings.path, settings.format, run_parameters)?)),
+        };
+    }
+
+    pub fn write (&mut self, sample: &Sample) -> io::Result<()> {
+        return match self {
+            RecordWriter::Rows(writer)      => writer.write(sample),
+            #[cfg(feature = "columnar")]
+            RecordWriter::Columnar(writer)  => writer.write(sample),
+        };
+    }
+
+    pub fn finish (self) -> io::Result<()> {
+        return match self {
+            RecordWriter::Rows(writer)      => writer.finish(),
+            #[cfg(feature = "columnar")]
+            RecordWriter::Columnar(writer)  => writer.finish(),
+        };
+    }
+}
+
+
+
+/// CSV and binary files, row by row.
+
+pub struct RowWriter {
+    file:       BufWriter<File>,
+    binary:     bool,
+    layout:     RecordLayout,
+    elements:   Option<usize>,  // Number of elements, known after the first sample
+}
+
+impl RowWriter {
+
+    fn create (settings: &RecorderSettings, binary: bool) -> io::Result<Self> {
+        return Ok(RowWriter {
+            file:       BufWriter::new(File::create(&settings.path)?),
+            binary:     binary,
+            layout:     settings.layout,
+            elements:   None,
+        });
+    }
+
+    fn write (&mut self, sample: &Sample) -> io::Result<()> {
+
+        // The header is written with the first sample, since the wide layout needs the number of elements
+        match self.elements {
+            None => {
+                self.write_header(sample.elements.len())?;
+                self.elements = Some(sample.elements.len());
+            },
+            Some(elements) if elements != sample.elements.len() && self.layout == RecordLayout::Wide => {
+                return Err(io::Error::new(io::ErrorKind::Other, "the number of elements changed, the wide layout can't hold it"));
+            },
+            Some(_) => {},
+        }
+
+        let time    = sample.time.value;
+        let globals = global_values(sample);
+
+        return match (self.binary, self.layout) {
+
+            (false, RecordLayout::Wide) => {
+                let mut row: Vec<String> = vec![time.to_string()];
+                row.extend(globals.iter().map(|value| value.to_string()));
+                for element in sample.elements.iter() {
+                    row.extend(element_values(element).iter().map(|value| value.to_string()));
+                }
+                writeln!(self.file, "{}", row.join(","))
+            },
+
+            (false, RecordLayout::Long) => {
+                for (variable, value) in GLOBAL_VARIABLES.iter().zip(globals) {
+                    writeln!(self.file, "{},,{},{}", time, variable, value)?;
+                }
+                for (index, element) in sample.elements.iter().enumerate() {
+                    for (variable, value) in ELEMENT_VARIABLES.iter().zip(element_values(element)) {
+                        writeln!(self.file, "{},{},{},{}", time, index, variable, value)?;
+                    }
+                }
+                Ok(())
+            },
+
+            (true, RecordLayout::Wide) => {
+                self.file.write_all(&time.to_le_bytes())?;
+                for value in globals {
+                    self.file.write_all(&value.to_le_bytes())?;
+                }
+                for element in sample.elements.iter() {
+                    for value in element_values(element) {
+                        self.file.write_all(&value.to_le_bytes())?;
+                    }
+                }
+                Ok(())
+            },
+
+            (true, RecordLayout::Long) => {
+                for (variable, value) in globals.iter().enumerate() {
+                    self.write_long_binary(time, -1, variable, *value)?;
+                }
+                for (index, element) in sample.elements.iter().enumerate() {
+                    for (variable, value) in element_values(element).iter().enumerate() {
+                        self.write_long_binary(time, index as i32, GLOBAL_VARIABLES.len() + variable, *value)?;
+                    }
+                }
+                Ok(())
+            },
+        };
+    }
+
+    fn finish (mut self) -> io::Result<()> {
+        return self.file.flush();
+    }
+
+    fn write_header (&mut self, elements: usize) -> io::Result<()> {
+
+        let names: Vec<String> = match self.layout {
+            RecordLayout::Wide => {
+                let mut names = vec![String::from("time_s")];
+                names.extend(GLOBAL_VARIABLES.iter().map(|name| name.to_string()));
+                for index in 0..elements {
+                    names.extend(ELEMENT_VARIABLES.iter().map(|name| format!("e{}_{}", index, name)));
+                }
+                names
+            },
+            RecordLayout::Long => {
+                GLOBAL_VARIABLES.iter().chain(ELEMENT_VARIABLES.iter()).map(|name| name.to_string()).collect()
+            },
+        };
+
+        return match self.binary {
+
+            false => match self.layout {
+                RecordLayout::Wide => writeln!(self.file, "{}", names.join(",")),
+                RecordLayout::Long => writeln!(self.file, "time_s,element,variable,value"),
+            },
+
+            true => {
+                self.file.write_all(MAGIC)?;
+                self.file.write_all(&[if self.layout == RecordLayout::Wide { 0 } else { 1 }])?;
+                self.file.write_all(&(names.len() as u32).to_le_bytes())?;
+                for name in names.iter() {
+                    self.file.write_all(&(name.len() as u16).to_le_bytes())?;
+                    self.file.write_all(name.as_bytes())?;
+                }
+                Ok(())
+            },
+        };
+    }
+
+    fn write_long_binary (&mut self, time: f64, element: i32, variable: usize, value: f64) -> io::Result<()> {
+        self.file.write_all(&time.to_le_bytes())?;
+        self.file.write_all(&element.to_le_bytes())?;
+        self.file.write_all(&(variable as u16).to_le_bytes())?;
+        self.file.write_all(&value.to_le_bytes())?;
+        return Ok(());
+    }
+}
+
+
+
+/// Values of GLOBAL_VARIABLES, in order.
+
+pub fn global_values (sample: &Sample) -> [f64; 9] {
+
+    let thrust  = sample.thrust.to_dvec3();
+    let com     = sample.center_of_mass.to_dvec3();
+
+    return [thrust.x, thrust.y, thrust.z, com.x, com.y, com.z, sample.spin_rate.value, sample.iterations as f64, sample.residual];
+}
+
+/// Values of ELEMENT_VARIABLES, in order.
+
+pub fn element_values (element: &ElementSample) -> [f64; 10] {
+
+    let position    = element.position.to_dvec3();
+    let velocity    = element.velocity.to_dvec3();
+    let force       = element.force.to_dvec3();
+
+    return [
+        position.x, position.y, position.z,
+        velocity.x, velocity.y, velocity.z,
+        force.x, force.y, force.z,
+        if element.is_deployed { 1.0 } else { 0.0 },
+    ];
+}
@@ -4,6 +4,7 @@ use uom::si::f64 as quantities;
 use uom::si::*;
 
 use uom::lib::marker::PhantomData;
+use serde::{ Deserialize, Serialize };
 
 // Maybe a constants.rs could contain these
 pub const M_PROTON:  quantities::Mass = quantities::Mass {dimension: PhantomData, units: PhantomData, value: 1.672e-27};
@@ -11,12 +12,64 @@ pub const Q_E:       quantities::ElectricCharge = quantities::ElectricCharge {di
 pub const EPSILON_0: quantities::ElectricPermittivity = quantities::ElectricPermittivity {dimension: PhantomData, units: PhantomData, value: 8.854e-12};
 
 
+/// How many constraint iterations run every timestep.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IterationMode {
+    Fixed,      // Always `iterations`
+    Residual,   // Until no segment is stretched more than `iteration_tolerance`, `iterations` at most
+}
+
+impl IterationMode {
+
+    pub const ALL: [IterationMode; 2] = [IterationMode::Fixed, IterationMode::Residual];
+
+    pub fn name (&self) -> &'static str {
+        match self {
+            IterationMode::Fixed    => "Fixed count",
+            IterationMode::Residual => "Until converged",
+        }
+    }
+}
+
+/// How the segment lengths are enforced after every timestep.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConstraintSolver {
+    GaussSeidel,    // One constraint at a time, sweeping from the reel outwards, for any constraint graph (see simulation/graph_solver.rs)
+    Tridiagonal,    // Every segment at once, Newton iterations on a tridiagonal system (see simulation/chain_solver.rs)
+    RedBlack,       // Even segments and then odd ones, each half split between threads (see simulation/red_black.rs)
+}
+
+impl ConstraintSolver {
+
+    pub const ALL: [ConstraintSolver; 3] = [ConstraintSolver::GaussSeidel, ConstraintSolver::Tridiagonal, ConstraintSolver::RedBlack];
+
+    pub fn name (&self) -> &'static str {
+        match self {
+            ConstraintSolver::GaussSeidel   => "Gauss–Seidel",
+            ConstraintSolver::Tridiagonal   => "Tridiagonal (direct)",
+            ConstraintSolver::RedBlack      => "Red–black (parallel)",
+        }
+    }
+}
+
 #[derive(Resource)]
 pub struct SimulationParameters {
-    pub iterations:         i32,    // Number of constraint iterations per timestep.
+    pub constraint_solver:  ConstraintSolver,
+    pub iterations:         i32,    // Number of constraint iterations (sweeps or Newton steps) per timestep, or the most of them in residual mode.
+    pub iteration_mode:     IterationMode,
+    pub iteration_tolerance: f64,   // Relative stretch of a segment below which residual mode stops iterating.
+    pub iterations_used:    i32,    // Constraint iterations of the last timestep.
+    pub threads:            usize,  // Threads of the red–black solver, 0 for every core.
     pub timestep:           f64,    // Timestep for the physics simulation, in seconds. Should be an uom quantity, right??
     pub timestep_s:         quantities::Time,   // Update everything to uom seconds later
     pub leftover_time:      f64,    // Unused time from the previous simulation loop.
+    pub simulated_time:     quantities::Time,   // Clock of the simulation, advanced one timestep at a time.
+    pub constraint_residual: f64,   // Largest relative stretch of a segment, or error of another constraint, after the last timestep.
+    pub slack_segments:     usize,  // Deployed segments shorter than their length after the last timestep.
     pub debug:              bool,   // Toggle for printing debug information to console.
     pub com_visibility:     bool,   // Toggle for showing/hiding the center of mass.
     pub axes_visibility:    bool,
@@ -26,10 +79,18 @@ pub struct SimulationParameters {
 impl Default for SimulationParameters {
     fn default() -> SimulationParameters {
         SimulationParameters {
+            constraint_solver:  ConstraintSolver::GaussSeidel,
             iterations:         60,
+            iteration_mode:     IterationMode::Fixed,
+            iteration_tolerance: 5.0e-4,
+            iterations_used:    0,
+            threads:            0,
             timestep:           1.0/60.0,   // In seconds (right?)
             timestep_s:         quantities::Time::new::<time::second>(1.0/60.0),
             leftover_time:      0.0,
+            simulated_time:     quantities::Time::new::<time::second>(0.0),
+            constraint_residual: 0.0,
+            slack_segments:     0,
             debug:              false,
             com_visibility:     false,
             axes_visibility:    true,
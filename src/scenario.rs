@@ -0,0 +1,1046 @@
+// Scenario files: every parameter of a run in a human-editable TOML file, with explicit units.
+//
+//      [spacecraft]
+//      wire_radius     = "10 um"
+//      wire_potential  = "20 kV"
+//
+//      [[timeline]]
+//      action          = "ramp_potential"
+//      start           = "10 s"
+//      target          = "20 kV"
+//      duration        = "60 s"
+//
+//      [[constraint]]
+//      kind            = "distance"
+//      between         = [10, 19]
+//      length          = "0.3 m"
+//
+//      [remote_unit]
+//      max_thrust      = "1 mN"
+//
+//      [reel]
+//      mass            = "20 g"
+//
+// Anything missing takes its default value. Errors come with the line of the file they refer to.
+
+use bevy::prelude::*;
+use bevy::math::DVec3;
+use serde::{ Deserialize, Serialize };
+use uom::si::f64 as quantities;
+use uom::si::length::meter;
+
+use crate::{ physics, recorder, resources, simulation, solar_wind, spacecraft };
+use physics::constraint_graph::Link;
+use physics::coulomb_drag::{ CoulombDragParameters, ForceModel };
+use physics::vector3::PositionVector;
+use simulation::convergence::{ ConvergenceMonitor, ConvergenceSettings };
+use simulation::timeline::{ Action, ScheduledAction, Timeline };
+use spacecraft::remote_unit::{ ThrustCommand, ThrusterType };
+
+pub mod units;
+
+pub struct ScenarioPlugin;
+
+impl Plugin for ScenarioPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(ScenarioState{..Default::default()})
+            .add_event::<ScenarioRequest>()
+            .add_systems(
+                Update,
+                handle_scenario_requests
+            )
+        ;
+    }
+}
+
+/// Scenario file shown in the GUI, and the result of the last load or save.
+
+#[derive(Resource)]
+pub struct ScenarioState {
+    pub path:   String,
+    pub status: String,
+}
+
+impl Default for ScenarioState {
+    fn default() -> Self {
+        ScenarioState {
+            path:   String::from("scenarios/default.toml"),
+            status: String::new(),
+        }
+    }
+}
+
+#[derive(Event)]
+pub enum ScenarioRequest {
+    Load,
+    Save,
+}
+
+/// Everything a scenario file sets.
+
+#[derive(Default)]
+pub struct Scenario {
+    pub spacecraft:     spacecraft::SpacecraftParameters,
+    pub solar_wind:     solar_wind::SolarWind,
+    pub simulation:     resources::SimulationParameters,
+    pub coulomb_drag:   CoulombDragParameters,
+    pub timeline:       Timeline,
+    pub convergence:    ConvergenceSettings,
+    pub recorder:       Option<recorder::RecorderSettings>,   // Record the run from the start
+}
+
+#[derive(Debug)]
+pub struct ScenarioError {
+    pub line:       Option<usize>,
+    pub message:    String,
+}
+
+impl std::fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.line {
+            Some(line)  => write!(f, "line {}: {}", line, self.message),
+            None        => write!(f, "{}", self.message),
+        }
+    }
+}
+
+
+
+// File layout. Quantities are strings with units, parsed with units::parse.
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct ScenarioFile {
+    #[serde(default)]
+    spacecraft:     SpacecraftSection,
+    #[serde(default)]
+    remote_unit:    RemoteUnitSection,
+    #[serde(default)]
+    reel:           ReelSection,
+    #[serde(default)]
+    solar_wind:     SolarWindSection,
+    #[serde(default)]
+    simulation:     SimulationSection,
+    #[serde(default)]
+    coulomb_drag:   CoulombDragSection,
+    #[serde(default)]
+    convergence:    ConvergenceSection,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    recorder:       Option<RecorderSection>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    timeline:       Vec<toml::Table>,   // TimelineEntry, read one by one to know which one is wrong
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    constraint:     Vec<toml::Table>,   // ConstraintEntry, the same way
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct SpacecraftSection {
+    rpm:                Option<String>,
+    spin_mode:          Option<spacecraft::SpinMode>,
+    rotation_axis:      Option<[f64; 3]>,
+    wire_length:        Option<String>,
+    wire_radius:        Option<String>,
+    wire_density:       Option<String>,
+    wire_potential:     Option<String>,
+    wire_resolution:    Option<String>,
+    tether_type:        Option<spacecraft::TetherType>,
+    filaments:          Option<usize>,
+    bond_spacing:       Option<String>,
+    loop_slack:         Option<f64>,
+    body_size:          Option<String>,
+    body_mass:          Option<String>,
+    esail_origin:       Option<[String; 3]>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct RemoteUnitSection {
+    dry_mass:           Option<String>,
+    propellant:         Option<String>,
+    auxiliary_tether:   Option<String>,
+    auxiliary_tether_density: Option<String>,
+    thruster:           Option<ThrusterType>,
+    max_thrust:         Option<String>,
+    damping_gain:       Option<String>,
+    command:            Option<ThrustCommand>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct ReelSection {
+    mass:               Option<String>,
+    radius:             Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+#[allow(non_snake_case)]
+struct SolarWindSection {
+    n_0:                Option<String>,
+    velocity:           Option<String>,
+    direction:          Option<[f64; 3]>,
+    T_e:                Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct SimulationSection {
+    constraint_solver:  Option<resources::ConstraintSolver>,
+    iterations:         Option<i32>,
+    iteration_mode:     Option<resources::IterationMode>,
+    iteration_tolerance: Option<f64>,
+    threads:            Option<usize>,
+    timestep:           Option<String>,
+    pixels_per_meter:   Option<i32>,
+    debug:              Option<bool>,
+    com_visibility:     Option<bool>,
+    axes_visibility:    Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+#[allow(non_snake_case)]
+struct CoulombDragSection {
+    model:              Option<ForceModel>,
+    K:                  Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct ConvergenceSection {
+    velocity_tolerance: Option<String>,
+    force_tolerance:    Option<f64>,
+    residual_tolerance: Option<f64>,
+    hold:               Option<String>,
+    stop:               Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct RecorderSection {
+    path:               Option<String>,
+    format:             Option<recorder::RecordFormat>,
+    layout:             Option<recorder::RecordLayout>,
+    decimation:         Option<u32>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case", deny_unknown_fields)]
+enum TimelineEntry {
+    Deploy          { start: String, length: String, speed: String },
+    RampPotential   { start: String, target: String, duration: String },
+    SolarWindSpeed  { start: String, velocity: String },
+    CutTether       { start: String, tether: usize },
+    BreakWire       { start: String, tether: usize, segment: usize, filament: usize },
+    Thrusters       { start: String, command: ThrustCommand },
+}
+
+// Elements are numbered from 0, the one that stays in the reel, to the remote unit. A distance left out
+// is the length of tether between the two elements.
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+enum ConstraintEntry {
+    Distance        { between: [usize; 2], length: Option<String> },
+    Anchor          { element: usize, position: [String; 3] },
+    Attachment      { element: usize, offset: [String; 3] },   // To the spacecraft body, from its center
+}
+
+
+
+/// Reads and validates a scenario file.
+
+pub fn load_scenario (path: &str) -> Result<Scenario, ScenarioError> {
+
+    let text = std::fs::read_to_string(path).map_err(|error| ScenarioError {
+        line:       None,
+        message:    format!("Can't read {}: {}", path, error),
+    })?;
+
+    return parse_scenario(&text);
+}
+
+
+
+/// Parses and validates the text of a scenario file.
+
+pub fn parse_scenario (text: &str) -> Result<Scenario, ScenarioError> {
+
+    let file: ScenarioFile = toml::from_str(text).map_err(|error| ScenarioError {
+        line:       error.span().map(|span| line_number(text, span.start)),
+        message:    error.message().to_string(),
+    })?;
+
+    let reader = FieldReader { text: text, occurrence: 0 };
+
+    // Spacecraft
+
+    let mut craft   = spacecraft::SpacecraftParameters{..Default::default()};
+    let section     = &file.spacecraft;
+    let name        = "spacecraft";
+
+    craft.rpm               = reader.quantity(name, "rpm",             &section.rpm,             units::FREQUENCY,                 craft.rpm)?;
+    craft.wire_length       = reader.quantity(name, "wire_length",     &section.wire_length,     units::LENGTH,                    craft.wire_length)?;
+    craft.wire_radius       = reader.quantity(name, "wire_radius",     &section.wire_radius,     units::LENGTH,                    craft.wire_radius)?;
+    craft.wire_density      = reader.quantity(name, "wire_density",    &section.wire_density,    units::MASS_DENSITY,              craft.wire_density)?;
+    craft.wire_potential    = reader.quantity(name, "wire_potential",  &section.wire_potential,  units::ELECTRIC_POTENTIAL,        craft.wire_potential)?;
+    craft.wire_resolution   = reader.quantity(name, "wire_resolution", &section.wire_resolution, units::LINEAR_NUMBER_DENSITY,     craft.wire_resolution)?;
+    craft.bond_spacing      = reader.quantity(name, "bond_spacing",    &section.bond_spacing,    units::LENGTH,                    craft.bond_spacing)?;
+    craft.body_size         = reader.quantity(name, "body_size",       &section.body_size,       units::LENGTH,                    craft.body_size)?;
+    craft.body_mass         = reader.quantity(name, "body_mass",       &section.body_mass,       units::MASS,                      craft.body_mass)?;
+    craft.spin_mode         = section.spin_mode.unwrap_or(craft.spin_mode);
+    craft.tether_type       = section.tether_type.unwrap_or(craft.tether_type);
+    craft.filaments         = section.filaments.unwrap_or(craft.filaments);
+    craft.loop_slack        = section.loop_slack.unwrap_or(craft.loop_slack);
+
+    if let Some(axis) = section.rotation_axis {
+        craft.rotation_axis = DVec3::from_array(axis);
+    }
+
+    if let Some(origin) = &section.esail_origin {
+        craft.esail_origin = PositionVector::from_dvec3(DVec3::from_array(reader.point(name, "esail_origin", origin)?));
+    }
+
+    reader.check(craft.rotation_axis.length() > 0.0,            name, "rotation_axis",   "must not be zero")?;
+    reader.check(craft.wire_length.value > 0.0,                 name, "wire_length",     "must be positive")?;
+    reader.check(craft.wire_radius.value > 0.0,                 name, "wire_radius",     "must be positive")?;
+    reader.check(craft.wire_density.value > 0.0,                name, "wire_density",    "must be positive")?;
+    reader.check(craft.wire_resolution.value > 0.0,             name, "wire_resolution", "must be positive")?;
+    reader.check(craft.number_of_esail_elements() >= 2,         name, "wire_resolution", "gives less than two elements for this wire_length")?;
+    reader.check(craft.filaments >= 1,                          name, "filaments",       "must be at least 1")?;
+    reader.check(craft.bond_spacing.value > 0.0,                name, "bond_spacing",    "must be positive")?;
+    reader.check(craft.loop_slack >= 0.0,                       name, "loop_slack",      "must not be negative")?;
+    reader.check(craft.body_size.value > 0.0,                   name, "body_size",       "must be positive")?;
+    reader.check(craft.body_mass.value > 0.0,                   name, "body_mass",       "must be positive")?;
+
+    craft.rotation_axis = craft.rotation_axis.normalize();
+
+    // Constraints beyond the segments of the tether, one [[constraint]] table each
+
+    let name        = "constraint";
+    let elements    = craft.number_of_esail_elements() as usize;
+
+    for (occurrence, table) in file.constraint.iter().enumerate() {
+
+        let reader = reader.entry(occurrence);
+
+        let entry: ConstraintEntry = table.clone().try_into().map_err(|error: toml::de::Error| ScenarioError {
+            line:       table_line(text, name, occurrence),
+            message:    format!("constraint {}: {}", occurrence + 1, error.message()),
+        })?;
+
+        let link = match &entry {
+
+            ConstraintEntry::Distance { between: [first, second], length } => {
+
+                let along_tether    = craft.segment_length() * first.abs_diff(*second) as f64;
+                let length          = reader.quantity(name, "length", length, units::LENGTH, along_tether)?;
+
+                reader.check(first != second,       name, "between", "must be two different elements")?;
+                reader.check(length.value > 0.0,    name, "length",  "must be positive")?;
+
+                Link::Distance { first: *first, second: *second, length: length.value }
+            },
+
+            ConstraintEntry::Anchor { element, position } => Link::Anchor {
+                element:    *element,
+                position:   reader.point(name, "position", position)?,
+            },
+
+            ConstraintEntry::Attachment { element, offset } => Link::Attachment {
+                element:    *element,
+                offset:     reader.point(name, "offset", offset)?,
+            },
+        };
+
+        let key = match &entry { ConstraintEntry::Distance { .. } => "between", _ => "element" };
+
+        reader.check(link.elements().iter().all(|element| *element < elements), name, key, "is past the last element of the tether")?;
+
+        craft.links.push(link);
+    }
+
+    // Remote unit
+
+    let unit    = &mut craft.remote_unit;
+    let section = &file.remote_unit;
+    let name    = "remote_unit";
+
+    unit.dry_mass           = reader.quantity(name, "dry_mass",         &section.dry_mass,         units::MASS,                unit.dry_mass)?;
+    unit.propellant         = reader.quantity(name, "propellant",       &section.propellant,       units::MASS,                unit.propellant)?;
+    unit.auxiliary_tether   = reader.quantity(name, "auxiliary_tether", &section.auxiliary_tether, units::LENGTH,              unit.auxiliary_tether)?;
+    unit.auxiliary_tether_density = reader.quantity(name, "auxiliary_tether_density", &section.auxiliary_tether_density, units::LINEAR_MASS_DENSITY, unit.auxiliary_tether_density)?;
+    unit.max_thrust         = reader.quantity(name, "max_thrust",       &section.max_thrust,       units::FORCE,               unit.max_thrust)?;
+    unit.damping_gain       = reader.quantity(name, "damping_gain",     &section.damping_gain,     units::FREQUENCY,           unit.damping_gain)?;
+    unit.thruster           = section.thruster.unwrap_or(unit.thruster);
+    unit.command            = section.command.unwrap_or(unit.command);
+
+    reader.check(unit.dry_mass.value >= 0.0,                    name, "dry_mass",                 "must not be negative")?;
+    reader.check(unit.propellant.value >= 0.0,                  name, "propellant",               "must not be negative")?;
+    reader.check(unit.auxiliary_tether.value >= 0.0,            name, "auxiliary_tether",         "must not be negative")?;
+    reader.check(unit.auxiliary_tether_density.value >= 0.0,    name, "auxiliary_tether_density", "must not be negative")?;
+    reader.check(unit.max_thrust.value >= 0.0,                  name, "max_thrust",               "must not be negative")?;
+    reader.check(unit.damping_gain.value >= 0.0,                name, "damping_gain",             "must not be negative")?;
+
+    // Reel
+
+    let reel    = &mut craft.reel;
+    let section = &file.reel;
+    let name    = "reel";
+
+    reel.mass   = reader.quantity(name, "mass",   &section.mass,   units::MASS,   reel.mass)?;
+    reel.radius = reader.quantity(name, "radius", &section.radius, units::LENGTH, reel.radius)?;
+
+    reader.check(reel.mass.value >= 0.0,    name, "mass",   "must not be negative")?;
+    reader.check(reel.radius.value >= 0.0,  name, "radius", "must not be negative")?;
+
+    // Solar wind
+
+    let mut wind    = solar_wind::SolarWind{..Default::default()};
+    let section     = &file.solar_wind;
+    let name        = "solar_wind";
+
+    wind.n_0        = reader.quantity(name, "n_0",      &section.n_0,      units::VOLUMETRIC_NUMBER_DENSITY,   wind.n_0)?;
+    wind.velocity   = reader.quantity(name, "velocity", &section.velocity, units::VELOCITY,                    wind.velocity)?;
+    wind.T_e        = reader.quantity(name, "T_e",      &section.T_e,      units::ENERGY,                      wind.T_e)?;
+
+    if let Some(direction) = section.direction {
+        wind.direction = DVec3::from_array(direction);
+    }
+
+    reader.check(wind.n_0.value > 0.0,              name, "n_0",       "must be positive")?;
+    reader.check(wind.velocity.value >= 0.0,        name, "velocity",  "must not be negative")?;
+    reader.check(wind.T_e.value > 0.0,              name, "T_e",       "must be positive")?;
+    reader.check(wind.direction.length() > 0.0,     name, "direction", "must not be zero")?;
+
+    wind.direction = wind.direction.normalize();
+
+    // Simulation
+
+    let mut simulation  = resources::SimulationParameters{..Default::default()};
+    let section         = &file.simulation;
+    let name            = "simulation";
+
+    simulation.timestep_s       = reader.quantity(name, "timestep", &section.timestep, units::TIME, simulation.timestep_s)?;
+    simulation.timestep         = simulation.timestep_s.value;
+    simulation.constraint_solver = section.constraint_solver.unwrap_or(simulation.constraint_solver);
+    simulation.iterations       = section.iterations.unwrap_or(simulation.iterations);
+    simulation.iteration_mode   = section.iteration_mode.unwrap_or(simulation.iteration_mode);
+    simulation.iteration_tolerance = section.iteration_tolerance.unwrap_or(simulation.iteration_tolerance);
+    simulation.threads          = section.threads.unwrap_or(simulation.threads);
+    simulation.pixels_per_meter = section.pixels_per_meter.unwrap_or(simulation.pixels_per_meter);
+    simulation.debug            = section.debug.unwrap_or(simulation.debug);
+    simulation.com_visibility   = section.com_visibility.unwrap_or(simulation.com_visibility);
+    simulation.axes_visibility  = section.axes_visibility.unwrap_or(simulation.axes_visibility);
+
+    reader.check(simulation.timestep > 0.0,             name, "timestep",         "must be positive")?;
+    reader.check(simulation.iterations >= 1,            name, "iterations",       "must be at least 1")?;
+    reader.check(simulation.iteration_tolerance > 0.0,  name, "iteration_tolerance", "must be positive")?;
+    reader.check(simulation.pixels_per_meter >= 1,      name, "pixels_per_meter", "must be at least 1")?;
+
+    // Coulomb drag
+
+    let mut coulomb_drag    = CoulombDragParameters{..Default::default()};
+    let section             = &file.coulomb_drag;
+
+    coulomb_drag.model  = section.model.unwrap_or(coulomb_drag.model);
+    coulomb_drag.K      = section.K.unwrap_or(coulomb_drag.K);
+
+    reader.check(coulomb_drag.K.is_finite() && coulomb_drag.K >= 0.0, "coulomb_drag", "K", "must not be negative")?;
+
+    // Convergence
+
+    let mut convergence = ConvergenceSettings{..Default::default()};
+    let section         = &file.convergence;
+    let name            = "convergence";
+
+    convergence.velocity_tolerance  = reader.quantity(name, "velocity_tolerance", &section.velocity_tolerance, units::VELOCITY, convergence.velocity_tolerance)?;
+    convergence.hold                = reader.quantity(name, "hold",               &section.hold,               units::TIME,     convergence.hold)?;
+    convergence.force_tolerance     = section.force_tolerance.unwrap_or(convergence.force_tolerance);
+    convergence.residual_tolerance  = section.residual_tolerance.unwrap_or(convergence.residual_tolerance);
+    convergence.stop                = section.stop.unwrap_or(convergence.stop);
+
+    reader.check(convergence.velocity_tolerance.value > 0.0,   name, "velocity_tolerance", "must be positive")?;
+    reader.check(convergence.force_tolerance > 0.0,            name, "force_tolerance",    "must be positive")?;
+    reader.check(convergence.residual_tolerance > 0.0,         name, "residual_tolerance", "must be positive")?;
+    reader.check(convergence.hold.value >= 0.0,                name, "hold",               "must not be negative")?;
+
+    // Recorder, only if the section is there
+
+    let recorder_settings = match &file.recorder {
+        Some(section) => {
+            let defaults = recorder::RecorderSettings{..Default::default()};
+            let settings = recorder::RecorderSettings {
+                path:       section.path.clone().unwrap_or(defaults.path),
+                format:     section.format.unwrap_or(defaults.format),
+                layout:     section.layout.unwrap_or(defaults.layout),
+                decimation: section.decimation.unwrap_or(defaults.decimation),
+            };
+            reader.check(settings.decimation >= 1, "recorder", "decimation", "must be at least 1")?;
+            Some(settings)
+        },
+        None => None,
+    };
+
+    // Timeline, one [[timeline]] table per action
+
+    let mut timeline    = Timeline{..Default::default()};
+    let name            = "timeline";
+
+    for (occurrence, table) in file.timeline.iter().enumerate() {
+
+        let reader = reader.entry(occurrence);
+
+        let entry: TimelineEntry = table.clone().try_into().map_err(|error: toml::de::Error| ScenarioError {
+            line:       table_line(text, name, occurrence),
+            message:    format!("timeline action {}: {}", occurrence + 1, error.message()),
+        })?;
+
+        let scheduled = match &entry {
+
+            TimelineEntry::Deploy { start, length, speed } => {
+
+                let length  = reader.required(name, "length", length, units::LENGTH)?;
+                let speed   = reader.required(name, "speed", speed, units::VELOCITY)?;
+
+                reader.check(length.value > 0.0,    name, "length", "must be positive")?;
+                reader.check(speed.value > 0.0,     name, "speed",  "must be positive")?;
+
+                ScheduledAction::new(reader.required(name, "start", start, units::TIME)?,
+                                     Action::Deploy { length: length, speed: speed, deployed: 0 })
+            },
+
+            TimelineEntry::RampPotential { start, target, duration } => {
+
+                let target      = reader.required(name, "target", target, units::ELECTRIC_POTENTIAL)?;
+                let duration    = reader.required(name, "duration", duration, units::TIME)?;
+
+                reader.check(duration.value >= 0.0, name, "duration", "must not be negative")?;
+
+                ScheduledAction::new(reader.required(name, "start", start, units::TIME)?,
+                                     Action::RampPotential { target: target, duration: duration, initial: None })
+            },
+
+            TimelineEntry::SolarWindSpeed { start, velocity } => {
+
+                let velocity = reader.required(name, "velocity", velocity, units::VELOCITY)?;
+
+                reader.check(velocity.value >= 0.0, name, "velocity", "must not be negative")?;
+
+                ScheduledAction::new(reader.required(name, "start", start, units::TIME)?,
+                                     Action::SolarWindSpeed { velocity: velocity })
+            },
+
+            TimelineEntry::CutTether { start, tether } => {
+
+                reader.check(*tether == 0, name, "tether", "must be 0, the only tether")?;
+
+                ScheduledAction::new(reader.required(name, "start", start, units::TIME)?,
+                                     Action::CutTether { tether: *tether })
+            },
+
+            TimelineEntry::BreakWire { start, tether, segment, filament } => {
+
+                reader.check(*tether == 0,                              name, "tether",   "must be 0, the only tether")?;
+                reader.check(*segment >= 1 && *segment < elements,      name, "segment",  "must be between 1 and the last element of the tether")?;
+                reader.check(*filament <= craft.loop_filaments(),       name, "filament", "must be 0 (the base wire) or a loop wire of the tether")?;
+
+                ScheduledAction::new(reader.required(name, "start", start, units::TIME)?,
+                                     Action::BreakWire { tether: *tether, segment: *segment, filament: *filament })
+            },
+
+            TimelineEntry::Thrusters { start, command } => {
+
+                ScheduledAction::new(reader.required(name, "start", start, units::TIME)?,
+                                     Action::Thrusters { command: *command })
+            },
+        };
+
+        reader.check(scheduled.start.value >= 0.0, name, "start", "must not be negative")?;
+
+        timeline.actions.push(scheduled);
+    }
+
+    return Ok(Scenario {
+        spacecraft:     craft,
+        solar_wind:     wind,
+        simulation:     simulation,
+        coulomb_drag:   coulomb_drag,
+        timeline:       timeline,
+        convergence:    convergence,
+        recorder:       recorder_settings,
+    });
+}
+
+
+
+/// Writes the given parameters as a scenario file.
+
+pub fn scenario_to_string (
+    craft:          &spacecraft::SpacecraftParameters,
+    wind:           &solar_wind::SolarWind,
+    simulation:     &resources::SimulationParameters,
+    coulomb_drag:   &CoulombDragParameters,
+    timeline:       &Timeline,
+    convergence:    &ConvergenceSettings,
+    recorder:       Option<&recorder::RecorderSettings>,
+    ) -> String {
+
+    let file = ScenarioFile {
+
+        spacecraft: SpacecraftSection {
+            rpm:                Some(units::format(craft.rpm,              units::FREQUENCY,              "rpm")),
+            spin_mode:          Some(craft.spin_mode),
+            rotation_axis:      Some(craft.rotation_axis.to_array()),
+            wire_length:        Some(units::format(craft.wire_length,      units::LENGTH,                 "m")),
+            wire_radius:        Some(units::format(craft.wire_radius,      units::LENGTH,                 "um")),
+            wire_density:       Some(units::format(craft.wire_density,     units::MASS_DENSITY,           "g/cm3")),
+            wire_potential:     Some(units::format(craft.wire_potential,   units::ELECTRIC_POTENTIAL,     "kV")),
+            wire_resolution:    Some(units::format(craft.wire_resolution,  units::LINEAR_NUMBER_DENSITY,  "/m")),
+            tether_type:        Some(craft.tether_type),
+            filaments:          Some(craft.filaments),
+            bond_spacing:       Some(units::format(craft.bond_spacing,     units::LENGTH,                 "m")),
+            loop_slack:         Some(craft.loop_slack),
+            body_size:          Some(units::format(craft.body_size,        units::LENGTH,                 "m")),
+            body_mass:          Some(units::format(craft.body_mass,        units::MASS,                   "kg")),
+            esail_origin:       Some(craft.esail_origin.0.map(|coordinate| units::format(coordinate, units::LENGTH, "m"))),
+        },
+
+        remote_unit: RemoteUnitSection {
+            dry_mass:           Some(units::format(craft.remote_unit.dry_mass,          units::MASS,    "g")),
+            propellant:         Some(units::format(craft.remote_unit.propellant,        units::MASS,    "g")),
+            auxiliary_tether:   Some(units::format(craft.remote_unit.auxiliary_tether,  units::LENGTH,  "m")),
+            auxiliary_tether_density: Some(units::format(craft.remote_unit.auxiliary_tether_density, units::LINEAR_MASS_DENSITY, "g/km")),
+            thruster:           Some(craft.remote_unit.thruster),
+            max_thrust:         Some(units::format(craft.remote_unit.max_thrust,        units::FORCE,   "mN")),
+            damping_gain:       Some(units::format(craft.remote_unit.damping_gain,      units::FREQUENCY, "/s")),
+            command:            Some(craft.remote_unit.command),
+        },
+
+        reel: ReelSection {
+            mass:               Some(units::format(craft.reel.mass,     units::MASS,    "g")),
+            radius:             Some(units::format(craft.reel.radius,   units::LENGTH,  "cm")),
+        },
+
+        solar_wind: SolarWindSection {
+            n_0:                Some(units::format(wind.n_0,       units::VOLUMETRIC_NUMBER_DENSITY,  "/cm3")),
+            velocity:           Some(units::format(wind.velocity,  units::VELOCITY,                   "km/s")),
+            direction:          Some(wind.direction.to_array()),
+            T_e:                Some(units::format(wind.T_e,       units::ENERGY,                     "eV")),
+        },
+
+        simulation: SimulationSection {
+            constraint_solver:  Some(simulation.constraint_solver),
+            iterations:         Some(simulation.iterations),
+            iteration_mode:     Some(simulation.iteration_mode),
+            iteration_tolerance: Some(simulation.iteration_tolerance),
+            threads:            Some(simulation.threads),
+            timestep:           Some(units::format(simulation.timestep_s, units::TIME, "s")),
+            pixels_per_meter:   Some(simulation.pixels_per_meter),
+            debug:              Some(simulation.debug),
+            com_visibility:     Some(simulation.com_visibility),
+            axes_visibility:    Some(simulation.axes_visibility),
+        },
+
+        coulomb_drag: CoulombDragSection {
+            model:              Some(coulomb_drag.model),
+            K:                  Some(coulomb_drag.K),
+        },
+
+        convergence: ConvergenceSection {
+            velocity_tolerance: Some(units::format(convergence.velocity_tolerance, units::VELOCITY, "mm/s")),
+            force_tolerance:    Some(convergence.force_tolerance),
+            residual_tolerance: Some(convergence.residual_tolerance),
+            hold:               Some(units::format(convergence.hold, units::TIME, "s")),
+            stop:               Some(convergence.stop),
+        },
+
+        recorder: recorder.map(|settings| RecorderSection {
+            path:               Some(settings.path.clone()),
+            format:             Some(settings.format),
+            layout:             Some(settings.layout),
+            decimation:         Some(settings.decimation),
+        }),
+
+        timeline: timeline.actions.iter().map(|scheduled| {
+
+            let start = units::format(scheduled.start, units::TIME, "s");
+
+            let entry = match &scheduled.action {
+                Action::Deploy { length, speed, .. } => TimelineEntry::Deploy {
+                    start:      start,
+                    length:     units::format(*length, units::LENGTH, "m"),
+                    speed:      units::format(*speed, units::VELOCITY, "m/s"),
+                },
+                Action::RampPotential { target, duration, .. } => TimelineEntry::RampPotential {
+                    start:      start,
+                    target:     units::format(*target, units::ELECTRIC_POTENTIAL, "kV"),
+                    duration:   units::format(*duration, units::TIME, "s"),
+                },
+                Action::SolarWindSpeed { velocity } => TimelineEntry::SolarWindSpeed {
+                    start:      start,
+                    velocity:   units::format(*velocity, units::VELOCITY, "km/s"),
+                },
+                Action::CutTether { tether } => TimelineEntry::CutTether {
+                    start:      start,
+                    tether:     *tether,
+                },
+                Action::BreakWire { tether, segment, filament } => TimelineEntry::BreakWire {
+                    start:      start,
+                    tether:     *tether,
+                    segment:    *segment,
+                    filament:   *filament,
+                },
+                Action::Thrusters { command } => TimelineEntry::Thrusters {
+                    start:      start,
+                    command:    *command,
+                },
+            };
+
+            toml::Table::try_from(entry).expect("Timeline action can't be serialised")
+        }).collect(),
+
+        constraint: craft.links.iter().map(|link| {
+
+            let point = |coordinates: &[f64; 3]| coordinates.map(|coordinate| units::format(quantities::Length::new::<meter>(coordinate), units::LENGTH, "m"));
+
+            let entry = match link {
+                Link::Distance { first, second, length } => ConstraintEntry::Distance {
+                    between:    [*first, *second],
+                    length:     Some(units::format(quantities::Length::new::<meter>(*length), units::LENGTH, "m")),
+                },
+                Link::Anchor { element, position } => ConstraintEntry::Anchor {
+                    element:    *element,
+                    position:   point(position),
+                },
+                Link::Attachment { element, offset } => ConstraintEntry::Attachment {
+                    element:    *element,
+                    offset:     point(offset),
+                },
+            };
+
+            toml::Table::try_from(entry).expect("Constraint can't be serialised")
+        }).collect(),
+    };
+
+    return toml::to_string_pretty(&file).expect("Scenario can't be serialised");
+}
+
+
+
+/// Loads or saves the scenario file when the GUI asks for it. Loading rebuilds the E-sail, since
+/// its number of elements may have changed, and starts the clock of the simulation again.
+
+fn handle_scenario_requests (
+    mut commands:           Commands,
+    mut meshes:             ResMut<Assets<Mesh>>,
+    mut materials:          ResMut<Assets<StandardMaterial>>,
+    mut requests:           EventReader<ScenarioRequest>,
+    mut state:              ResMut<ScenarioState>,
+    mut craft_params:       ResMut<spacecraft::SpacecraftParameters>,
+    mut solar_wind:         ResMut<solar_wind::SolarWind>,
+    mut sim_params:         ResMut<resources::SimulationParameters>,
+    mut drag_params:        ResMut<CoulombDragParameters>,
+    mut timeline:           ResMut<Timeline>,
+    mut monitor:            ResMut<ConvergenceMonitor>,
+    mut recorder:           ResMut<recorder::Recorder>,
+    esail_query:            Query<(Entity, &spacecraft::esail::ESail)>,
+    ) {
+
+    for request in requests.read() {
+
+        match request {
+
+            ScenarioRequest::Load => {
+
+                match load_scenario(&state.path) {
+
+                    Ok(scenario) => {
+
+                        *craft_params   = scenario.spacecraft;
+                        *solar_wind     = scenario.solar_wind;
+                        *sim_params     = scenario.simulation;
+                        *drag_params    = scenario.coulomb_drag;
+                        *timeline       = scenario.timeline;
+                        *monitor        = ConvergenceMonitor::new(scenario.convergence);
+
+                        if let Some(settings) = scenario.recorder {
+                            let run_parameters = scenario_to_string(&craft_params, &solar_wind, &sim_params, &drag_params,
+                                                                    &timeline, &monitor.settings, Some(&settings));
+                            recorder.settings = settings;
+                            recorder.start(&run_parameters);
+                        }
+
+                        for (esail_entity, esail) in esail_query.iter() {
+                            spacecraft::esail::despawn_esail(&mut commands, esail_entity, esail);
+                        }
+
+                        spacecraft::esail::build_esail(&mut commands, &mut meshes, &mut materials, &craft_params);
+
+                        state.status = format!("Loaded {}", state.path);
+                    },
+
+                    Err(error) => {
+                        state.status = format!("{}: {}", state.path, error);
+                    },
+                }
+            },
+
+            ScenarioRequest::Save => {
+
+                let text = scenario_to_string(&craft_params, &solar_wind, &sim_params, &drag_params, &timeline, &monitor.settings,
+                                              if recorder.is_recording() { Some(&recorder.settings) } else { None });
+
+                state.status = match std::fs::write(&state.path, text) {
+                    Ok(())      => format!("Saved {}", state.path),
+                    Err(error)  => format!("Can't write {}: {}", state.path, error),
+                };
+            },
+        }
+    }
+}
+
+
+
+/// Reads fields of the file, pointing at their line when something is wrong.
+
+struct FieldReader<'a> {
+    text:       &'a str,
+    occurrence: usize,  // Which table, for arrays of tables like [[timeline]]
+}
+
+impl<'a> FieldReader<'a> {
+
+    fn entry (&self, occurrence: usize) -> Self {
+        return FieldReader { text: self.text, occurrence: occurrence };
+    }
+
+    fn quantity<D: uom::si::Dimension + ?Sized> (
+        &self,
+        section:    &str,
+        key:        &str,
+        value:      &Option<String>,
+        table:      units::UnitTable,
+        default:    physics::vector3::Quantity<D>,
+        ) -> Result<physics::vector3::Quantity<D>, ScenarioError> {
+
+        return match value {
+            Some(text)  => self.required(section, key, text, table),
+            None        => Ok(default),
+        };
+    }
+
+    fn required<D: uom::si::Dimension + ?Sized> (
+        &self,
+        section:    &str,
+        key:        &str,
+        text:       &str,
+        table:      units::UnitTable,
+        ) -> Result<physics::vector3::Quantity<D>, ScenarioError> {
+
+        return units::parse(text, table).map_err(|message| self.error(section, key, message));
+    }
+
+    /// Three lengths, in meters.
+
+    fn point (&self, section: &str, key: &str, coordinates: &[String; 3]) -> Result<[f64; 3], ScenarioError> {
+
+        let mut point = [0.0; 3];
+
+        for (coordinate, text) in point.iter_mut().zip(coordinates) {
+            let length: quantities::Length = self.required(section, key, text, units::LENGTH)?;
+            *coordinate = length.get::<meter>();
+        }
+
+        return Ok(point);
+    }
+
+    fn check (&self, condition: bool, section: &str, key: &str, message: &str) -> Result<(), ScenarioError> {
+
+        if condition {
+            return Ok(());
+        }
+
+        return Err(self.error(section, key, format!("{} {}", key, message)));
+    }
+
+    fn error (&self, section: &str, key: &str, message: String) -> ScenarioError {
+        return ScenarioError {
+            line:       key_line(self.text, section, self.occurrence, key),
+            message:    message,
+        };
+    }
+}
+
+
+
+/// Line (starting at 1) of a byte offset in the text.
+
+fn line_number (text: &str, offset: usize) -> usize {
+    return text[..offset.min(text.len())].matches('\n').count() + 1;
+}
+
+
+
+/// Line (starting at 1) of the header of a [[section]], for arrays of tables.
+
+fn table_line (text: &str, section: &str, occurrence: usize) -> Option<usize> {
+
+    return text.lines()
+               .enumerate()
+               .filter(|(_, line)| line.trim().trim_matches(|c| c == '[' || c == ']').trim() == section && line.trim().starts_with("[["))
+               .nth(occurrence)
+               .map(|(index, _)| index + 1);
+}
+
+
+
+/// Line (starting at 1) where a key is set inside a [section], if it's there at all. For arrays of
+/// tables, occurrence says which [[section]] to look in.
+
+fn key_line (text: &str, section: &str, occurrence: usize, key: &str) -> Option<usize> {
+
+    let mut in_section  = false;
+    let mut seen        = 0;
+
+    for (index, line) in text.lines().enumerate() {
+
+        let line = line.trim();
+
+        if line.starts_with('[') {
+            in_section = false;
+            if line.trim_matches(|c| c == '[' || c == ']').trim() == section {
+                in_section = seen == occurrence;
+                seen += 1;
+            }
+            continue;
+        }
+
+        if in_section && line.split('=').next().map(str::trim) == Some(key) {
+            return Some(index + 1);
+        }
+    }
+
+    return None;
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    /// Writes a scenario as a file would have it.
+
+    fn to_string (scenario: &Scenario) -> String {
+        return scenario_to_string(&scenario.spacecraft, &scenario.solar_wind, &scenario.simulation, &scenario.coulomb_drag,
+                                  &scenario.timeline, &scenario.convergence, scenario.recorder.as_ref());
+    }
+
+    /// Line of the error of a scenario that doesn't parse.
+
+    fn error_line (text: &str) -> Option<usize> {
+        return parse_scenario(text).err().expect("Invalid scenario parsed").line;
+    }
+
+    #[test]
+    fn round_trip () {
+
+        let text = r#"
+            [spacecraft]
+            rpm             = "3.3 rpm"
+            spin_mode       = "held"
+            wire_length     = "2 m"
+            wire_radius     = "12.5 um"
+            wire_potential  = "17 kV"
+            tether_type     = "heytether"
+
+            [remote_unit]
+            dry_mass        = "45 g"
+            command         = "spin_up"
+
+            [solar_wind]
+            velocity        = "470 km/s"
+
+            [coulomb_drag]
+            model           = "toivanen_janhunen"
+            K               = 2.5
+
+            [recorder]
+            path            = "run.csv"
+
+            [[timeline]]
+            action          = "deploy"
+            start           = "0 s"
+            length          = "1.5 m"
+            speed           = "0.1 m/s"
+
+            [[timeline]]
+            action          = "break_wire"
+            start           = "20 s"
+            tether          = 0
+            segment         = 5
+            filament        = 2
+
+            [[timeline]]
+            action          = "cut_tether"
+            start           = "1 min"
+            tether          = 0
+
+            [[constraint]]
+            kind            = "distance"
+            between         = [10, 19]
+            length          = "0.3 m"
+
+            [[constraint]]
+            kind            = "anchor"
+            element         = 39
+            position        = ["2 m", "0 m", "0.5 m"]
+        "#;
+
+        let scenario    = parse_scenario(text).expect("Invalid scenario");
+        let written     = to_string(&scenario);
+        let read        = parse_scenario(&written).expect("Written scenario doesn't parse");
+
+        // Writing what was read gives the same file, so nothing was lost on the way
+        assert_eq!(to_string(&read), written);
+
+        assert_eq!(read.spacecraft.wire_radius, scenario.spacecraft.wire_radius);
+        assert_eq!(read.spacecraft.rpm, scenario.spacecraft.rpm);
+        assert_eq!(read.spacecraft.tether_type, spacecraft::TetherType::Heytether);
+        assert_eq!(read.spacecraft.links.len(), 2);
+        assert_eq!(read.coulomb_drag.model, ForceModel::ToivanenJanhunen);
+        assert_eq!(read.timeline.actions.len(), 3);
+        assert_eq!(read.timeline.actions[2].start, scenario.timeline.actions[2].start);
+        assert!(read.recorder.is_some());
+    }
+
+    #[test]
+    fn default_scenario_file_round_trips () {
+
+        let scenario    = load_scenario("scenarios/default.toml").expect("Invalid default scenario");
+        let written     = to_string(&scenario);
+
+        assert_eq!(to_string(&parse_scenario(&written).expect("Written scenario doesn't parse")), written);
+    }
+
+    #[test]
+    fn errors_point_at_their_line () {
+
+        // Unknown keys, in a section and in an array of tables
+        assert_eq!(error_line("[spacecraft]\nwire_length = \"1 m\"\nwire_lenght = \"1 m\"\n"), Some(3));
+        assert_eq!(error_line("[[timeline]]\naction = \"deploy\"\nstart = \"0 s\"\nlength = \"1 m\"\nspeed = \"1 m/s\"\n\n[[timeline]]\naction = \"cut_tether\"\nstart = \"1 s\"\ntether = 0\nspeed = \"1 m/s\"\n"), Some(7));
+
+        // Bad units and values
+        assert_eq!(error_line("[spacecraft]\nrpm = \"5 rpm\"\nwire_radius = \"10 uf\"\n"), Some(3));
+        assert_eq!(error_line("\n[solar_wind]\nvelocity = \"fast\"\n"), Some(3));
+        assert_eq!(error_line("[spacecraft]\nwire_length = \"-1 m\"\n"), Some(2));
+
+        // Only tether 0 can be cut or broken
+        assert_eq!(error_line("[[timeline]]\naction = \"cut_tether\"\nstart = \"1 s\"\ntether = 1\n"), Some(4));
+        assert_eq!(error_line("[[timeline]]\naction = \"break_wire\"\nstart = \"1 s\"\ntether = 2\nsegment = 3\nfilament = 0\n"), Some(4));
+    }
+}
@@ -0,0 +1,152 @@
+// Quantities written with explicit units in scenario files, like "10 um" or "20 kV".
+// Every table maps unit symbols to their factor to SI base units.
+
+use uom::si::{ Dimension, Quantity, SI };
+use uom::lib::marker::PhantomData;
+
+pub type UnitTable = &'static [(&'static str, f64)];
+
+pub const LENGTH: UnitTable = &[
+    ("m", 1.0), ("km", 1.0e3), ("cm", 1.0e-2), ("mm", 1.0e-3), ("um", 1.0e-6), ("µm", 1.0e-6),
+];
+
+pub const VELOCITY: UnitTable = &[
+    ("m/s", 1.0), ("km/s", 1.0e3), ("mm/s", 1.0e-3),
+];
+
+pub const FREQUENCY: UnitTable = &[
+    ("Hz", 1.0), ("rpm", 1.0 / 60.0), ("/s", 1.0),
+];
+
+pub const MASS: UnitTable = &[
+    ("kg", 1.0), ("g", 1.0e-3), ("mg", 1.0e-6),
+];
+
+pub const LINEAR_MASS_DENSITY: UnitTable = &[
+    ("kg/m", 1.0), ("g/m", 1.0e-3), ("g/km", 1.0e-6),
+];
+
+pub const FORCE: UnitTable = &[
+    ("N", 1.0), ("mN", 1.0e-3), ("uN", 1.0e-6), ("µN", 1.0e-6),
+];
+
+pub const MASS_DENSITY: UnitTable = &[
+    ("kg/m3", 1.0), ("g/cm3", 1.0e3),
+];
+
+pub const ELECTRIC_POTENTIAL: UnitTable = &[
+    ("V", 1.0), ("kV", 1.0e3),
+];
+
+pub const LINEAR_NUMBER_DENSITY: UnitTable = &[
+    ("/m", 1.0), ("/cm", 1.0e2),
+];
+
+pub const VOLUMETRIC_NUMBER_DENSITY: UnitTable = &[
+    ("/m3", 1.0), ("/cm3", 1.0e6),
+];
+
+pub const ENERGY: UnitTable = &[
+    ("J", 1.0), ("eV", 1.602_176_634e-19),
+];
+
+pub const TIME: UnitTable = &[
+    ("s", 1.0), ("ms", 1.0e-3), ("min", 60.0), ("h", 3600.0),
+];
+
+
+
+/// Parses "<number> <unit>" into a quantity. The unit must be in the table.
+
+pub fn parse<D: Dimension + ?Sized> (
+    text:   &str,
+    table:  UnitTable,
+    ) -> Result<Quantity<D, SI<f64>, f64>, String> {
+
+    let text = text.trim();
+
+    // The number ends at the first character that can't be part of it
+    let split = text.find(|c: char| !(c.is_ascii_digit() || "+-.eE".contains(c))).unwrap_or(text.len());
+
+    // "1e" followed by a unit starting with e (like "eV") isn't an exponent
+    let split = if text[..split].ends_with(['e', 'E']) { split - 1 } else { split };
+
+    let (number, unit) = text.split_at(split);
+
+    let value: f64 = number.parse()
+                           .map_err(|_| format!("\"{}\" doesn't start with a number", text))?;
+
+    let unit = unit.trim();
+
+    let factor = table.iter()
+                      .find(|(symbol, _)| *symbol == unit)
+                      .map(|(_, factor)| *factor)
+                      .ok_or_else(|| format!("unknown unit \"{}\" in \"{}\", expected one of: {}",
+                                             unit, text, table.iter().map(|(symbol, _)| *symbol).collect::<Vec<_>>().join(", ")))?;
+
+    if !value.is_finite() {
+        return Err(format!("\"{}\" is not a finite number", text));
+    }
+
+    return Ok(Quantity { dimension: PhantomData, units: PhantomData, value: value * factor });
+}
+
+
+
+/// Writes a quantity in the given unit of the table, like "10 um".
+
+pub fn format<D: Dimension + ?Sized> (
+    quantity:   Quantity<D, SI<f64>, f64>,
+    table:      UnitTable,
+    unit:       &str,
+    ) -> String {
+
+    let factor = table.iter()
+                      .find(|(symbol, _)| *symbol == unit)
+                      .map(|(_, factor)| *factor)
+                      .expect("Unit not in its table");
+
+    // Round away the last digits, so that 10 um doesn't come out as 9.999999999999999 um
+    let value: f64 = format!("{:.12e}", quantity.value / factor).parse().expect("Formatted number doesn't parse");
+
+    return format!("{} {}", value, unit);
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use uom::si::f64 as quantities;
+    use uom::si::length::meter;
+
+    #[test]
+    fn parse_with_units () {
+
+        let length: quantities::Length = parse(" 10 um ", LENGTH).expect("Doesn't parse");
+        assert_eq!(length.get::<meter>(), 10.0 * 1.0e-6);
+
+        let energy: quantities::Energy = parse("1e1eV", ENERGY).expect("Doesn't parse");
+        assert_eq!(energy.value, 10.0 * 1.602_176_634e-19);
+
+        assert!(parse::<uom::si::length::Dimension>("10 furlongs", LENGTH).is_err());
+        assert!(parse::<uom::si::length::Dimension>("m", LENGTH).is_err());
+        assert!(parse::<uom::si::length::Dimension>("1e999 m", LENGTH).is_err());
+    }
+
+    #[test]
+    fn format_keeps_the_digits_that_matter () {
+
+        // Rounded past the floating point noise of the unit conversion
+        assert_eq!(format(quantities::Length::new::<meter>(10.0e-6), LENGTH, "um"), "10 um");
+        assert_eq!(format(quantities::Length::new::<meter>(0.3), LENGTH, "cm"), "30 cm");
+
+        // and no further: 13 significant digits are kept
+        let third = quantities::Length::new::<meter>(1.0 / 3.0);
+        assert_eq!(format(third, LENGTH, "m"), "0.3333333333333 m");
+
+        let parsed: quantities::Length = parse(&format(third, LENGTH, "mm"), LENGTH).expect("Doesn't parse");
+        assert!((parsed.value / third.value - 1.0).abs() < 1.0e-12);
+    }
+}
@@ -1,6 +1,14 @@
 // Move the simulation plugin and the resource. Leave physics.rs only with use position_vector, use etc
 use bevy::prelude::*;
 
+use crate::{ recorder, scenario, spacecraft, spectrum };
+
+mod chain_solver;
+pub mod convergence;
+mod graph_solver;
+pub mod red_black;
+pub mod spin;
+pub mod timeline;
 mod verlet_simulation;
 //mod new_verlet_simulation;
 mod voltage;
@@ -22,13 +30,50 @@ pub struct SimulationPlugin;
 impl Plugin for SimulationPlugin {
     fn build(&self, app: &mut App) {
         app
+            .insert_resource(timeline::Timeline{..Default::default()})
+            .insert_resource(convergence::ConvergenceMonitor::default())
+            .insert_resource(spin::SpinState::default())
+            .add_event::<convergence::SteadyState>()
             .add_systems(
                 Update, (
                     verlet_simulation::verlet_simulation,
                     //new_verlet_simulation::new_verlet_simulation,
-                    voltage::update_esail_voltage
+                    voltage::update_esail_voltage,
+                    convergence::monitor_convergence.after(verlet_simulation::verlet_simulation),
                 )
             )
         ;
     }
 }
+
+
+
+/// An App that runs the simulation of a scenario without window or graphics, one timestep per
+/// update. For batch runs: the caller calls update() until it has seen enough.
+
+pub fn headless_app (scenario: scenario::Scenario) -> App {
+
+    let timestep = std::time::Duration::from_secs_f64(scenario.simulation.timestep);
+
+    let mut app = App::new();
+
+    app
+        .add_plugins(MinimalPlugins)
+        .add_plugins(AssetPlugin::default())   // The E-sail elements still get meshes, nobody draws them
+        .init_asset::<Mesh>()
+        .init_asset::<StandardMaterial>()
+        .add_plugins(SimulationPlugin)
+        .insert_resource(scenario.spacecraft)
+        .insert_resource(scenario.solar_wind)
+        .insert_resource(scenario.simulation)
+        .insert_resource(scenario.coulomb_drag)
+        .insert_resource(scenario.timeline)
+        .insert_resource(convergence::ConvergenceMonitor::new(scenario.convergence))
+        .insert_resource(recorder::Recorder::default())
+        .insert_resource(spectrum::TipHistory::default())
+        .insert_resource(bevy::time::TimeUpdateStrategy::ManualDuration(timestep))
+        .add_systems(Startup, spacecraft::esail::spawn_esail)
+    ;
+
+    return app;
+}
@@ -0,0 +1,309 @@
+// Direct solver for the segment lengths of an unbranched tether, instead of the Gauss–Seidel sweep
+// of verlet_simulation.rs, which fixes one segment at a time and needs about as many sweeps as there
+// are elements for a correction to go from one end to the other.
+//
+// Every segment k joins elements k-1 and k, and its constraint is C_k = |x_k - x_(k-1)| - L. Moving
+// the elements along the constraint gradients, Δx = W Jᵀ λ (W the inverse masses), and asking the
+// linearised constraints to vanish, C + J Δx = 0, gives
+//
+//      J W Jᵀ λ = -C
+//
+// A segment only shares elements with the one before and the one after, so J W Jᵀ is tridiagonal:
+//
+//      diagonal        w_(k-1) + w_k
+//      off-diagonal    -w_k n_k·n_(k+1), through the element they share
+//
+// with n_k the direction of segment k. The Thomas algorithm solves it in O(n), and a few Newton
+// iterations (building the system again from the new positions) take care of the nonlinearity.
+// A tether folded onto itself makes the system nearly singular and a full Newton step can make
+// things worse, so the step is halved until the squared errors go down. A bent wire that a far
+// heavier remote unit has to pull straight is another hard case: Newton only converges linearly
+// towards a straight chain, and then uses up the iterations it's given.
+//
+// The weights are inverse masses relative to a segment, like in the Gauss–Seidel sweep: wire
+// elements weigh 1, the remote unit far less, and elements still in the reel 0, which holds them.
+// Solving the whole system at once, a remote unit far heavier than the wire needs nothing more. The
+// tether can't push: only segments that have been stretched during the timestep are in the system,
+// and a segment that ends up pushing its elements apart is let go.
+
+use bevy::prelude::*;
+use bevy::math::DVec3;
+
+use crate::{ physics, spacecraft };
+
+const MAX_HALVINGS: usize = 10;
+
+/// Enforces the length of every stretched segment, and returns the Newton iterations done. Stops
+/// early once no segment is stretched more than the tolerance, if there is one.
+///
+/// Adds to pulls how far every element has been pulled towards the preceding one, like the
+/// Gauss–Seidel sweep does, so that the tension comes out the same way.
+
+pub fn solve (
+    esail:          &spacecraft::esail::ESail,
+    verlet_query:   &mut Query<&mut physics::verlet_object::VerletObject>,
+    first_deployed: usize,
+    segment_length: f64,
+    weights:        &[f64],
+    max_iterations: i32,
+    tolerance:      Option<f64>,
+    pulls:          &mut [f64],
+    ) -> i32 {
+
+    let n = esail.elements.len();
+
+    let mut positions: Vec<DVec3> = esail.elements.iter()
+        .map(|entity| verlet_query.get(*entity).expect("No sail element found").current_coordinates.to_dvec3())
+        .collect();
+
+    // A cut tether has no segment to the reel
+    let first_segment = (first_deployed + if esail.is_cut { 1 } else { 0 }).max(1);
+
+    if first_segment >= n {
+        return 0;
+    }
+
+    // Unknowns are the segments from first_segment on
+    let segments = n - first_segment;
+
+    let mut active      = vec![false; segments];
+    let mut directions  = vec![DVec3::ZERO; segments];
+    let mut errors      = vec![0.0; segments];
+
+    let mut iterations = 0;
+
+    while iterations < max_iterations {
+
+        let mut stretch = 0.0_f64;
+
+        for segment in 0..segments {
+
+            let index       = first_segment + segment;
+            let relative    = positions[index] - positions[index - 1];
+            let distance    = relative.length();
+
+            directions[segment] = if distance > 0.0 { relative / distance } else { DVec3::ZERO };
+            errors[segment]     = distance - segment_length;
+
+            if errors[segment] > 0.0 && distance > 0.0 {
+                active[segment] = true;
+            }
+
+            if active[segment] {
+                stretch = stretch.max(errors[segment] / segment_length);
+            }
+        }
+
+        if tolerance.map_or(false, |tolerance| stretch < tolerance) {
+            break;
+        }
+
+        iterations += 1;
+
+        // Segments out of the system get a row of their own, λ = 0
+        let mut lower       = vec![0.0; segments];
+        let mut diagonal    = vec![1.0; segments];
+        let mut upper       = vec![0.0; segments];
+        let mut rhs         = vec![0.0; segments];
+
+        for segment in 0..segments {
+
+            if !active[segment] {
+                continue;
+            }
+
+            let index = first_segment + segment;
+
+            diagonal[segment]   = weights[index - 1] + weights[index];
+            rhs[segment]        = -errors[segment];
+
+            if segment > 0 && active[segment - 1] {
+                lower[segment] = -weights[index - 1] * directions[segment - 1].dot(directions[segment]);
+            }
+
+            if segment + 1 < segments && active[segment + 1] {
+                upper[segment] = -weights[index] * directions[segment].dot(directions[segment + 1]);
+            }
+        }
+
+        let multipliers = physics::tridiagonal::solve(&lower, &diagonal, &upper, &rhs);
+
+        // Halving the step until it helps, or until it's tiny and might as well be taken
+        let merit = squared_errors(&positions, &active, first_segment, segment_length);
+
+        let mut step = 1.0;
+        let mut trial = positions.clone();
+
+        for _ in 0..MAX_HALVINGS {
+
+            trial.copy_from_slice(&positions);
+            move_elements(&mut trial, &multipliers, step, &active, &directions, weights, first_segment);
+
+            if squared_errors(&trial, &active, first_segment, segment_length) < merit {
+                break;
+            }
+
+            step *= 0.5;
+        }
+
+        positions = trial;
+
+        for segment in 0..segments {
+
+            if !active[segment] {
+                continue;
+            }
+
+            let index = first_segment + segment;
+
+            // Negative multipliers pull the element back towards the preceding one
+            pulls[index] -= step * multipliers[segment];
+
+            if pulls[index] < 0.0 {
+                active[segment] = false;
+            }
+        }
+    }
+
+    for (entity, position) in esail.elements.iter().zip(positions) {
+        let mut verlet_object = verlet_query.get_mut(*entity).expect("No sail element found");
+        let correction = physics::vector3::PositionVector::from_dvec3(position) - verlet_object.current_coordinates;
+        verlet_object.correct_current_coordinates(correction);
+    }
+
+    return iterations;
+}
+
+/// Moves the elements of the active segments along the directions, by the multipliers times the step.
+
+fn move_elements (
+    positions:      &mut [DVec3],
+    multipliers:    &[f64],
+    step:           f64,
+    active:         &[bool],
+    directions:     &[DVec3],
+    weights:        &[f64],
+    first_segment:  usize,
+    ) {
+
+    for (segment, multiplier) in multipliers.iter().enumerate() {
+
+        if !active[segment] {
+            continue;
+        }
+
+        let index   = first_segment + segment;
+        let impulse = directions[segment] * (step * multiplier);
+
+        positions[index]        += impulse * weights[index];
+        positions[index - 1]    -= impulse * weights[index - 1];
+    }
+}
+
+/// Sum of the squared length errors of the active segments.
+
+fn squared_errors (positions: &[DVec3], active: &[bool], first_segment: usize, segment_length: f64) -> f64 {
+
+    return active.iter().enumerate()
+        .filter(|(_, active)| **active)
+        .map(|(segment, _)| {
+            let index = first_segment + segment;
+            ((positions[index] - positions[index - 1]).length() - segment_length).powi(2)
+        })
+        .sum();
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    use bevy::ecs::system::SystemState;
+    use uom::si::f64 as quantities;
+    use uom::si::force;
+
+    use physics::vector3::{ ForceVector, PositionVector };
+
+    /// Solves a tether of one element in the reel and 40 deployed, the last one a thousand times
+    /// heavier than a segment. Segments are longer than they should be by up to stretch, and turn
+    /// by up to bend radians one way and the other. Returns the Newton steps, the segment lengths
+    /// over the length they should be, and the pulls.
+
+    fn solve_wavy_chain (stretch: f64, bend: f64) -> (i32, Vec<f64>, Vec<f64>) {
+
+        let (elements, segment_length) = (41, 0.05);
+
+        let mut world = World::new();
+
+        let mut position = DVec3::ZERO;
+
+        let entities: Vec<Entity> = (0..elements).map(|index| {
+            if index > 0 {
+                let angle = bend * (index as f64).sin();
+                position += DVec3::new(angle.cos(), angle.sin(), 0.0) * segment_length * (1.0 + stretch * (index as f64 * 0.7).sin().abs());
+            }
+            let position = PositionVector::from_dvec3(position);
+            world.spawn(physics::verlet_object::VerletObject {
+                previous_coordinates:   position,
+                current_coordinates:    position,
+                is_deployed:            index > 0,
+                current_force:          ForceVector::zero(),
+                coulomb_force:          ForceVector::zero(),
+                tension:                quantities::Force::new::<force::newton>(0.0),
+                is_slack:               false,
+            }).id()
+        }).collect();
+
+        let body = world.spawn_empty().id();
+
+        let esail = spacecraft::esail::ESail {
+            origin:                 PositionVector::zero(),
+            elements:               entities.clone(),
+            undeployed_elements:    entities[..1].to_vec(),
+            deployed_elements:      entities[1..].to_vec(),
+            total_force:            ForceVector::zero(),
+            is_cut:                 false,
+            graph:                  physics::constraint_graph::ConstraintGraph::new(&spacecraft::SpacecraftParameters::default(), elements, body),
+        };
+
+        let weights: Vec<f64> = (0..elements).map(|index| match index {
+            0                               => 0.0,
+            index if index + 1 == elements  => 1.0e-3,
+            _                               => 1.0,
+        }).collect();
+
+        let mut pulls = vec![0.0; elements];
+
+        let mut state: SystemState<Query<&mut physics::verlet_object::VerletObject>> = SystemState::new(&mut world);
+        let mut query = state.get_mut(&mut world);
+
+        let iterations = solve(&esail, &mut query, 1, segment_length, &weights, 20, Some(1.0e-10), &mut pulls);
+
+        let positions: Vec<DVec3> = entities.iter().map(|entity| query.get(*entity).unwrap().current_coordinates.to_dvec3()).collect();
+
+        assert_eq!(positions[0], DVec3::ZERO, "The reel moved");
+
+        let lengths = (1..elements).map(|index| (positions[index] - positions[index - 1]).length() / segment_length).collect();
+
+        return (iterations, lengths, pulls);
+    }
+
+    #[test]
+    fn stretched_chain_in_a_few_newton_steps () {
+
+        // Straight, the heavy tip takes up all of the stretch, and bent, the wire straightens out
+        for (stretch, bend) in [(0.1, 0.0), (0.01, 0.3), (1.0e-4, 1.0e-3)] {
+
+            let (iterations, lengths, pulls) = solve_wavy_chain(stretch, bend);
+
+            assert!(iterations <= 5, "Stretch {}, bend {}: {} Newton steps", stretch, bend, iterations);
+
+            for (segment, length) in lengths.iter().enumerate() {
+                assert!((length - 1.0).abs() < 1.0e-10, "Stretch {}, bend {}: segment {} is {} of its length", stretch, bend, segment + 1, length);
+                assert!(pulls[segment + 1] > 0.0, "Stretch {}, bend {}: segment {} pushes", stretch, bend, segment + 1);
+            }
+        }
+    }
+}
@@ -0,0 +1,144 @@
+// Steady state detection. A run is settled when the elements have stopped moving, the total force
+// has stopped changing and the constraints hold, and all of it has stayed so for a while.
+//
+// The monitor runs after the simulation every frame. When the run settles it sends a SteadyState
+// event, and stops the App if asked to, for batch runs. If the run leaves the tolerances again, for
+// example because the timeline changed the potential, it can settle again later. Nothing settles
+// while there are timeline actions still to come.
+
+use bevy::prelude::*;
+use bevy::app::AppExit;
+
+use crate::{ physics, resources, spacecraft };
+use physics::vector3::ForceVector;
+
+use uom::si::f64 as quantities;
+use uom::si::*;
+
+#[derive(Clone)]
+pub struct ConvergenceSettings {
+    pub velocity_tolerance: quantities::Velocity,   // Fastest element
+    pub force_tolerance:    f64,                    // Relative change of the total force, per second
+    pub residual_tolerance: f64,                    // Largest relative error of a segment length
+    pub hold:               quantities::Time,       // How long everything has to stay within tolerance
+    pub stop:               bool,                   // Close the App once settled
+}
+
+impl Default for ConvergenceSettings {
+    fn default() -> Self {
+        ConvergenceSettings {
+            velocity_tolerance: quantities::Velocity::new::<velocity::millimeter_per_second>(1.0),
+            force_tolerance:    1.0e-3,
+            residual_tolerance: 1.0e-3,
+            hold:               quantities::Time::new::<time::second>(10.0),
+            stop:               false,
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct ConvergenceMonitor {
+    pub settings:       ConvergenceSettings,
+    pub max_speed:      quantities::Velocity,
+    pub force_change:   f64,                        // Relative, per second
+    pub residual:       f64,
+    pub steady_at:      Option<quantities::Time>,   // When the run settled, if it has
+    within_since:       Option<quantities::Time>,   // Since when everything is within tolerance
+    previous_force:     Option<(quantities::Time, ForceVector)>,
+}
+
+#[derive(Event)]
+pub struct SteadyState {
+    pub time: quantities::Time,
+}
+
+impl ConvergenceMonitor {
+
+    pub fn new (settings: ConvergenceSettings) -> Self {
+        return ConvergenceMonitor { settings: settings, ..Default::default() };
+    }
+
+    pub fn is_steady (&self) -> bool {
+        return self.steady_at.is_some();
+    }
+
+    /// Forgets the history, for a new run.
+
+    pub fn reset (&mut self) {
+        *self = ConvergenceMonitor::new(self.settings.clone());
+    }
+}
+
+
+
+pub fn monitor_convergence (
+    mut monitor:        ResMut<ConvergenceMonitor>,
+    sim_params:         Res<resources::SimulationParameters>,
+    timeline:           Res<super::timeline::Timeline>,
+    esail_query:        Query<&spacecraft::esail::ESail>,
+    verlet_query:       Query<&physics::verlet_object::VerletObject>,
+    mut steady_events:  EventWriter<SteadyState>,
+    mut exit_events:    EventWriter<AppExit>,
+    ) {
+
+    let time = sim_params.simulated_time;
+
+    // Time going backwards means that the simulation was restarted
+    if monitor.previous_force.map_or(false, |(previous_time, _)| time < previous_time) {
+        monitor.reset();
+    }
+
+    let Ok(esail) = esail_query.get_single() else { return };
+
+    // Only once per timestep, frames without a timestep change nothing
+    if monitor.previous_force.map_or(false, |(previous_time, _)| time == previous_time) {
+        return;
+    }
+
+    monitor.max_speed = esail.deployed_elements.iter()
+        .map(|entity| {
+            let verlet_object = verlet_query.get(*entity).expect("No sail element found");
+            (verlet_object.current_coordinates - verlet_object.previous_coordinates).norm() / sim_params.timestep_s
+        })
+        .fold(quantities::Velocity::default(), |max, speed| max.max(speed));
+
+    monitor.force_change = match monitor.previous_force {
+        Some((previous_time, previous_force)) => {
+            let change      = (esail.total_force - previous_force).norm().get::<force::newton>();
+            let magnitude   = esail.total_force.norm().get::<force::newton>();
+            let elapsed     = (time - previous_time).get::<time::second>();
+            if change == 0.0 { 0.0 } else if magnitude > 0.0 { change / magnitude / elapsed } else { f64::INFINITY }
+        },
+        None => f64::INFINITY,  // Nothing to compare with yet
+    };
+
+    monitor.residual        = sim_params.constraint_residual;
+    monitor.previous_force  = Some((time, esail.total_force));
+
+    let settings = &monitor.settings;
+
+    let within = monitor.max_speed <= settings.velocity_tolerance
+              && monitor.force_change <= settings.force_tolerance
+              && monitor.residual <= settings.residual_tolerance
+              && timeline.actions.iter().all(|scheduled| scheduled.finished);
+
+    if !within {
+        monitor.within_since    = None;
+        monitor.steady_at       = None;
+        return;
+    }
+
+    let within_since = *monitor.within_since.get_or_insert(time);
+
+    if monitor.steady_at.is_none() && time - within_since >= monitor.settings.hold {
+
+        monitor.steady_at = Some(time);
+
+        steady_events.send(SteadyState { time: time });
+
+        if monitor.settings.stop {
+            println!("Steady state at t = {} s, stopping", time.get::<time::second>());
+            exit_events.send(AppExit);
+        }
+    }
+}
@@ -0,0 +1,243 @@
+// Gauss–Seidel sweep over the constraint graph of the E-sail (see physics/constraint_graph.rs): one
+// constraint at a time, in the order of the graph, so the segments from the reel outwards and then
+// everything else.
+//
+// A distance constraint moves its two elements in inverse proportion to their masses, as weights
+// relative to a segment: w = m_segment / m, so wire elements weigh 1, and elements still in the reel
+// 0, since the reel holds them. It only pulls: a line shorter than its length is slack. A segment
+// keeps what it has pulled over the timestep, its multiplier, and can give some of it back when it
+// turns out to have pulled too much, but never more than it has. An anchor or an attachment puts
+// its element right on its point, since whatever holds it doesn't give.
+//
+// A remote unit is far heavier than the wire, so the segments next to it hardly move it, and the
+// sweeps alone would take a very long time to shorten a tether stretched all along. So every sweep
+// ends with correct_uniform_pull: the same pull on every taut wire of the tether, the one that
+// leaves them stretched by nothing on the whole, which goes into their tension like any other.
+//
+// Elements in the reel don't move, so constraints between two of them are skipped, and so are broken
+// ones. Once the tether is cut, the reel doesn't hold anything anymore, and nothing joins it to
+// deployed elements.
+
+use bevy::prelude::*;
+use bevy::math::DVec3;
+
+use crate::{ components, physics, spacecraft };
+use physics::constraint_graph::Constraint;
+
+/// Sweeps the constraints, and returns the sweeps done. Stops early once no constraint is off by
+/// more than the tolerance, if there is one.
+///
+/// Adds to pulls how far every element has been pulled towards the preceding one by its segment, over
+/// its weight, like the other solvers, so that the tension comes out the same way. Only segments have
+/// an element to keep their tension in.
+
+pub fn solve (
+    esail:          &spacecraft::esail::ESail,
+    verlet_query:   &mut Query<&mut physics::verlet_object::VerletObject>,
+    body_query:     &Query<&components::RigidBody>,
+    first_deployed: usize,
+    segment_length: f64,
+    weights:        &[f64],
+    max_iterations: i32,
+    tolerance:      Option<f64>,
+    pulls:          &mut [f64],
+    ) -> i32 {
+
+    let mut positions: Vec<DVec3> = esail.elements.iter()
+        .map(|entity| verlet_query.get(*entity).expect("No sail element found").current_coordinates.to_dvec3())
+        .collect();
+
+    let mut iterations = 0;
+
+    while iterations < max_iterations {
+
+        iterations += 1;
+
+        // Largest relative error found in this sweep, before correcting it
+        let mut sweep_error = 0.0_f64;
+
+        for (number, constraint) in esail.graph.constraints.iter().enumerate() {
+
+            if !is_active(esail, number, first_deployed) {
+                continue;
+            }
+
+            match *constraint {
+
+                Constraint::Distance { first, second, length } => {
+
+                    let relative = positions[second] - positions[first];
+                    let distance = relative.length();
+
+                    let is_segment  = number < esail.graph.segments;
+                    let held        = if is_segment { pulls[second] } else { 0.0 };
+
+                    // Slack, and not pulling, nothing to correct
+                    if distance == 0.0 || (distance <= length && held <= 0.0) {
+                        continue;
+                    }
+
+                    sweep_error = sweep_error.max((distance / length - 1.0).abs());
+
+                    // What's been pulled can be given back, but no more
+                    let mut pull = (distance - length) / (weights[first] + weights[second]);
+
+                    if is_segment {
+                        pull = pull.max(-held);
+                        pulls[second] += pull;
+                    }
+
+                    let correction = relative * (pull / distance);
+
+                    positions[second]   -= correction * weights[second];
+                    positions[first]    += correction * weights[first];
+                },
+
+                Constraint::Anchor { element, .. } | Constraint::Attachment { element, .. } => {
+
+                    let Some(point) = target(constraint, body_query) else { continue };
+
+                    sweep_error = sweep_error.max(positions[element].distance(point) / segment_length);
+
+                    positions[element] = point;
+                },
+            }
+        }
+
+        correct_uniform_pull(esail, &mut positions, weights, first_deployed, pulls);
+
+        if tolerance.map_or(false, |tolerance| sweep_error < tolerance) {
+            break;
+        }
+    }
+
+    for (entity, position) in esail.elements.iter().zip(positions) {
+        let mut verlet_object = verlet_query.get_mut(*entity).expect("No sail element found");
+        let correction = physics::vector3::PositionVector::from_dvec3(position) - verlet_object.current_coordinates;
+        verlet_object.correct_current_coordinates(correction);
+    }
+
+    return iterations;
+}
+
+/// Pulls every taut wire of the tether (segments and loop wires) by the same amount, the amount that
+/// leaves them stretched by nothing on the whole, to first order. Adds the pull to the segments.
+/// Nothing if they're shorter than their lengths on the whole, since the pull can't push.
+
+pub fn correct_uniform_pull (
+    esail:          &spacecraft::esail::ESail,
+    positions:      &mut [DVec3],
+    weights:        &[f64],
+    first_deployed: usize,
+    pulls:          &mut [f64],
+    ) {
+
+    let wires = esail.graph.segments + esail.graph.loops;
+
+    let mut directions  = vec![DVec3::ZERO; positions.len()];
+    let mut stretch     = 0.0;
+    let mut taut        = Vec::new();
+
+    for number in 0..wires {
+
+        let Constraint::Distance { first, second, length } = esail.graph.constraints[number] else { continue };
+
+        if !is_active(esail, number, first_deployed) {
+            continue;
+        }
+
+        let relative = positions[second] - positions[first];
+        let distance = relative.length();
+
+        let held = if number < esail.graph.segments { pulls[second] } else { 0.0 };
+
+        if distance == 0.0 || (distance <= length && held <= 0.0) {
+            continue;
+        }
+
+        let direction = relative / distance;
+
+        directions[second]  += direction;
+        directions[first]   -= direction;
+        stretch             += distance - length;
+
+        taut.push(number);
+    }
+
+    let stiffness: f64 = directions.iter().zip(weights).map(|(direction, weight)| weight * direction.length_squared()).sum();
+
+    if stiffness <= 0.0 || stretch <= 0.0 {
+        return;
+    }
+
+    let pull = stretch / stiffness;
+
+    for ((position, direction), weight) in positions.iter_mut().zip(&directions).zip(weights) {
+        *position -= *direction * (weight * pull);
+    }
+
+    for number in taut.into_iter().filter(|number| *number < esail.graph.segments) {
+        if let Constraint::Distance { second, .. } = esail.graph.constraints[number] {
+            pulls[second] += pull;
+        }
+    }
+}
+
+/// Relative error of a constraint of the graph: the stretch of a distance constraint, or how far an
+/// element is from its point in segment lengths. None if the constraint does nothing now.
+
+pub fn relative_error (
+    number:         usize,
+    esail:          &spacecraft::esail::ESail,
+    verlet_query:   &Query<&mut physics::verlet_object::VerletObject>,
+    body_query:     &Query<&components::RigidBody>,
+    first_deployed: usize,
+    segment_length: f64,
+    ) -> Option<f64> {
+
+    if !is_active(esail, number, first_deployed) {
+        return None;
+    }
+
+    let constraint = &esail.graph.constraints[number];
+
+    let position = |element: usize| verlet_query.get(esail.elements[element]).expect("No sail element found").current_coordinates.to_dvec3();
+
+    return match *constraint {
+        Constraint::Distance { first, second, length } =>
+            Some(position(second).distance(position(first)) / length - 1.0),
+        Constraint::Anchor { element, .. } | Constraint::Attachment { element, .. } =>
+            target(constraint, body_query).map(|point| position(element).distance(point) / segment_length),
+    };
+}
+
+/// Whether a constraint has anything to do: it has to be whole, it has to hold at least one element
+/// out of the reel, and a cut tether isn't joined to the reel anymore.
+
+fn is_active (esail: &spacecraft::esail::ESail, number: usize, first_deployed: usize) -> bool {
+
+    if esail.graph.broken[number] {
+        return false;
+    }
+
+    return match esail.graph.constraints[number] {
+        Constraint::Distance { first, second, .. } => {
+            let held = [first, second].iter().filter(|element| **element < first_deployed).count();
+            held == 0 || (held == 1 && !esail.is_cut)
+        },
+        Constraint::Anchor { element, .. } | Constraint::Attachment { element, .. } => element >= first_deployed,
+    };
+}
+
+/// Point an anchor or an attachment holds its element at, in meters. None for distance constraints,
+/// and for attachments to a body that isn't there.
+
+fn target (constraint: &Constraint, body_query: &Query<&components::RigidBody>) -> Option<DVec3> {
+
+    return match *constraint {
+        Constraint::Distance { .. }                 => None,
+        Constraint::Anchor { position, .. }         => Some(position),
+        Constraint::Attachment { body, offset, .. } => body_query.get(body).ok()
+            .map(|body| body.position.to_dvec3() + body.orientation * offset),
+    };
+}
@@ -3,14 +3,12 @@ use bevy::math::DVec3;
 
 use crate::{ physics, resources, solar_wind, spacecraft };
 
-use std::ops::{ Mul };
-
 use uom::si::length::meter;
 use uom::si::mass::kilogram;
 
-use physics::force_vector::ForceVector as ForceVector;
-use physics::position_vector::PositionVector as PositionVector;
-use physics::acceleration_vector::AccelerationVector as AccelerationVector;
+use physics::vector3::ForceVector as ForceVector;
+use physics::vector3::PositionVector as PositionVector;
+use physics::vector3::AccelerationVector as AccelerationVector;
 
 pub fn new_verlet_simulation (
     time:               Res<Time>, 
@@ -41,8 +39,7 @@ pub fn new_verlet_simulation (
             for index in 0..new_esail.deployed_elements.len() {
 
                 let current_element_coordinates = new_esail.deployed_elements[index]
-                                                           .current_coordinates
-                                                           .clone();
+                                                           .current_coordinates;
 
                 let preceding_element_coordinates = if index == 0 {
                     &new_esail.origin
@@ -53,10 +50,10 @@ pub fn new_verlet_simulation (
                 
                 let vector_between_elements = PositionVector::from_a_to_b(
                                                 current_element_coordinates,
-                                                preceding_element_coordinates.clone(),
+                                                *preceding_element_coordinates,
                                                 );
 
-                let distance_between_elements = vector_between_elements.clone().length();
+                let distance_between_elements = vector_between_elements.norm();
 
                 let difference = if distance_between_elements.get::<meter>() > 0.0 {
                     (desired_distance_between_elements.get::<meter>() - distance_between_elements.get::<meter>())
@@ -67,7 +64,7 @@ pub fn new_verlet_simulation (
 
                 //println!("Index: {} | Distance to previous: {:?} | Difference: {:?}", index, distance_between_elements, difference);   // Weird...
 
-                let correction_vector = vector_between_elements.mul(0.5 * difference);
+                let correction_vector = vector_between_elements * (0.5 * difference);
 
                 //println!("(Index {}) New correction vector: {:?}", index, correction_vector);
 
@@ -95,7 +92,7 @@ fn new_verlet_integration(
 
     // Centrifugal force (Along x for now, this needs to change)
 
-    let centrifugal_force_magnitude = craft_params.segment_mass() * verlet_object.current_coordinates.clone().length() 
+    let centrifugal_force_magnitude = craft_params.segment_mass() * verlet_object.current_coordinates.norm() 
         * craft_params.angular_velocity() * craft_params.angular_velocity();
 
     let centrifugal_force_direction = DVec3::new(1.0, 0.0, 0.0);
@@ -118,15 +115,15 @@ fn new_verlet_integration(
 
     let total_force = coulomb_force + centrifugal_force;    // This is a ForceVector containing uom quantities
 
-    verlet_object.current_force = total_force.clone();
+    verlet_object.current_force = total_force;
 
-    let acc_vector = AccelerationVector::from_force(total_force.clone(), craft_params.segment_mass());
+    let acc_vector: AccelerationVector = total_force / craft_params.segment_mass();
 
-    let delta_from_acc = PositionVector::from_acceleration(acc_vector, sim_params.timestep_s);
+    let delta_from_acc: PositionVector = acc_vector * sim_params.timestep_s * sim_params.timestep_s;
  
 
     // Next position calculation (formula from here: https://www.algorithm-archive.org/contents/verlet_integration/verlet_integration.html)
-    let next_coordinates = verlet_object.current_coordinates.clone().mul(2.0) - verlet_object.previous_coordinates.clone() + delta_from_acc;
+    let next_coordinates = verlet_object.current_coordinates * 2.0 - verlet_object.previous_coordinates + delta_from_acc;
 
     // Damping here, before the update of coordinates?
 
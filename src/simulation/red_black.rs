@@ -0,0 +1,222 @@
+// Red–black ordering of the Gauss–Seidel sweep of verlet_simulation.rs, so that it can run on
+// several threads.
+//
+// Segment k joins elements k-1 and k, so segments of the same parity ("colour") never share an
+// element: all the even segments can be corrected at the same time, and then all the odd ones. The
+// elements are cut into pairs, (k-1, k) for every segment of the colour, and every thread gets a
+// contiguous run of pairs. Within a colour nothing depends on the order, so the result is the same
+// bit for bit whatever the number of threads. It isn't the same as the serial sweep, which goes
+// from the reel outwards, but it converges about as fast.
+//
+// Each segment is corrected exactly like in the serial sweep: the error split between the elements
+// by their weights, and nothing on an element still in the reel. Every sweep ends with the same
+// correction of the pull all along the tether, for the remote unit (see graph_solver.rs). It's done
+// on one thread, but it's a single pass over the elements.
+//
+// The threads share the segments of one tether. A sail has a single tether (verlet_simulation.rs
+// works on the one E-sail there is), so there's nothing to gain from solving tethers side by side
+// yet. Tethers would only meet at the hub, and each one could go to a thread of its own then.
+//
+// Starting threads costs some tens of microseconds, so they're only worth it for long tethers:
+// every thread gets at least MIN_PAIRS_PER_THREAD segments, and short tethers stay on one thread.
+// Correcting a pair takes about 15 ns, and a thread 15 to 30 µs to start and join, every half sweep
+// (--benchmark, and timing half_sweep alone), so 2048 pairs are about twice what their thread costs.
+// Two threads start at some 8000 elements.
+
+use std::sync::OnceLock;
+
+use bevy::prelude::*;
+use bevy::math::DVec3;
+
+use crate::{ physics, spacecraft };
+
+const MIN_PAIRS_PER_THREAD: usize = 2048;
+
+/// Cores of the machine, asked only once since it can mean reading files.
+
+pub fn available_threads () -> usize {
+
+    static THREADS: OnceLock<usize> = OnceLock::new();
+
+    return *THREADS.get_or_init(|| std::thread::available_parallelism().map(|threads| threads.get()).unwrap_or(1));
+}
+
+/// Sweeps the taut segments in red–black order, and returns the sweeps done. Stops early once
+/// no segment is stretched more than the tolerance, if there is one.
+///
+/// Adds to pulls how far every element has been pulled towards the preceding one, like the serial
+/// sweep does, so that the tension comes out the same way.
+
+pub fn solve (
+    esail:          &spacecraft::esail::ESail,
+    verlet_query:   &mut Query<&mut physics::verlet_object::VerletObject>,
+    first_deployed: usize,
+    segment_length: f64,
+    weights:        &[f64],
+    max_iterations: i32,
+    tolerance:      Option<f64>,
+    pulls:          &mut [f64],
+    threads:        usize,
+    ) -> i32 {
+
+    let mut positions: Vec<DVec3> = esail.elements.iter()
+        .map(|entity| verlet_query.get(*entity).expect("No sail element found").current_coordinates.to_dvec3())
+        .collect();
+
+    // A cut tether has no segment to the reel
+    let first_segment = (first_deployed + if esail.is_cut { 1 } else { 0 }).max(1);
+
+    let mut iterations = 0;
+
+    while iterations < max_iterations {
+
+        iterations += 1;
+
+        let stretch = [0, 1].map(|colour| {
+            half_sweep(&mut positions, pulls, weights, colour, first_segment, segment_length, threads)
+        });
+
+        super::graph_solver::correct_uniform_pull(esail, &mut positions, weights, first_deployed, pulls);
+
+        if tolerance.map_or(false, |tolerance| stretch[0].max(stretch[1]) < tolerance) {
+            break;
+        }
+    }
+
+    for (entity, position) in esail.elements.iter().zip(positions) {
+        let mut verlet_object = verlet_query.get_mut(*entity).expect("No sail element found");
+        let correction = physics::vector3::PositionVector::from_dvec3(position) - verlet_object.current_coordinates;
+        verlet_object.correct_current_coordinates(correction);
+    }
+
+    return iterations;
+}
+
+/// Corrects every segment of one colour, split between the threads. Returns the largest relative
+/// stretch found, before correcting it.
+
+fn half_sweep (
+    positions:      &mut [DVec3],
+    pulls:          &mut [f64],
+    weights:        &[f64],
+    colour:         usize,
+    first_segment:  usize,
+    segment_length: f64,
+    threads:        usize,
+    ) -> f64 {
+
+    let first = if first_segment % 2 == colour { first_segment } else { first_segment + 1 };
+
+    if first >= positions.len() {
+        return 0.0;
+    }
+
+    // Pairs start at the element before the first segment
+    let offset  = first - 1;
+    let pairs   = (positions.len() - offset) / 2;
+    let threads = threads.min(pairs / MIN_PAIRS_PER_THREAD).max(1);
+
+    if threads == 1 {
+        return correct_pairs(&mut positions[offset..], &mut pulls[offset..], &weights[offset..], segment_length);
+    }
+
+    // An even number of elements per thread, so that no pair is split
+    let chunk = 2 * pairs.div_ceil(threads);
+
+    return std::thread::scope(|scope| {
+
+        let handles: Vec<_> = positions[offset..].chunks_mut(chunk).zip(pulls[offset..].chunks_mut(chunk)).zip(weights[offset..].chunks(chunk))
+            .map(|((positions, pulls), weights)| {
+                scope.spawn(move || correct_pairs(positions, pulls, weights, segment_length))
+            })
+            .collect();
+
+        handles.into_iter().map(|handle| handle.join().expect("Constraint thread panicked")).fold(0.0, f64::max)
+    });
+}
+
+/// Corrects the segment between the two elements of every pair.
+
+fn correct_pairs (
+    positions:      &mut [DVec3],
+    pulls:          &mut [f64],
+    weights:        &[f64],
+    segment_length: f64,
+    ) -> f64 {
+
+    let mut stretch = 0.0_f64;
+
+    for ((elements, pulls), weights) in positions.chunks_exact_mut(2).zip(pulls.chunks_exact_mut(2)).zip(weights.chunks_exact(2)) {
+
+        let relative = elements[1] - elements[0];
+        let distance = relative.length();
+
+        // Slack, and not pulling, nothing to correct
+        if distance == 0.0 || (distance <= segment_length && pulls[1] <= 0.0) {
+            continue;
+        }
+
+        stretch = stretch.max((distance / segment_length - 1.0).abs());
+
+        // What's been pulled can be given back, but no more
+        let pull        = ((distance - segment_length) / (weights[0] + weights[1])).max(-pulls[1]);
+        let correction  = relative * (pull / distance);
+
+        elements[1] -= correction * weights[1];
+        elements[0] += correction * weights[0];
+
+        pulls[1] += pull;
+    }
+
+    return stretch;
+}
+
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn same_bits_on_any_number_of_threads () {
+
+        // Long enough for every thread to get its share, held by the reel at one end, with a heavy
+        // element at the other, and stretched unevenly
+        let threads     = 4;
+        let elements    = 2 * threads * MIN_PAIRS_PER_THREAD + 3;
+
+        let weights: Vec<f64> = (0..elements).map(|index| match index {
+            0                               => 0.0,
+            index if index + 1 == elements  => 1.0e-3,
+            _                               => 1.0,
+        }).collect();
+
+        let start: Vec<DVec3> = (0..elements).map(|index| {
+            DVec3::new(index as f64 * 0.0105, 1.0e-4 * (index as f64).sin(), 1.0e-4 * (index as f64 * 0.3).cos())
+        }).collect();
+
+        let sweeps = |threads: usize| {
+
+            let mut positions   = start.clone();
+            let mut pulls       = vec![0.0; elements];
+
+            for sweep in 0..10 {
+                half_sweep(&mut positions, &mut pulls, &weights, sweep % 2, 1, 0.01, threads);
+            }
+
+            (positions, pulls)
+        };
+
+        let (serial_positions, serial_pulls) = sweeps(1);
+
+        for threads in 2..=threads {
+
+            let (positions, pulls) = sweeps(threads);
+
+            assert!(positions == serial_positions, "Positions differ on {} threads", threads);
+            assert!(pulls == serial_pulls, "Pulls differ on {} threads", threads);
+        }
+    }
+}
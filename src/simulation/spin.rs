@@ -0,0 +1,120 @@
+// Spin rate of the spacecraft, from its angular momentum about the spin axis. The simulation works
+// in a frame spinning with the body, so the spin rate is the rate of the frame:
+//
+//      L = I ω + Σ m (r × v)·axis
+//
+// I is the moment of inertia of the body, the reel and the tether out of it, and the sum is the
+// angular momentum of the tether elements moving in the spinning frame. Every timestep L is measured
+// before anything moves, the torques of the Coulomb drag and the thrusters of the remote unit are
+// added over the timestep, and the new spin rate is whatever keeps L with the new I and the new
+// velocities. Wire paying out raises I and slows the spin down, and taking it up speeds it up again.
+// The tension at the reel, the centrifugal force and the constraints are all inside the spacecraft,
+// and don't change L.
+//
+// Moving in the spinning frame, the elements feel the Coriolis force -2m ω × v, and while the spin
+// rate changes, the Euler force -m dω/dt × r too, besides the centrifugal force (see
+// verlet_integration). dω/dt is the change of the spin rate over the last timestep, so it comes one
+// timestep late.
+
+use bevy::prelude::*;
+use bevy::math::DVec3;
+use uom::si::f64 as quantities;
+use uom::si::*;
+
+use crate::{ components, physics, spacecraft };
+use physics::vector3::ForceVector;
+
+/// What the spin rate was worked out from during the last timestep, for the GUI.
+
+#[derive(Resource, Default)]
+pub struct SpinState {
+    pub angular_momentum:   quantities::AngularMomentum,
+    pub moment_of_inertia:  quantities::MomentOfInertia,
+    pub drag_torque:        quantities::Torque,
+    pub thrust_torque:      quantities::Torque,
+    pub angular_acceleration: quantities::AngularAcceleration,  // Of the frame, over the last timestep
+}
+
+/// How the angular momentum of the spacecraft splits, at some moment: the moment of inertia that
+/// spins with the frame, and the angular momentum of the tether moving in it.
+
+pub struct Spin {
+    pub moment_of_inertia:  quantities::MomentOfInertia,
+    pub relative:           quantities::AngularMomentum,
+}
+
+impl Spin {
+
+    /// Measures the spacecraft as it is now. Velocities are the ones implied by the last step.
+    pub fn measure (
+        esail:          &spacecraft::esail::ESail,
+        craft_params:   &spacecraft::SpacecraftParameters,
+        reel:           &spacecraft::reel::Reel,
+        verlet_query:   &Query<&physics::verlet_object::VerletObject>,
+        mass_query:     &Query<&components::Mass>,
+        timestep:       quantities::Time,
+        ) -> Self {
+
+        let rotation_axis   = craft_params.rotation_axis.normalize();
+        let dt              = timestep.get::<time::second>();
+
+        // A cut tether isn't part of the spacecraft anymore, and takes its angular momentum along
+        let tether: &[Entity] = if esail.is_cut { &[] } else { &esail.deployed_elements };
+
+        let relative: f64 = tether.iter().map(|entity| {
+
+            let verlet_object   = verlet_query.get(*entity).expect("No sail element found");
+            let mass            = mass_query.get(*entity).expect("Sail element without mass").0.get::<mass::kilogram>();
+
+            let position = verlet_object.current_coordinates.to_dvec3();
+            let velocity = (verlet_object.current_coordinates - verlet_object.previous_coordinates).to_dvec3() / dt;
+
+            mass * position.cross(velocity).dot(rotation_axis)
+
+        }).sum();
+
+        return Spin {
+            moment_of_inertia:  esail.mass_properties(craft_params, reel, verlet_query, mass_query).moment_of_inertia,
+            relative:           quantities::AngularMomentum::new::<angular_momentum::kilogram_square_meter_per_second>(relative),
+        };
+    }
+
+    /// Angular momentum about the spin axis, spinning at the given angular velocity (in rad/s).
+    pub fn angular_momentum (&self, angular_velocity: quantities::Frequency) -> quantities::AngularMomentum {
+        // uom keeps angular momentum apart from the moment of inertia times a frequency, as a kind
+        // of its own
+        return quantities::AngularMomentum::new::<angular_momentum::kilogram_square_meter_per_second>(
+            self.moment_of_inertia.value * angular_velocity.value + self.relative.value
+        );
+    }
+
+    /// Angular velocity (in rad/s) that the given angular momentum spins the frame at.
+    pub fn angular_velocity (&self, angular_momentum: quantities::AngularMomentum) -> quantities::Frequency {
+        return quantities::Frequency::new::<frequency::hertz>((angular_momentum - self.relative).value / self.moment_of_inertia.value);
+    }
+}
+
+/// Angular momentum a torque adds over a timestep.
+
+pub fn impulse (
+    torque:     quantities::Torque,
+    timestep:   quantities::Time,
+    ) -> quantities::AngularMomentum {
+
+    return quantities::AngularMomentum::new::<angular_momentum::kilogram_square_meter_per_second>(
+        torque.get::<torque::newton_meter>() * timestep.get::<time::second>()
+    );
+}
+
+/// Torque about the spin axis of a force on a point, in meters.
+
+pub fn torque (
+    craft_params:   &spacecraft::SpacecraftParameters,
+    position:       DVec3,
+    force:          ForceVector,
+    ) -> quantities::Torque {
+
+    let rotation_axis = craft_params.rotation_axis.normalize();
+
+    return quantities::Torque::new::<torque::newton_meter>(position.cross(force.to_dvec3()).dot(rotation_axis));
+}
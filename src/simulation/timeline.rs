@@ -0,0 +1,182 @@
+// Actions scheduled at given simulated times, so that runs can be repeated without touching the
+// sliders or the arrow keys. They're read from the [[timeline]] tables of the scenario file.
+//
+// The timeline is applied at every timestep of the simulation, not once per frame, so that a run
+// gives the same result whatever the frame rate.
+
+use bevy::prelude::*;
+use uom::si::f64 as quantities;
+use uom::si::*;
+
+use crate::{ solar_wind, spacecraft };
+
+#[derive(Resource, Default)]
+pub struct Timeline {
+    pub actions: Vec<ScheduledAction>,
+}
+
+pub struct ScheduledAction {
+    pub start:      quantities::Time,   // Simulated time at which the action starts
+    pub action:     Action,
+    pub finished:   bool,
+}
+
+pub enum Action {
+    /// Deploys a length of tether at a constant speed.
+    Deploy {
+        length:     quantities::Length,
+        speed:      quantities::Velocity,
+        deployed:   usize,  // Elements deployed so far by this action
+    },
+    /// Changes the wire potential linearly, from whatever it is when the action starts to the target.
+    RampPotential {
+        target:     quantities::ElectricPotential,
+        duration:   quantities::Time,
+        initial:    Option<quantities::ElectricPotential>,
+    },
+    SolarWindSpeed {
+        velocity:   quantities::Velocity,
+    },
+    /// Cuts the tether from the spacecraft. Tethers are numbered from 0, in the order they were spawned.
+    CutTether {
+        tether:     usize,
+    },
+    /// Breaks one wire of a tether, like a micrometeoroid would. A Heytether holds on through its
+    /// other wires, a single wire comes apart.
+    BreakWire {
+        tether:     usize,
+        segment:    usize,  // Segment k joins elements k-1 and k
+        filament:   usize,  // 0 for the base wire, loop wires of a Heytether from 1
+    },
+    /// Tells the thrusters of the remote units what to do, until told otherwise.
+    Thrusters {
+        command:    spacecraft::remote_unit::ThrustCommand,
+    },
+}
+
+impl ScheduledAction {
+
+    pub fn new (start: quantities::Time, action: Action) -> Self {
+        return Self { start: start, action: action, finished: false };
+    }
+
+    /// Short description, for the GUI and the console.
+
+    pub fn describe (&self) -> String {
+
+        let start = self.start.get::<time::second>();
+
+        return match &self.action {
+            Action::Deploy { length, speed, .. } =>
+                format!("t = {} s: deploy {} m at {} m/s", start, length.get::<length::meter>(), speed.get::<velocity::meter_per_second>()),
+            Action::RampPotential { target, duration, .. } =>
+                format!("t = {} s: ramp wire potential to {} kV over {} s", start, target.get::<electric_potential::kilovolt>(), duration.get::<time::second>()),
+            Action::SolarWindSpeed { velocity } =>
+                format!("t = {} s: solar wind speed {} km/s", start, velocity.get::<velocity::kilometer_per_second>()),
+            Action::CutTether { tether } =>
+                format!("t = {} s: cut tether {}", start, tether),
+            Action::BreakWire { tether, segment, filament } =>
+                format!("t = {} s: break wire {} of tether {} at segment {}", start, filament, tether, segment),
+            Action::Thrusters { command } =>
+                format!("t = {} s: remote unit thrusters {}", start, command.name().to_lowercase()),
+        };
+    }
+}
+
+
+
+impl Timeline {
+
+    /// Applies every action that is due at the given simulated time.
+
+    pub fn apply (
+        &mut self,
+        clock:          quantities::Time,
+        esail_query:    &mut Query<(Entity, &mut spacecraft::esail::ESail)>,
+        craft_params:   &mut spacecraft::SpacecraftParameters,
+        solar_wind:     &mut solar_wind::SolarWind,
+        ) {
+
+        for scheduled in self.actions.iter_mut() {
+
+            if scheduled.finished || clock < scheduled.start {
+                continue;
+            }
+
+            let elapsed = clock - scheduled.start;
+
+            match &mut scheduled.action {
+
+                Action::Deploy { length, speed, deployed } => {
+
+                    // Whole elements that should be out by now
+                    let total   = (*length * craft_params.wire_resolution).value.round() as usize;
+                    let target  = ((elapsed * *speed * craft_params.wire_resolution).value.floor() as usize).min(total);
+
+                    let requested = target - *deployed;
+
+                    let moved = esail_query.iter_mut()
+                        .map(|(_, mut esail)| esail.deploy_esail(requested))
+                        .max()
+                        .unwrap_or(0);
+
+                    // Count what went out, not what was asked for: an empty reel or a cut tether
+                    // pays out less, and then there's nothing more to wait for
+                    *deployed += moved;
+                    scheduled.finished = *deployed == total || moved < requested;
+                },
+
+                Action::RampPotential { target, duration, initial } => {
+
+                    let initial = *initial.get_or_insert(craft_params.wire_potential);
+
+                    let fraction = if duration.value > 0.0 { (elapsed / *duration).value.min(1.0) } else { 1.0 };
+
+                    craft_params.wire_potential = initial + (*target - initial) * fraction;
+                    scheduled.finished = fraction >= 1.0;
+                },
+
+                Action::SolarWindSpeed { velocity } => {
+                    solar_wind.velocity = *velocity;
+                    scheduled.finished = true;
+                },
+
+                Action::CutTether { tether } => {
+
+                    let mut esails: Vec<(Entity, Mut<spacecraft::esail::ESail>)> = esail_query.iter_mut().collect();
+                    esails.sort_by_key(|(entity, _)| *entity);
+
+                    // Scenarios only have tether 0
+                    if let Some((_, esail)) = esails.get_mut(*tether) {
+                        esail.is_cut = true;
+                    }
+
+                    scheduled.finished = true;
+                },
+
+                Action::BreakWire { tether, segment, filament } => {
+
+                    let mut esails: Vec<(Entity, Mut<spacecraft::esail::ESail>)> = esail_query.iter_mut().collect();
+                    esails.sort_by_key(|(entity, _)| *entity);
+
+                    if let Some((_, esail)) = esails.get_mut(*tether) {
+                        if esail.graph.break_wire(*segment, *filament).is_none() {
+                            println!("Timeline: tether {} has no wire {} at segment {}", tether, filament, segment);
+                        }
+                    }
+
+                    scheduled.finished = true;
+                },
+
+                Action::Thrusters { command } => {
+                    craft_params.remote_unit.command = *command;
+                    scheduled.finished = true;
+                },
+            }
+
+            if scheduled.finished {
+                println!("Timeline: done with \"{}\"", scheduled.describe());
+            }
+        }
+    }
+}
@@ -1,113 +1,329 @@
 use bevy::prelude::*;
 use bevy::math::DVec3;
 
-use crate::{ physics, resources, solar_wind, spacecraft };
-
-use std::ops::{ Mul };
+use crate::{ components, physics, recorder, resources, solar_wind, spacecraft, spectrum };
 
+use uom::si::f64 as quantities;
 use uom::si::length::meter;
+use uom::si::angular_acceleration;
+
+use physics::vector3::ForceVector as ForceVector;
+use physics::vector3::PositionVector as PositionVector;
+use physics::vector3::AccelerationVector as AccelerationVector;
 
-use physics::force_vector::ForceVector as ForceVector;
-use physics::position_vector::PositionVector as PositionVector;
-use physics::acceleration_vector::AccelerationVector as AccelerationVector;
+// Relative shortening below which a segment still counts as taut. The constraints leave taut
+// segments a hair short now and then, when a neighbour pulls them in.
+const SLACK_TOLERANCE: f64 = 1.0e-3;
 
 
 pub fn verlet_simulation(
     time:                   Res<Time>, 
-    esail_query:            Query<&spacecraft::esail::ESail>,  
-    solar_wind:             Res<solar_wind::SolarWind>,
-    craft_params:           Res<spacecraft::SpacecraftParameters>,
+    mut esail_query:        Query<(Entity, &mut spacecraft::esail::ESail)>,  
+    mut solar_wind:         ResMut<solar_wind::SolarWind>,
+    mut craft_params:       ResMut<spacecraft::SpacecraftParameters>,
     mut verlet_query:       Query<&mut physics::verlet_object::VerletObject>,
     mut sim_params:         ResMut<resources::SimulationParameters>,
+    drag_params:            Res<physics::coulomb_drag::CoulombDragParameters>,
+    mut timeline:           ResMut<super::timeline::Timeline>,
+    mut recorder:           ResMut<recorder::Recorder>,
+    mut tip_history:        ResMut<spectrum::TipHistory>,
+    mut mass_query:         Query<&mut components::Mass>,
+    body_query:             Query<&components::RigidBody>,
+    mut remote_unit_query:  Query<&mut spacecraft::remote_unit::RemoteUnit>,
+    mut reel_query:         Query<&mut spacecraft::reel::Reel>,
+    mut spin_state:         ResMut<super::spin::SpinState>,
     ) {
 
-    let esail = esail_query.single();
-
     // Timesteps since last frame
     let timesteps = timestep_calculation(&time, &mut sim_params);
 
 
     for _ in 0..timesteps { 
 
+        // Scheduled actions, before anything moves
+
+        timeline.apply(sim_params.simulated_time, &mut esail_query, &mut craft_params, &mut solar_wind);
+
+        let (esail_entity, esail) = esail_query.single();
+
+        // Direction of the wire at every deployed element, before any of them moves. Some force
+        // models depend on it.
+
+        let wire_directions: Vec<DVec3> = esail.deployed_elements.iter().enumerate().map(|(index, entity)| {
+
+            let element_position = verlet_query.get(*entity).expect("No sail element found").current_coordinates;
+
+            let preceding_position = if index > 0 {
+                verlet_query.get(esail.deployed_elements[index - 1]).expect("No sail element found").current_coordinates
+            } else {
+                esail.origin
+            };
+
+            (element_position - preceding_position).to_dvec3()
+
+        }).collect();
+
+        // REMOTE UNIT: the thrusters fire as commanded, and the propellant they burn goes
+
+        let remote_unit_entity = *esail.elements.last().expect("E-sail without elements");
+
+        let mut remote_unit_mass    = craft_params.segment_mass();
+        let mut remote_unit_thrust  = ForceVector::zero();
+
+        if let Ok(mut remote_unit) = remote_unit_query.get_mut(remote_unit_entity) {
+
+            remote_unit.fire(&craft_params, verlet_query.get(remote_unit_entity).expect("No sail element found"), sim_params.timestep_s);
+
+            remote_unit_mass    = remote_unit.element_mass(&craft_params);
+            remote_unit_thrust  = remote_unit.thrust;
+
+            if let Ok(mut mass) = mass_query.get_mut(remote_unit_entity) {
+                mass.0 = remote_unit_mass;
+            }
+        }
+
+        // REEL: the wire still on it is its mass. Elements take their segment of wire along as they
+        // pay out, and give it back when they're taken up again.
+
+        if let Ok(mut reel) = reel_query.get_mut(esail_entity) {
+            reel.stowed = craft_params.segment_mass() * esail.undeployed_elements.len() as f64;
+        }
+
+        for entity in esail.undeployed_elements.iter() {
+            if let Ok(mut mass) = mass_query.get_mut(*entity) {
+                mass.0 = quantities::Mass::default();
+            }
+        }
+
+        for entity in esail.deployed_elements.iter().filter(|entity| **entity != remote_unit_entity) {
+            if let Ok(mut mass) = mass_query.get_mut(*entity) {
+                mass.0 = craft_params.segment_mass();
+            }
+        }
+
+        // SPIN: angular momentum of the spacecraft, before anything moves
+
+        let reel                = reel_query.get(esail_entity).expect("E-sail without a reel");
+        let spin                = super::spin::Spin::measure(esail, &craft_params, reel, &verlet_query.to_readonly(), &mass_query.to_readonly(), sim_params.timestep_s);
+        let angular_momentum    = spin.angular_momentum(craft_params.angular_velocity());
+        let angular_velocity    = craft_params.angular_velocity();
+
+        // Every element but the remote unit is a segment of wire
+        let element_mass = |entity: &Entity| if *entity == remote_unit_entity { remote_unit_mass } else { craft_params.segment_mass() };
+
         // VERLET INTEGRATION: Forces are calculated for every element
 
-        for entity in esail.deployed_elements.iter() {  // Iterating over esail DEPLOYED elements, in order.
+        let mut thrust          = ForceVector::zero();
+        let mut drag_torque     = quantities::Torque::default();
+        let mut thruster_torque = quantities::Torque::default();
+
+        // Deployed elements are the last ones of the list
+        let first_deployed = esail.elements.len() - esail.deployed_elements.len();
+
+        for (index, (entity, wire_direction)) in esail.deployed_elements.iter().zip(wire_directions).enumerate() {  // Iterating over esail DEPLOYED elements, in order.
 
             let mut verlet_object = verlet_query.get_mut(*entity).expect("No sail element found");
 
-            verlet_integration(&mut sim_params, &mut verlet_object, &craft_params, &solar_wind);
+            // The remote unit carries half a segment of wire, beyond the end of the tether
+            let (wire_share, applied_force) = if *entity == remote_unit_entity { (0.5, remote_unit_thrust) } else { (1.0, ForceVector::zero()) };
+
+            // The element is the end of segment first_deployed + index, with whatever wires of it are left
+            let wires = esail.graph.wires(first_deployed + index);
+
+            let position = verlet_object.current_coordinates.to_dvec3();
+
+            let coulomb_force = verlet_integration(&mut sim_params, &mut verlet_object, &craft_params, &solar_wind, &drag_params, wire_direction,
+                                                   wires, element_mass(entity), wire_share, applied_force, spin_state.angular_acceleration);
+
+            thrust += coulomb_force;
+
+            // Torques on the spacecraft, as long as the tether hangs from it
+            if !esail.is_cut {
+                drag_torque     += super::spin::torque(&craft_params, position, coulomb_force);
+                thruster_torque += super::spin::torque(&craft_params, position, applied_force);
+            }
 
             //println!("Verlet force: {:?}", verlet_object.current_force);
         }
 
         // CONSTRAINT LOOP
 
-        for _ in 0..sim_params.iterations {
+        // A tether can pull but not push: segments, and the other lines of the constraint graph, are
+        // only corrected when they're longer than their length, and shorter ones are left slack,
+        // free to buckle.
 
-            //for index in 1..esail.elements.len() {  // Skipping first item
-            for index in 0..esail.elements.len() {  // Why are these two the same!?
+        // How far every element has been pulled back towards the preceding one, over all the
+        // iterations (in meters), over its weight. That's what the segment had to do to keep its
+        // length, so segment mass * pull / dt² is its tension.
+        let mut pulls: Vec<f64> = vec![0.0; esail.elements.len()];
 
-                // Relative position between element and preceding element, as a PositionVector
-                let relative_position_between_elements = esail.vector_to_previous_element(index, &verlet_query);
+        // Inverse masses relative to a segment, none in the reel, which holds the tether
+        let weights: Vec<f64> = esail.elements.iter().enumerate().map(|(index, entity)| {
+            if index >= first_deployed { (craft_params.segment_mass() / element_mass(entity)).value } else { 0.0 }
+        }).collect();
 
-                // Desired distance between elements (in meters)
-                let desired_relative_position_between_elements = craft_params.segment_length();
+        let tolerance = match sim_params.iteration_mode {
+            resources::IterationMode::Fixed     => None,
+            resources::IterationMode::Residual  => Some(sim_params.iteration_tolerance),
+        };
 
-                // Correction calculation
-                let distance_between_elements = relative_position_between_elements.clone().length();
+        // The tridiagonal and red–black solvers only know the segments of one tether. Anything more
+        // in the graph needs the general sweep.
+        let solver = if esail.graph.is_chain() { sim_params.constraint_solver } else { resources::ConstraintSolver::GaussSeidel };
 
-                let difference = if distance_between_elements.get::<meter>() > 0.0 {
-                    (desired_relative_position_between_elements.get::<meter>() - distance_between_elements.get::<meter>())
-                        / distance_between_elements.get::<meter>()
-                } else {
-                    0.0
-                };
+        let iterations_used = match solver {
 
-                let correction_vector = relative_position_between_elements.mul(0.5 * difference);
+            resources::ConstraintSolver::Tridiagonal => super::chain_solver::solve(
+                esail, &mut verlet_query, first_deployed, craft_params.segment_length().get::<meter>(), &weights,
+                sim_params.iterations, tolerance, &mut pulls,
+            ),
 
-                // UPDATING POSITIONS
-                
-                let mut current_verlet_object = verlet_query.get_mut(esail.elements[index]).expect("No sail element found");
+            resources::ConstraintSolver::RedBlack => super::red_black::solve(
+                esail, &mut verlet_query, first_deployed, craft_params.segment_length().get::<meter>(), &weights,
+                sim_params.iterations, tolerance, &mut pulls,
+                if sim_params.threads > 0 { sim_params.threads } else { super::red_black::available_threads() },
+            ),
 
-                current_verlet_object.correct_current_coordinates(correction_vector.clone());
+            resources::ConstraintSolver::GaussSeidel => super::graph_solver::solve(
+                esail, &mut verlet_query, &body_query, first_deployed, craft_params.segment_length().get::<meter>(), &weights,
+                sim_params.iterations, tolerance, &mut pulls,
+            ),
+        };
 
-                // Changing previous element if previous element is not the first.
-                if index > 0 {
-                    let mut preceding_verlet_object = verlet_query.get_mut(esail.elements[index - 1]).expect("No previous sail element found");
-                    if preceding_verlet_object.is_deployed {
-                        // Maybe a method to give the negative?
-                        preceding_verlet_object.correct_current_coordinates(correction_vector.mul(-1.0));
-                    }
-                }
+        sim_params.iterations_used = iterations_used;
+
+        // TENSION
+
+        let timestep = sim_params.timestep_s;
+
+        for (entity, pull) in esail.elements.iter().zip(pulls) {
+            let mut verlet_object = verlet_query.get_mut(*entity).expect("No sail element found");
+            verlet_object.tension = craft_params.segment_mass() * quantities::Length::new::<meter>(pull) / (timestep * timestep);
+        }
+
+        // RESIDUAL: how much the deployed segments are still stretched, and which ones are slack
+
+        let segment_length = craft_params.segment_length().get::<meter>();
+
+        // A cut tether has no segment to the reel
+        let first_segment = first_deployed + if esail.is_cut { 1 } else { 0 };
+
+        let mut residual    = 0.0_f64;
+        let mut slack       = 0;
+
+        for index in 0..esail.elements.len() {
+
+            // Elements in the reel, or cut loose from it, have no segment to measure, and neither
+            // do those after a broken one
+            if index < first_segment.max(1) || esail.graph.broken[index - 1] {
+                verlet_query.get_mut(esail.elements[index]).expect("No sail element found").is_slack = false;
+                continue;
+            }
+
+            let stretch = (esail.vector_to_previous_element(index, &verlet_query).norm().get::<meter>() - segment_length) / segment_length;
+            let is_slack = stretch < -SLACK_TOLERANCE;
+
+            residual = residual.max(stretch);
+            if is_slack {
+                slack += 1;
+            }
+
+            verlet_query.get_mut(esail.elements[index]).expect("No sail element found").is_slack = is_slack;
+        }
+
+        // And the rest of the graph, slack lines aside
+        for number in esail.graph.segments..esail.graph.constraints.len() {
+            if let Some(error) = super::graph_solver::relative_error(number, esail, &verlet_query, &body_query, first_deployed, segment_length) {
+                residual = residual.max(error);
             }
         }
+
+        sim_params.constraint_residual  = residual;
+        sim_params.slack_segments       = slack;
+
+        // SPIN: a free spacecraft spins at whatever rate keeps its angular momentum, plus what the
+        // torques added over the timestep
+
+        let angular_momentum    = angular_momentum + super::spin::impulse(drag_torque + thruster_torque, timestep);
+        let reel                = reel_query.get(esail_entity).expect("E-sail without a reel");
+        let spin                = super::spin::Spin::measure(esail, &craft_params, reel, &verlet_query.to_readonly(), &mass_query.to_readonly(), timestep);
+
+        if craft_params.spin_mode == spacecraft::SpinMode::Free {
+            craft_params.rpm = spin.angular_velocity(angular_momentum) / (2.0 * std::f64::consts::PI);    // Radians per second to cycles per second
+        }
+
+        // Radians per second squared, uom keeps angular accelerations apart from frequencies over time
+        let angular_acceleration = (craft_params.angular_velocity() - angular_velocity) / timestep;
+
+        *spin_state = super::spin::SpinState {
+            angular_momentum:   spin.angular_momentum(craft_params.angular_velocity()),
+            moment_of_inertia:  spin.moment_of_inertia,
+            drag_torque:        drag_torque,
+            thrust_torque:      thruster_torque,
+            angular_acceleration: quantities::AngularAcceleration::new::<angular_acceleration::radian_per_second_squared>(angular_acceleration.value),
+        };
+
+        sim_params.simulated_time += timestep;
+
+        let (_, mut esail) = esail_query.single_mut();
+        esail.total_force = thrust;
+
+        let reel = reel_query.get(esail_entity).expect("E-sail without a reel");
+
+        recorder.record_step(&sim_params, &craft_params, &esail, reel, &verlet_query, &mass_query.to_readonly());
+        tip_history.record_step(&sim_params, &craft_params, &esail, &verlet_query);
     }
 }
 
-/// Updates the position of a verlet object
+/// Updates the position of a verlet object, and returns the Coulomb drag on it. The element carries
+/// wire_share of a segment of wire, of which the given number of wires are whole, and the applied
+/// force comes on top, thrusters say. The spinning frame speeds up at the given angular acceleration.
 fn verlet_integration(
     sim_params:     &mut ResMut<resources::SimulationParameters>,
     verlet_object:  &mut physics::verlet_object::VerletObject,
-    craft_params:   &Res<spacecraft::SpacecraftParameters>,
-    solar_wind:     &Res<solar_wind::SolarWind>,
-    ){
+    craft_params:   &spacecraft::SpacecraftParameters,
+    solar_wind:     &solar_wind::SolarWind,
+    drag_params:    &Res<physics::coulomb_drag::CoulombDragParameters>,
+    wire_direction: DVec3,
+    wires:          usize,
+    mass:           quantities::Mass,
+    wire_share:     f64,
+    applied_force:  ForceVector,
+    angular_acceleration: quantities::AngularAcceleration,
+    ) -> ForceVector {
+
+    // Forces per verlet (so, per segment of wire, and the remote unit on top of it at the tip)
+
+    // Centrifugal force, away from the spin axis (which goes through the center of the spacecraft)
 
-    // Forces per verlet (so, per segment)
+    let rotation_axis   = craft_params.rotation_axis.normalize();
+    let position        = verlet_object.current_coordinates.to_dvec3();
+    let from_axis       = position - rotation_axis * position.dot(rotation_axis);
 
-    // Centrifugal force (Along x for now, this needs to change)
+    // m * ω², in SI. Multiplying it by the distance to the axis gives the force, in newtons
+    let centrifugal_stiffness = (mass * craft_params.angular_velocity() * craft_params.angular_velocity()).value;
 
-    let centrifugal_force_magnitude = craft_params.segment_mass() * verlet_object.current_coordinates.clone().length() 
-        * craft_params.angular_velocity() * craft_params.angular_velocity();
+    let centrifugal_force = ForceVector::from_dvec3(from_axis * centrifugal_stiffness);
 
-    let centrifugal_force_direction = DVec3::new(1.0, 0.0, 0.0);
+    // Coriolis force of moving in the spinning frame, -2m ω × v, and Euler force of the frame
+    // speeding up or slowing down, -m dω/dt × r. In SI.
 
-    let centrifugal_force = ForceVector::from_direction(centrifugal_force_magnitude, centrifugal_force_direction);
+    let angular_velocity    = rotation_axis * craft_params.angular_velocity().value;
+    let velocity            = (verlet_object.current_coordinates - verlet_object.previous_coordinates).to_dvec3() / sim_params.timestep;
+
+    let coriolis_force      = ForceVector::from_dvec3(-2.0 * mass.value * angular_velocity.cross(velocity));
+    let euler_force         = ForceVector::from_dvec3(-mass.value * (rotation_axis * angular_acceleration.value).cross(position));
 
     // Coulomb drag force
     
-    let coulomb_force_magnitude= coulomb_force_per_meter(&solar_wind, &craft_params) * craft_params.segment_length();
+    // Drag of the wires of the segment that aren't broken, none where they all are
+
+    let coulomb_force_per_meter = match wires {
+        0 => physics::vector3::Vector3::zero(),
+        _ => drag_params.model.force_per_meter_vector(solar_wind, craft_params, drag_params, wire_direction, craft_params.radius_of_wires(wires)),
+    };
 
-    let coulomb_force = ForceVector::from_direction(coulomb_force_magnitude, solar_wind.direction); 
+    let coulomb_force: ForceVector = coulomb_force_per_meter * (craft_params.segment_length() * wire_share);
 
     // Stiffness reaction force here?
     // A function on verlet_object should do this? passing a verlet query? Not verlet_object, wait. ESail maybe?
@@ -117,17 +333,18 @@ fn verlet_integration(
 
     // Total force
 
-    let total_force = coulomb_force + centrifugal_force;    // This is a ForceVector containing uom quantities
+    let total_force = coulomb_force + centrifugal_force + coriolis_force + euler_force + applied_force;    // This is a ForceVector containing uom quantities
 
-    verlet_object.current_force = total_force.clone();
+    verlet_object.current_force = total_force;
+    verlet_object.coulomb_force = coulomb_force;
 
-    let acc_vector = AccelerationVector::from_force(total_force.clone(), craft_params.segment_mass());
+    let acc_vector: AccelerationVector = total_force / mass;
 
-    let delta_from_acc = PositionVector::from_acceleration(acc_vector, sim_params.timestep_s);
+    let delta_from_acc: PositionVector = acc_vector * sim_params.timestep_s * sim_params.timestep_s;
  
 
     // Next position calculation (formula from here: https://www.algorithm-archive.org/contents/verlet_integration/verlet_integration.html)
-    let next_coordinates = verlet_object.current_coordinates.clone().mul(2.0) - verlet_object.previous_coordinates.clone() + delta_from_acc;
+    let next_coordinates = verlet_object.current_coordinates * 2.0 - verlet_object.previous_coordinates + delta_from_acc;
 
     // Damping here, before the update of coordinates?
 
@@ -135,6 +352,7 @@ fn verlet_integration(
     // Updating verlet coordinates
     verlet_object.update_coordinates(next_coordinates);
 
+    return coulomb_force;
 }
 
 /// Calculates how many timesteps should happen in the current frame, considering any potential unspent time from the previous frame.
@@ -152,33 +370,3 @@ fn timestep_calculation(
 
     return timesteps;
 }
-
-// From janhunen2007, equation 8. Corroborate all the results. And recheck the equations too.
-// Should this go inside the physics folder, in its own file?
-#[allow(non_snake_case)]
-pub fn coulomb_force_per_meter( 
-    solar_wind:         &Res<solar_wind::SolarWind>, 
-    spacecraft:         &Res<spacecraft::SpacecraftParameters>,
-    ) -> uom::si::f64::RadiantExposure {    // Radiant exposure is [mass][time]⁻²
-
-    // First: r_0, distance at which the potential vanishes
-    let r0_numerator    = resources::EPSILON_0 * solar_wind.T_e;
-    let r0_denominator  = solar_wind.n_0 * resources::Q_E * resources::Q_E; 
-    let r_0             = 2.0 * (r0_numerator / r0_denominator).sqrt();    
-
-    // Second: r_s, stopping distance of protons
-    let exp_numerator   = resources::M_PROTON * solar_wind.velocity * solar_wind.velocity * (r_0 / spacecraft.wire_radius).ln();
-    let exp_denominator = resources::Q_E * spacecraft.wire_potential; 
-    let exp             = (exp_numerator / exp_denominator).exp();  
-    let rs_denominator  = (exp.value - 1.0).sqrt();
-    let r_s             = r_0 / rs_denominator;
-
-    // Third: force per unit length
-    let K = 3.09;   // Empirical, from Monte Carlo sims, I need to calculate this myself somehow.
-
-    let force_per_unit_length = r_s * K * resources::M_PROTON * solar_wind.n_0 * solar_wind.velocity * solar_wind.velocity;
-
-    //println!("{}: {:?}", "Force per meter", force_per_unit_length); 
-
-    return force_per_unit_length;
-}
@@ -0,0 +1,581 @@
+// Snapshots: the full state of a run, saved to a RON file so that it can be continued later exactly
+// where it was. Useful to start parameter studies from a sail that has already settled, instead of
+// waiting minutes for it every time.
+//
+// A snapshot holds every VerletObject, the deployed and undeployed lists of the E-sail, the
+// spacecraft, solar wind and simulation parameters, and the simulation clock. Elements are saved in
+// the order of ESail::elements, and the lists as indices into it, so loading spawns new entities and
+// points the lists at them. The remote unit at the tip keeps the propellant it has left, and the
+// reel gets the wire of the undeployed elements back. The constraints beyond the segments of the
+// tether are saved with the spacecraft parameters, and the constraint graph is built again from them.
+//
+// The Coulomb drag settings and the timeline are scenario material, and are left as they are. How far
+// the timeline has got is saved though (which actions are done, how much each deploy has paid out,
+// where each ramp started), and put back on the timeline loaded if it has as many actions; a different
+// timeline starts from the beginning. Of the spin, the rate is with the spacecraft and the angular
+// momentum is measured again from it and the elements every timestep, but the change of the rate
+// over the last timestep is saved, for the Euler force of the next one.
+//
+// All quantities are stored as plain numbers in SI base units, with every digit, so that a restored
+// run continues bit for bit.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy::math::DVec3;
+use serde::{ Deserialize, Serialize };
+
+use crate::{ components, physics, resources, simulation, solar_wind, spacecraft };
+use physics::vector3::{ ForceVector, PositionVector };
+
+use uom::si::f64 as quantities;
+use uom::si::*;
+
+const SNAPSHOT_VERSION: u32 = 10;
+
+pub struct SnapshotPlugin;
+
+impl Plugin for SnapshotPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(SnapshotState{..Default::default()})
+            .add_event::<SnapshotRequest>()
+            .add_systems(
+                PreUpdate,      // So that the new E-sail is in place before the simulation runs
+                handle_snapshot_requests
+            )
+        ;
+    }
+}
+
+/// Snapshot file shown in the GUI, and the result of the last load or save.
+
+#[derive(Resource)]
+pub struct SnapshotState {
+    pub path:   String,
+    pub status: String,
+}
+
+impl Default for SnapshotState {
+    fn default() -> Self {
+        SnapshotState {
+            path:   String::from("esme_snapshot.ron"),
+            status: String::new(),
+        }
+    }
+}
+
+#[derive(Event)]
+pub enum SnapshotRequest {
+    Load,
+    Save,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Snapshot {
+    pub version:        u32,
+    pub simulation:     SimulationState,
+    pub spacecraft:     SpacecraftState,
+    pub solar_wind:     SolarWindState,
+    pub esail:          ESailState,
+    pub timeline:       Vec<ActionProgress>,    // One per action of the timeline, in order
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SimulationState {
+    pub simulated_time:     f64,
+    pub leftover_time:      f64,
+    pub timestep:           f64,
+    pub constraint_solver:  resources::ConstraintSolver,
+    pub iterations:         i32,
+    pub iteration_mode:     resources::IterationMode,
+    pub iteration_tolerance: f64,
+    pub debug:              bool,
+    pub com_visibility:     bool,
+    pub axes_visibility:    bool,
+    pub pixels_per_meter:   i32,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SpacecraftState {
+    pub rpm:                f64,    // Hz, like every uom frequency. Where the spin is at, if it's free.
+    pub angular_acceleration: f64,  // Of the spin over the last timestep, in rad/s²
+    pub spin_mode:          spacecraft::SpinMode,
+    pub rotation_axis:      [f64; 3],
+    pub wire_length:        f64,
+    pub wire_radius:        f64,
+    pub wire_density:       f64,
+    pub wire_potential:     f64,
+    pub wire_resolution:    f64,
+    pub body_size:          f64,
+    pub body_mass:          f64,
+    pub esail_origin:       [f64; 3],
+    pub tether_type:        spacecraft::TetherType,
+    pub filaments:          usize,
+    pub bond_spacing:       f64,
+    pub loop_slack:         f64,
+    pub links:              Vec<physics::constraint_graph::Link>,
+    pub remote_unit:        RemoteUnitState,
+    pub reel:               ReelState,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RemoteUnitState {
+    pub dry_mass:           f64,
+    pub propellant:         f64,    // At the start of the run, what's left is in ESailState
+    pub auxiliary_tether:   f64,
+    pub auxiliary_tether_density: f64,
+    pub thruster:           spacecraft::remote_unit::ThrusterType,
+    pub max_thrust:         f64,
+    pub damping_gain:       f64,
+    pub command:            spacecraft::remote_unit::ThrustCommand,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ReelState {
+    pub mass:               f64,    // Empty, the wire on it is counted from the undeployed elements
+    pub radius:             f64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SolarWindState {
+    pub n_0:                f64,
+    pub velocity:           f64,
+    pub direction:          [f64; 3],
+    pub t_e:                f64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ESailState {
+    pub origin:             [f64; 3],
+    pub total_force:        [f64; 3],
+    pub is_cut:             bool,
+    pub broken:             Vec<usize>,     // Constraints of the graph, see physics/constraint_graph.rs
+    pub propellant:         f64,            // Left in the remote unit
+    pub elements:           Vec<ElementState>,
+    pub deployed:           Vec<usize>,     // Indices into elements, in the order of ESail::deployed_elements
+    pub undeployed:         Vec<usize>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ActionProgress {
+    pub finished:           bool,
+    pub deployed:           usize,          // Elements paid out, for a deploy
+    pub initial:            Option<f64>,    // Potential the ramp started from, for a potential ramp
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ElementState {
+    pub previous:           [f64; 3],
+    pub current:            [f64; 3],
+    pub is_deployed:        bool,
+    pub force:              [f64; 3],
+    pub tension:            f64,
+    pub mass:               f64,
+}
+
+
+
+impl Snapshot {
+
+    /// Takes the state of the world as it is now.
+
+    pub fn capture (
+        sim_params:     &resources::SimulationParameters,
+        craft_params:   &spacecraft::SpacecraftParameters,
+        solar_wind:     &solar_wind::SolarWind,
+        esail:          &spacecraft::esail::ESail,
+        verlet_query:   &Query<&physics::verlet_object::VerletObject>,
+        mass_query:     &Query<&components::Mass>,
+        remote_unit:    &spacecraft::remote_unit::RemoteUnit,
+        timeline:       &simulation::timeline::Timeline,
+        spin_state:     &simulation::spin::SpinState,
+        ) -> Self {
+
+        let indices: HashMap<Entity, usize> = esail.elements.iter().enumerate().map(|(index, entity)| (*entity, index)).collect();
+
+        let elements = esail.elements.iter().map(|entity| {
+
+            let verlet_object   = verlet_query.get(*entity).expect("No sail element found");
+            let mass            = mass_query.get(*entity).expect("Sail element without mass");
+
+            ElementState {
+                previous:       verlet_object.previous_coordinates.to_dvec3().to_array(),
+                current:        verlet_object.current_coordinates.to_dvec3().to_array(),
+                is_deployed:    verlet_object.is_deployed,
+                force:          verlet_object.current_force.to_dvec3().to_array(),
+                tension:        verlet_object.tension.get::<force::newton>(),
+                mass:           mass.0.get::<mass::kilogram>(),
+            }
+        }).collect();
+
+        return Snapshot {
+            version:    SNAPSHOT_VERSION,
+            simulation: SimulationState {
+                simulated_time:     sim_params.simulated_time.get::<time::second>(),
+                leftover_time:      sim_params.leftover_time,
+                timestep:           sim_params.timestep,
+                constraint_solver:  sim_params.constraint_solver,
+                iterations:         sim_params.iterations,
+                iteration_mode:     sim_params.iteration_mode,
+                iteration_tolerance: sim_params.iteration_tolerance,
+                debug:              sim_params.debug,
+                com_visibility:     sim_params.com_visibility,
+                axes_visibility:    sim_params.axes_visibility,
+                pixels_per_meter:   sim_params.pixels_per_meter,
+            },
+            spacecraft: SpacecraftState {
+                rpm:                craft_params.rpm.get::<frequency::hertz>(),
+                angular_acceleration: spin_state.angular_acceleration.get::<angular_acceleration::radian_per_second_squared>(),
+                spin_mode:          craft_params.spin_mode,
+                rotation_axis:      craft_params.rotation_axis.to_array(),
+                wire_length:        craft_params.wire_length.get::<length::meter>(),
+                wire_radius:        craft_params.wire_radius.get::<length::meter>(),
+                wire_density:       craft_params.wire_density.get::<mass_density::kilogram_per_cubic_meter>(),
+                wire_potential:     craft_params.wire_potential.get::<electric_potential::volt>(),
+                wire_resolution:    craft_params.wire_resolution.get::<linear_number_density::per_meter>(),
+                body_size:          craft_params.body_size.get::<length::meter>(),
+                body_mass:          craft_params.body_mass.get::<mass::kilogram>(),
+                esail_origin:       craft_params.esail_origin.to_dvec3().to_array(),
+                tether_type:        craft_params.tether_type,
+                filaments:          craft_params.filaments,
+                bond_spacing:       craft_params.bond_spacing.get::<length::meter>(),
+                loop_slack:         craft_params.loop_slack,
+                links:              craft_params.links.clone(),
+                remote_unit:        RemoteUnitState {
+                    dry_mass:           craft_params.remote_unit.dry_mass.get::<mass::kilogram>(),
+                    propellant:         craft_params.remote_unit.propellant.get::<mass::kilogram>(),
+                    auxiliary_tether:   craft_params.remote_unit.auxiliary_tether.get::<length::meter>(),
+                    auxiliary_tether_density: craft_params.remote_unit.auxiliary_tether_density.get::<linear_mass_density::kilogram_per_meter>(),
+                    thruster:           craft_params.remote_unit.thruster,
+                    max_thrust:         craft_params.remote_unit.max_thrust.get::<force::newton>(),
+                    damping_gain:       craft_params.remote_unit.damping_gain.get::<frequency::hertz>(),
+                    command:            craft_params.remote_unit.command,
+                },
+                reel:               ReelState {
+                    mass:               craft_params.reel.mass.get::<mass::kilogram>(),
+                    radius:             craft_params.reel.radius.get::<length::meter>(),
+                },
+            },
+            solar_wind: SolarWindState {
+                n_0:                solar_wind.n_0.get::<volumetric_number_density::per_cubic_meter>(),
+                velocity:           solar_wind.velocity.get::<velocity::meter_per_second>(),
+                direction:          solar_wind.direction.to_array(),
+                t_e:                solar_wind.T_e.get::<energy::joule>(),
+            },
+            esail: ESailState {
+                origin:             esail.origin.to_dvec3().to_array(),
+                total_force:        esail.total_force.to_dvec3().to_array(),
+                is_cut:             esail.is_cut,
+                broken:             esail.graph.broken.iter().enumerate().filter(|(_, broken)| **broken).map(|(number, _)| number).collect(),
+                propellant:         remote_unit.propellant.get::<mass::kilogram>(),
+                elements:           elements,
+                deployed:           esail.deployed_elements.iter().map(|entity| indices[entity]).collect(),
+                undeployed:         esail.undeployed_elements.iter().map(|entity| indices[entity]).collect(),
+            },
+            timeline:   timeline.actions.iter().map(ActionProgress::of).collect(),
+        };
+    }
+
+    /// Overwrites the resources and spawns the saved E-sail, returning its entity. The E-sail that
+    /// was there has to be despawned by the caller.
+
+    pub fn restore (
+        &self,
+        commands:       &mut Commands,
+        meshes:         &mut ResMut<Assets<Mesh>>,
+        materials:      &mut ResMut<Assets<StandardMaterial>>,
+        sim_params:     &mut resources::SimulationParameters,
+        craft_params:   &mut spacecraft::SpacecraftParameters,
+        solar_wind:     &mut solar_wind::SolarWind,
+        spin_state:     &mut simulation::spin::SpinState,
+        ) -> Entity {
+
+        let simulation = &self.simulation;
+
+        *sim_params = resources::SimulationParameters {
+            constraint_solver:  simulation.constraint_solver,
+            iterations:         simulation.iterations,
+            iteration_mode:     simulation.iteration_mode,
+            iteration_tolerance: simulation.iteration_tolerance,
+            iterations_used:    0,      // Counted again at the next timestep
+            threads:            sim_params.threads,     // A property of this machine, not of the run
+            timestep:           simulation.timestep,
+            timestep_s:         quantities::Time::new::<time::second>(simulation.timestep),
+            leftover_time:      simulation.leftover_time,
+            simulated_time:     quantities::Time::new::<time::second>(simulation.simulated_time),
+            constraint_residual: 0.0,   // Measured again at the next timestep
+            slack_segments:     0,
+            debug:              simulation.debug,
+            com_visibility:     simulation.com_visibility,
+            axes_visibility:    simulation.axes_visibility,
+            pixels_per_meter:   simulation.pixels_per_meter,
+        };
+
+        let craft = &self.spacecraft;
+
+        *craft_params = spacecraft::SpacecraftParameters {
+            rpm:                quantities::Frequency::new::<frequency::hertz>(craft.rpm),
+            spin_mode:          craft.spin_mode,
+            rotation_axis:      DVec3::from_array(craft.rotation_axis),
+            wire_length:        quantities::Length::new::<length::meter>(craft.wire_length),
+            wire_radius:        quantities::Length::new::<length::meter>(craft.wire_radius),
+            wire_density:       quantities::MassDensity::new::<mass_density::kilogram_per_cubic_meter>(craft.wire_density),
+            wire_potential:     quantities::ElectricPotential::new::<electric_potential::volt>(craft.wire_potential),
+            wire_resolution:    quantities::LinearNumberDensity::new::<linear_number_density::per_meter>(craft.wire_resolution),
+            body_size:          quantities::Length::new::<length::meter>(craft.body_size),
+            body_mass:          quantities::Mass::new::<mass::kilogram>(craft.body_mass),
+            esail_origin:       PositionVector::from_dvec3(DVec3::from_array(craft.esail_origin)),
+            tether_type:        craft.tether_type,
+            filaments:          craft.filaments,
+            bond_spacing:       quantities::Length::new::<length::meter>(craft.bond_spacing),
+            loop_slack:         craft.loop_slack,
+            links:              craft.links.clone(),
+            remote_unit:        spacecraft::remote_unit::RemoteUnitParameters {
+                dry_mass:           quantities::Mass::new::<mass::kilogram>(craft.remote_unit.dry_mass),
+                propellant:         quantities::Mass::new::<mass::kilogram>(craft.remote_unit.propellant),
+                auxiliary_tether:   quantities::Length::new::<length::meter>(craft.remote_unit.auxiliary_tether),
+                auxiliary_tether_density: quantities::LinearMassDensity::new::<linear_mass_density::kilogram_per_meter>(craft.remote_unit.auxiliary_tether_density),
+                thruster:           craft.remote_unit.thruster,
+                max_thrust:         quantities::Force::new::<force::newton>(craft.remote_unit.max_thrust),
+                damping_gain:       quantities::Frequency::new::<frequency::hertz>(craft.remote_unit.damping_gain),
+                command:            craft.remote_unit.command,
+            },
+            reel:               spacecraft::reel::ReelParameters {
+                mass:               quantities::Mass::new::<mass::kilogram>(craft.reel.mass),
+                radius:             quantities::Length::new::<length::meter>(craft.reel.radius),
+            },
+        };
+
+        // The rest of the spin state is measured again at the next timestep
+        *spin_state = simulation::spin::SpinState {
+            angular_acceleration: quantities::AngularAcceleration::new::<angular_acceleration::radian_per_second_squared>(craft.angular_acceleration),
+            ..Default::default()
+        };
+
+        let wind = &self.solar_wind;
+
+        *solar_wind = solar_wind::SolarWind {
+            n_0:                quantities::VolumetricNumberDensity::new::<volumetric_number_density::per_cubic_meter>(wind.n_0),
+            velocity:           quantities::Velocity::new::<velocity::meter_per_second>(wind.velocity),
+            direction:          DVec3::from_array(wind.direction),
+            T_e:                quantities::Energy::new::<energy::joule>(wind.t_e),
+        };
+
+        let elements = self.esail.elements.iter().map(|element| (
+            physics::verlet_object::VerletObject {
+                previous_coordinates:   PositionVector::from_dvec3(DVec3::from_array(element.previous)),
+                current_coordinates:    PositionVector::from_dvec3(DVec3::from_array(element.current)),
+                is_deployed:            element.is_deployed,
+                current_force:          ForceVector::from_dvec3(DVec3::from_array(element.force)),
+                coulomb_force:          ForceVector::zero(),    // Computed again at the next timestep
+                tension:                quantities::Force::new::<force::newton>(element.tension),
+                is_slack:               false,  // Measured again at the next timestep
+            },
+            quantities::Mass::new::<mass::kilogram>(element.mass),
+        )).collect();
+
+        return spacecraft::esail::restore_esail(
+            commands, meshes, materials, craft_params,
+            elements, &self.esail.deployed, &self.esail.undeployed,
+            PositionVector::from_dvec3(DVec3::from_array(self.esail.origin)),
+            ForceVector::from_dvec3(DVec3::from_array(self.esail.total_force)),
+            self.esail.is_cut,
+            &self.esail.broken,
+            spacecraft::remote_unit::RemoteUnit {
+                propellant: quantities::Mass::new::<mass::kilogram>(self.esail.propellant),
+                thrust:     ForceVector::zero(),
+            },
+        );
+    }
+
+    /// Puts the progress back on the timeline, if it's the one that was saved, or one with as many
+    /// actions. Returns whether it did.
+
+    pub fn restore_timeline (&self, timeline: &mut simulation::timeline::Timeline) -> bool {
+
+        if timeline.actions.len() != self.timeline.len() {
+            return false;
+        }
+
+        for (scheduled, progress) in timeline.actions.iter_mut().zip(self.timeline.iter()) {
+            progress.apply(scheduled);
+        }
+
+        return true;
+    }
+
+    /// Checks that the file is one this version can load, and that its lists are consistent.
+
+    fn validate (&self) -> Result<(), String> {
+
+        if self.version != SNAPSHOT_VERSION {
+            return Err(format!("snapshot version {}, this program reads version {}", self.version, SNAPSHOT_VERSION));
+        }
+
+        let number_of_elements = self.esail.elements.len();
+
+        if number_of_elements == 0 {
+            return Err(String::from("the E-sail has no elements"));
+        }
+
+        // The graph and the reel are built again from the parameters, for as many elements as they give
+        let parameters = spacecraft::SpacecraftParameters {
+            wire_length:        quantities::Length::new::<length::meter>(self.spacecraft.wire_length),
+            wire_resolution:    quantities::LinearNumberDensity::new::<linear_number_density::per_meter>(self.spacecraft.wire_resolution),
+            ..Default::default()
+        };
+
+        if number_of_elements != parameters.number_of_esail_elements().max(0) as usize {
+            return Err(format!("the E-sail has {} elements, its wire length and resolution give {}", number_of_elements, parameters.number_of_esail_elements()));
+        }
+
+        // The reel always keeps the element the tether hangs from
+        if self.esail.undeployed.is_empty() {
+            return Err(String::from("the E-sail has no undeployed element"));
+        }
+
+        // Everything takes the deployed elements to be the last ones, in order, with the remote unit
+        // at the end, and the reel pays out the last undeployed one first
+        let first_deployed = number_of_elements.saturating_sub(self.esail.deployed.len());
+
+        if !self.esail.deployed.iter().copied().eq(first_deployed..number_of_elements) {
+            return Err(format!("the deployed elements aren't {} to {} in order", first_deployed, number_of_elements - 1));
+        }
+
+        if !self.esail.undeployed.iter().copied().eq(0..first_deployed) {
+            return Err(format!("the undeployed elements aren't 0 to {} in order", first_deployed.saturating_sub(1)));
+        }
+
+        if let Some(element) = self.spacecraft.links.iter().flat_map(|link| link.elements()).find(|element| *element >= number_of_elements) {
+            return Err(format!("a constraint holds element {}, there are {}", element, number_of_elements));
+        }
+
+        return Ok(());
+    }
+}
+
+
+
+impl ActionProgress {
+
+    fn of (scheduled: &simulation::timeline::ScheduledAction) -> Self {
+
+        use simulation::timeline::Action;
+
+        let (deployed, initial) = match &scheduled.action {
+            Action::Deploy { deployed, .. }        => (*deployed, None),
+            Action::RampPotential { initial, .. }  => (0, initial.map(|potential| potential.get::<electric_potential::volt>())),
+            _                                       => (0, None),
+        };
+
+        return ActionProgress {
+            finished:   scheduled.finished,
+            deployed:   deployed,
+            initial:    initial,
+        };
+    }
+
+    fn apply (&self, scheduled: &mut simulation::timeline::ScheduledAction) {
+
+        use simulation::timeline::Action;
+
+        scheduled.finished = self.finished;
+
+        match &mut scheduled.action {
+            Action::Deploy { deployed, .. }        => *deployed = self.deployed,
+            Action::RampPotential { initial, .. }  => *initial = self.initial.map(quantities::ElectricPotential::new::<electric_potential::volt>),
+            _                                       => {},
+        }
+    }
+}
+
+
+
+pub fn load_snapshot (path: &str) -> Result<Snapshot, String> {
+
+    let text = std::fs::read_to_string(path).map_err(|error| error.to_string())?;
+
+    let snapshot: Snapshot = ron::from_str(&text).map_err(|error| error.to_string())?;
+
+    snapshot.validate()?;
+
+    return Ok(snapshot);
+}
+
+pub fn save_snapshot (path: &str, snapshot: &Snapshot) -> Result<(), String> {
+
+    let text = ron::ser::to_string_pretty(snapshot, ron::ser::PrettyConfig::default()).map_err(|error| error.to_string())?;
+
+    return std::fs::write(path, text).map_err(|error| error.to_string());
+}
+
+
+
+fn handle_snapshot_requests (
+    mut commands:           Commands,
+    mut meshes:             ResMut<Assets<Mesh>>,
+    mut materials:          ResMut<Assets<StandardMaterial>>,
+    mut requests:           EventReader<SnapshotRequest>,
+    mut state:              ResMut<SnapshotState>,
+    mut sim_params:         ResMut<resources::SimulationParameters>,
+    mut craft_params:       ResMut<spacecraft::SpacecraftParameters>,
+    mut solar_wind:         ResMut<solar_wind::SolarWind>,
+    mut timeline:           ResMut<simulation::timeline::Timeline>,
+    mut spin_state:         ResMut<simulation::spin::SpinState>,
+    esail_query:            Query<(Entity, &spacecraft::esail::ESail)>,
+    verlet_query:           Query<&physics::verlet_object::VerletObject>,
+    mass_query:             Query<&components::Mass>,
+    remote_unit_query:      Query<&spacecraft::remote_unit::RemoteUnit>,
+    ) {
+
+    for request in requests.read() {
+
+        match request {
+
+            SnapshotRequest::Load => {
+
+                match load_snapshot(&state.path) {
+
+                    Ok(snapshot) => {
+
+                        for (esail_entity, esail) in esail_query.iter() {
+                            spacecraft::esail::despawn_esail(&mut commands, esail_entity, esail);
+                        }
+
+                        snapshot.restore(&mut commands, &mut meshes, &mut materials, &mut sim_params, &mut craft_params, &mut solar_wind, &mut spin_state);
+
+                        state.status = match snapshot.restore_timeline(&mut timeline) {
+                            true    => format!("Loaded {} at t = {} s", state.path, snapshot.simulation.simulated_time),
+                            false   => format!("Loaded {} at t = {} s, the timeline is another one and starts over", state.path, snapshot.simulation.simulated_time),
+                        };
+                    },
+
+                    Err(error) => {
+                        state.status = format!("{}: {}", state.path, error);
+                    },
+                }
+            },
+
+            SnapshotRequest::Save => {
+
+                let Ok((_, esail)) = esail_query.get_single() else {
+                    state.status = String::from("No E-sail to save");
+                    continue;
+                };
+
+                let Some(remote_unit) = esail.elements.last().and_then(|entity| remote_unit_query.get(*entity).ok()) else {
+                    state.status = String::from("No remote unit to save");
+                    continue;
+                };
+
+                let snapshot = Snapshot::capture(&sim_params, &craft_params, &solar_wind, esail, &verlet_query, &mass_query, remote_unit, &timeline, &spin_state);
+
+                state.status = match save_snapshot(&state.path, &snapshot) {
+                    Ok(())      => format!("Saved {} at t = {} s", state.path, snapshot.simulation.simulated_time),
+                    Err(error)  => format!("Can't write {}: {}", state.path, error),
+                };
+            },
+        }
+    }
+}
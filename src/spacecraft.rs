@@ -2,16 +2,19 @@ use bevy::prelude::*;
 use uom::si::f64 as quantities;
 use uom::si::*;
 use bevy::math::DVec3;
+use serde::{ Deserialize, Serialize };
 use std::f64::consts;
 
 use crate::{ physics };
-use physics::position_vector::PositionVector;
+use physics::vector3::PositionVector;
 
 pub mod axes;
 pub mod esail;
 //pub mod new_esail;
 pub mod body;
 pub mod center_mass; 
+pub mod reel;
+pub mod remote_unit;
 
 pub struct SpacecraftPlugin;
 
@@ -40,17 +43,71 @@ impl Plugin for SpacecraftPlugin {
     }
 }
 
+/// How the tether is built. A Heytether is a base wire plus loop wires bonded to it every so often,
+/// all in parallel, so that a single micrometeoroid hit doesn't cut it: the elements are the base
+/// wire, and every loop wire between two bonds is a distance constraint a bit longer than the base
+/// wire there, which only pulls once the base wire has broken (see physics/constraint_graph.rs).
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TetherType {
+    SingleWire,
+    Heytether,
+}
+
+impl TetherType {
+
+    pub fn name (&self) -> &'static str {
+        match self {
+            TetherType::SingleWire  => "Single wire",
+            TetherType::Heytether   => "Heytether",
+        }
+    }
+}
+
+/// What sets the spin rate. A free spacecraft keeps its angular momentum, so the spin slows down as
+/// the tether pays out, and the drag and the thrusters of the remote unit change it (see
+/// simulation/spin.rs). A held one stays at rpm, as if something on the body made up for all of it.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpinMode {
+    Free,
+    Held,
+}
+
+impl SpinMode {
+
+    pub const ALL: [SpinMode; 2] = [SpinMode::Free, SpinMode::Held];
+
+    pub fn name (&self) -> &'static str {
+        match self {
+            SpinMode::Free  => "Free",
+            SpinMode::Held  => "Held",
+        }
+    }
+}
+
 #[derive(Resource)]
 pub struct SpacecraftParameters {
-    pub rpm:                quantities::Frequency,  // This could be angular velocity, so I get value and direction together.
+    pub rpm:                quantities::Frequency,  // This could be angular velocity, so I get value and direction together. Changes as the spacecraft spins freely, and goes negative if it spins the other way round.
+    pub spin_mode:          SpinMode,
     pub rotation_axis:      DVec3,
     pub wire_length:        quantities::Length,
     pub wire_radius:        quantities::Length, 
     pub wire_density:       quantities::MassDensity,
     pub wire_potential:     quantities::ElectricPotential,
     pub wire_resolution:    quantities::LinearNumberDensity,
+    pub tether_type:        TetherType,
+    pub filaments:          usize,                  // Wires of a Heytether, the base wire included. All of them have wire_radius.
+    pub bond_spacing:       quantities::Length,     // Between the bonds of a Heytether, rounded to whole segments.
+    pub loop_slack:         f64,                    // How much longer a loop wire is than the base wire between two bonds.
     pub body_size:          quantities::Length, // Will become what, a tuple of lengths?
+    pub body_mass:          quantities::Mass,   // A solid cube of body_size, for its inertia
     pub esail_origin:       PositionVector, 
+    pub links:              Vec<physics::constraint_graph::Link>,   // Constraints beyond the segments of the tether
+    pub remote_unit:        remote_unit::RemoteUnitParameters,      // At the tip of the tether
+    pub reel:               reel::ReelParameters,                   // At the E-sail origin, with the wire not yet deployed
 }
 
 
@@ -58,6 +115,7 @@ impl Default for SpacecraftParameters {
     fn default() -> SpacecraftParameters {
         SpacecraftParameters {
             rpm:                quantities::Frequency::new::<frequency::cycle_per_minute>(0.0),
+            spin_mode:          SpinMode::Free,
             rotation_axis:      DVec3::new(0.0, 0.0, 1.0),  // Is it correct? I think so, right hand rule
             wire_length:        quantities::Length::new::<length::meter>(1.0),
             wire_radius:        quantities::Length::new::<length::micrometer>(10.0),
@@ -65,12 +123,20 @@ impl Default for SpacecraftParameters {
             wire_potential:     quantities::ElectricPotential::new::<electric_potential::kilovolt>(0.0),
             wire_resolution:    quantities::LinearNumberDensity::new::<linear_number_density::per_meter>(20.0),
             //wire_resolution:    quantities::LinearNumberDensity::new::<linear_number_density::per_meter>(100.0),
+            tether_type:        TetherType::SingleWire,
+            filaments:          4,
+            bond_spacing:       quantities::Length::new::<length::meter>(0.1),
+            loop_slack:         0.05,
             body_size:          quantities::Length::new::<length::meter>(0.15),
+            body_mass:          quantities::Mass::new::<mass::kilogram>(2.0),
             esail_origin:       PositionVector::new(
                                     quantities::Length::new::<length::meter>(0.15 / 2.0),
                                     quantities::Length::new::<length::meter>(0.0),
                                     quantities::Length::new::<length::meter>(0.0),
                                     ),
+            links:              Vec::new(),
+            remote_unit:        remote_unit::RemoteUnitParameters{..Default::default()},
+            reel:               reel::ReelParameters{..Default::default()},
         }
     }
 }
@@ -90,13 +156,55 @@ impl SpacecraftParameters {
         return quantities::Length::new::<length::meter>(segment_length);
     }
 
+    /// Every filament of the tether, for a segment. Loop wires are a bit longer than the segment.
     pub fn segment_mass(&self) -> quantities::Mass {
-        let segment_volume = consts::PI * self.wire_radius * self.wire_radius * self.segment_length();
+        let wire_per_segment = 1.0 + self.loop_filaments() as f64 * (1.0 + self.loop_slack);
+        let segment_volume = consts::PI * self.wire_radius * self.wire_radius * self.segment_length() * wire_per_segment;
         return segment_volume * self.wire_density;
     }
 
-    /// Untested
+    /// Loop wires beside the base wire, none for a single wire.
+    pub fn loop_filaments(&self) -> usize {
+        return match self.tether_type {
+            TetherType::SingleWire  => 0,
+            TetherType::Heytether   => self.filaments.saturating_sub(1),
+        };
+    }
+
+    /// Segments between two bonds of the loop wires, at least one.
+    pub fn bond_segments(&self) -> usize {
+        return ((self.bond_spacing * self.wire_resolution).value.round() as usize).max(1);
+    }
+
+    /// Radius of a single wire with the cross-section of all the filaments together, which is what
+    /// the Coulomb drag models get. The filaments are far closer together than the sheath is wide,
+    /// so the solar wind sees one thicker wire.
+    pub fn effective_wire_radius(&self) -> quantities::Length {
+        return self.radius_of_wires(1 + self.loop_filaments());
+    }
+
+    /// Like effective_wire_radius, for a number of the filaments only, the ones left where some broke.
+    pub fn radius_of_wires(&self, wires: usize) -> quantities::Length {
+        return self.wire_radius * (wires as f64).sqrt();
+    }
+
+    /// Of the body about the spin axis, which goes through its center.
+    pub fn body_moment_of_inertia(&self) -> quantities::MomentOfInertia {
+        return self.body_mass * self.body_size * self.body_size / 6.0;
+    }
+
+    /// In radians per second. rpm is a uom frequency, so it's stored in Hz whatever units it was given in.
     pub fn angular_velocity(&self) -> quantities::Frequency { 
-        return self.rpm * consts::PI / 30.0;    // RPM to Radians per second 
+        return self.rpm * 2.0 * consts::PI;     // Cycles per second to radians per second
+    }
+
+    /// Unit vector in the spin plane from the axis towards the E-sail origin, where the tether points
+    /// when nothing bends it. x, or y for an axis along x, if the origin is on the axis.
+    pub fn radial_direction(&self) -> DVec3 {
+        let axis    = self.rotation_axis.normalize();
+        let origin  = self.esail_origin.to_dvec3();
+        return (origin - axis * origin.dot(axis)).try_normalize()
+            .or_else(|| (DVec3::X - axis * axis.x).try_normalize())
+            .unwrap_or(DVec3::Y);
     }
 }
@@ -2,12 +2,9 @@ use bevy::prelude::*;
 
 use uom::si::f64 as quantities;
 use uom::si::*;
-use uom::lib::marker::PhantomData;  // Consts in uom are not very well supported
 
 use crate::{ physics, components };
 
-const ENDMASS_MASS: quantities::Mass = quantities::Mass {dimension: PhantomData, units: PhantomData, value: 0.05};
-
 // I think that what NewEsail did was to have vectors of VerletObject instead of Entities, and that
 // seems to make sense
 
@@ -16,12 +13,24 @@ const ENDMASS_MASS: quantities::Mass = quantities::Mass {dimension: PhantomData,
 
 #[derive(Component, Debug)]
 pub struct ESail {
-    pub origin:                 physics::position_vector::PositionVector, 
+    pub origin:                 physics::vector3::PositionVector, 
     pub elements:               Vec<Entity>,
     pub undeployed_elements:    Vec<Entity>,
     pub deployed_elements:      Vec<Entity>,
     // Testing, not sure about it.
-    pub total_force:            physics::force_vector::ForceVector,
+    pub total_force:            physics::vector3::ForceVector,
+    pub is_cut:                 bool,   // Cut from the spacecraft, the deployed part flies away on its own.
+    pub graph:                  physics::constraint_graph::ConstraintGraph,
+}
+
+/// Mass of the spacecraft, where its center is, and its moment of inertia about the spin axis, which
+/// goes through the center of the body.
+
+#[derive(Debug, Clone)]
+pub struct MassProperties {
+    pub mass:               quantities::Mass,
+    pub center_of_mass:     physics::vector3::PositionVector,
+    pub moment_of_inertia:  quantities::MomentOfInertia,
 }
 
 impl ESail {
@@ -30,7 +39,7 @@ impl ESail {
         &self, 
         index: usize, 
         verlet_query: &Query<&mut physics::verlet_object::VerletObject>
-    ) -> physics::position_vector::PositionVector {
+    ) -> physics::vector3::PositionVector {
 
         let element_position = &verlet_query.get(self.elements[index])
                                             .expect("")
@@ -43,11 +52,11 @@ impl ESail {
                              .expect("Element not found")
                              .current_coordinates;
 
-            return element_position.clone() - preceding_element_position.clone();
+            return *element_position - *preceding_element_position;
 
         } else {
 
-            return physics::position_vector::PositionVector::zero();
+            return physics::vector3::PositionVector::zero();
         }
     }
 
@@ -56,7 +65,7 @@ impl ESail {
         &self,
         index: usize,
         verlet_query: &Query<&mut physics::verlet_object::VerletObject>
-        ) -> f64 {
+        ) -> quantities::Angle {
 
 
         // I need the position of the current element and the TWO previous.
@@ -67,22 +76,30 @@ impl ESail {
         let preceding_element_position =    &verlet_query.get(self.elements[index-1]).expect("No preceding element").current_coordinates;
         let prepreceding_element_position = &verlet_query.get(self.elements[index-2]).expect("No pre-preceding element").current_coordinates;
 
-        let current_to_prev  = current_element_position.clone() - preceding_element_position.clone();
-        let prev_to_prevprev = preceding_element_position.clone() - prepreceding_element_position.clone();
+        let current_to_prev  = *current_element_position - *preceding_element_position;
+        let prev_to_prevprev = *preceding_element_position - *prepreceding_element_position;
 
-        let angle_between = physics::position_vector::angle_between(&current_to_prev, &prev_to_prevprev);
+        let angle_between = current_to_prev.angle_between(&prev_to_prevprev);
 
         return angle_between;
     }
 
-    pub fn deploy_esail ( &mut self, amount: usize ) {
+    /// Pays out up to the given number of elements, and returns how many actually went out.
+    pub fn deploy_esail ( &mut self, amount: usize ) -> usize {
 
-        let count = std::cmp::min(amount, self.undeployed_elements.len() - 1);
+        // Nothing left to push out of the reel
+        if self.is_cut {
+            return 0;
+        }
+
+        let count = std::cmp::min(amount, self.undeployed_elements.len().saturating_sub(1));
 
         for _ in 0..count {
             let entity = self.undeployed_elements.pop().unwrap();
             self.deployed_elements.insert(0, entity);
         }
+
+        return count;
     }
 
     // Unused?
@@ -96,6 +113,44 @@ impl ESail {
         }
     }
 
+    /// Mass properties of the body, the reel with the wire still on it, and the deployed tether as
+    /// long as it hangs from the reel. A cut tether isn't part of the spacecraft anymore.
+    pub fn mass_properties (
+        &self,
+        craft_params:   &super::SpacecraftParameters,
+        reel:           &super::reel::Reel,
+        verlet_query:   &Query<&physics::verlet_object::VerletObject>,
+        mass_query:     &Query<&components::Mass>,
+        ) -> MassProperties {
+
+        let rotation_axis = craft_params.rotation_axis.normalize();
+
+        // The body sits at the origin, and adds nothing to the moment
+        let mut total_mass          = craft_params.body_mass + reel.mass(craft_params);
+        let mut mass_moment         = self.origin * reel.mass(craft_params);
+        let mut moment_of_inertia   = craft_params.body_moment_of_inertia() + reel.moment_of_inertia(craft_params, self.origin.to_dvec3());
+
+        let tether: &[Entity] = if self.is_cut { &[] } else { &self.deployed_elements };
+
+        for entity in tether.iter() {
+
+            let position    = verlet_query.get(*entity).expect("No sail element found").current_coordinates;
+            let mass        = mass_query.get(*entity).expect("Sail element without mass").0;
+
+            let from_axis   = position.to_dvec3() - rotation_axis * position.to_dvec3().dot(rotation_axis);
+
+            total_mass          += mass;
+            mass_moment         += position * mass;
+            moment_of_inertia   += mass * quantities::Area::new::<area::square_meter>(from_axis.length_squared());
+        }
+
+        return MassProperties {
+            mass:               total_mass,
+            center_of_mass:     mass_moment / total_mass,
+            moment_of_inertia:  moment_of_inertia,
+        };
+    }
+
     // Temporary
     pub fn print_elements (&self) {
         println!("Undeployed elements: {:?}", self.undeployed_elements);
@@ -111,6 +166,35 @@ pub fn spawn_esail(
     spacecraft_parameters: Res<super::SpacecraftParameters>,
     ) {
 
+    build_esail(&mut commands, &mut meshes, &mut materials, &spacecraft_parameters);
+}
+
+
+/// Despawns an E-sail and all its elements, for example to build a new one with different parameters.
+
+pub fn despawn_esail (
+    commands:       &mut Commands,
+    esail_entity:   Entity,
+    esail:          &ESail,
+    ) {
+
+    for element in esail.elements.iter() {
+        commands.entity(*element).despawn_recursive();
+    }
+
+    commands.entity(esail_entity).despawn_recursive();
+}
+
+
+/// Spawns an E-sail and its elements, returning the E-sail entity.
+
+pub fn build_esail (
+    commands:               &mut Commands,
+    meshes:                 &mut ResMut<Assets<Mesh>>,
+    materials:              &mut ResMut<Assets<StandardMaterial>>,
+    spacecraft_parameters:  &super::SpacecraftParameters,
+    ) -> Entity {
+
     let mut element_vector: Vec<Entity> = Vec::new();
 
     let mut undeployed_elements:    Vec<Entity> = Vec::new();
@@ -134,14 +218,17 @@ pub fn spawn_esail(
         // Deploy all except the first one
         //let deployment_state = if number == 0 { false } else { true };
 
-        // Don't deploy any (only the endmass)
+        // Don't deploy any (only the remote unit)
         let deployment_state = false;
 
         println!("Element {} spawned, deployment_state: {}", number, deployment_state);
+
+        // The wire still in the reel is the reel's mass
+        let mass = if deployment_state { spacecraft_parameters.segment_mass() } else { quantities::Mass::new::<mass::kilogram>(0.0) };
         
         let element = spawn_esail_element(
-            &mut commands, &mut meshes, &mut materials, 
-            spacecraft_parameters.esail_origin.x(), spacecraft_parameters.segment_mass(), 
+            commands, meshes, materials, 
+            spacecraft_parameters.esail_origin.x(), mass, 
             deployment_state);
         element_vector.push(element);
         
@@ -152,41 +239,138 @@ pub fn spawn_esail(
         }
     }
 
-    // Endmass
-    println!("Plus one endmass");
-    let endmass_element = spawn_endmass(&mut commands, &mut meshes, &mut materials, spacecraft_parameters.esail_origin.x(), ENDMASS_MASS);
-    element_vector.push(endmass_element);
+    // Remote unit
+    println!("Plus one remote unit");
+    let remote_unit = super::remote_unit::RemoteUnit::new(&spacecraft_parameters.remote_unit);
+    let mass        = remote_unit.element_mass(spacecraft_parameters);
+    let remote_unit_element = spawn_remote_unit(commands, meshes, materials, spacecraft_parameters.esail_origin.x(), mass, remote_unit);
+    element_vector.push(remote_unit_element);
     // ??
-    deployed_elements.push(endmass_element);
+    deployed_elements.push(remote_unit_element);
 
     println!("Undeployed elements: {:?}", undeployed_elements);
     println!("Deployed elements: {:?}", deployed_elements);
 
+    let graph   = physics::constraint_graph::ConstraintGraph::new(spacecraft_parameters, element_vector.len(), esail_entity);
+    let reel    = super::reel::Reel::holding(undeployed_elements.len(), spacecraft_parameters);
+
     commands.entity(esail_entity)
         .insert(Name::new("E-sail"))
+        .insert(spacecraft_body())
+        .insert(reel)
         .insert(ESail{ 
-            origin: physics::position_vector::PositionVector::new(
+            origin: physics::vector3::PositionVector::new(
                             spacecraft_parameters.esail_origin.x(),
                             quantities::Length::new::<length::meter>(0.0), 
                             quantities::Length::new::<length::meter>(0.0)),
             elements:   element_vector,     
             undeployed_elements:    undeployed_elements,
             deployed_elements:      deployed_elements,
-            total_force:            physics::force_vector::ForceVector::empty(),
+            total_force:            physics::vector3::ForceVector::zero(),
+            is_cut:                 false,
+            graph:                  graph,
         })
     ;
 
     println!("E-sail spawned");
+
+    return esail_entity;
 }
 
-fn spawn_endmass (
-    commands:   &mut Commands,
-    meshes:     &mut ResMut<Assets<Mesh>>,
-    materials:  &mut ResMut<Assets<StandardMaterial>>,
+/// Spawns an E-sail from saved elements, returning the E-sail entity. The last element is the
+/// remote unit, like in build_esail, and the reel gets the wire of the undeployed ones. The deployed
+/// and undeployed lists are indices into `elements`, and come out pointing at the new entities. The
+/// constraint graph comes from craft_params, with the given constraints broken.
+
+pub fn restore_esail (
+    commands:       &mut Commands,
+    meshes:         &mut ResMut<Assets<Mesh>>,
+    materials:      &mut ResMut<Assets<StandardMaterial>>,
+    craft_params:   &super::SpacecraftParameters,
+    elements:       Vec<(physics::verlet_object::VerletObject, quantities::Mass)>,
+    deployed:       &[usize],
+    undeployed:     &[usize],
+    origin:         physics::vector3::PositionVector,
+    total_force:    physics::vector3::ForceVector,
+    is_cut:         bool,
+    broken:         &[usize],
+    remote_unit:    super::remote_unit::RemoteUnit,
+    ) -> Entity {
+
+    let number_of_elements = elements.len();
+
+    let mut remote_unit = Some(remote_unit);
+
+    let element_vector: Vec<Entity> = elements.into_iter().enumerate().map(|(index, (verlet_object, mass))| {
+
+        let x = verlet_object.current_coordinates.x();
+
+        let element = if index == number_of_elements - 1 {
+            spawn_remote_unit(commands, meshes, materials, x, mass, remote_unit.take().expect("Two remote units"))
+        } else {
+            spawn_esail_element(commands, meshes, materials, x, mass, verlet_object.is_deployed)
+        };
+
+        // Replaces the one the element was spawned with
+        commands.entity(element).insert(verlet_object);
+
+        element
+
+    }).collect();
+
+    let esail_entity = commands.spawn((
+        Name::new("E-sail"),
+        SpatialBundle{
+            visibility: Visibility::Visible,
+            ..Default::default()
+        }
+    )).id();
+
+    let mut graph = physics::constraint_graph::ConstraintGraph::new(craft_params, element_vector.len(), esail_entity);
+
+    // Snapshots are checked before this, but not against the graph
+    for number in broken {
+        if let Some(broken) = graph.broken.get_mut(*number) {
+            *broken = true;
+        }
+    }
+
+    commands.entity(esail_entity)
+        .insert(spacecraft_body())
+        .insert(super::reel::Reel::holding(undeployed.len(), craft_params))
+        .insert(ESail{
+            origin:                 origin,
+            undeployed_elements:    undeployed.iter().map(|index| element_vector[*index]).collect(),
+            deployed_elements:      deployed.iter().map(|index| element_vector[*index]).collect(),
+            elements:               element_vector,
+            total_force:            total_force,
+            is_cut:                 is_cut,
+            graph:                  graph,
+        })
+    ;
+
+    return esail_entity;
+}
+
+/// The E-sail entity stands for the spacecraft body the tether leaves from, for the attachments of
+/// its constraint graph. It sits at the center of the spinning frame, which the body doesn't leave.
+
+fn spacecraft_body () -> components::RigidBody {
+    return components::RigidBody {
+        position:       physics::vector3::PositionVector::zero(),
+        orientation:    bevy::math::DQuat::IDENTITY,
+    };
+}
+
+fn spawn_remote_unit (
+    commands:       &mut Commands,
+    meshes:         &mut ResMut<Assets<Mesh>>,
+    materials:      &mut ResMut<Assets<StandardMaterial>>,
     x: quantities::Length, mass: quantities::Mass,
+    remote_unit:    super::remote_unit::RemoteUnit,
     ) -> Entity {
 
-    let endmass = 
+    let remote_unit_element = 
         commands.spawn ( 
             PbrBundle {
                 mesh: meshes.add(Mesh::from(shape::Cube { size: 15.0 })),
@@ -198,17 +382,21 @@ fn spawn_endmass (
 
     let zero = quantities::Length::new::<length::meter>(0.0);
 
-    commands.entity(endmass)
-        .insert(Name::new("Endmass")) 
+    commands.entity(remote_unit_element)
+        .insert(Name::new("Remote unit")) 
         .insert(components::Mass(mass))
+        .insert(remote_unit)
         .insert(physics::verlet_object::VerletObject { 
-            previous_coordinates:   physics::position_vector::PositionVector::new(x, zero, zero),
-            current_coordinates:    physics::position_vector::PositionVector::new(x, zero, zero),
+            previous_coordinates:   physics::vector3::PositionVector::new(x, zero, zero),
+            current_coordinates:    physics::vector3::PositionVector::new(x, zero, zero),
             is_deployed:            true,
-            current_force:          physics::force_vector::ForceVector::empty(),
+            current_force:          physics::vector3::ForceVector::zero(),
+            coulomb_force:          physics::vector3::ForceVector::zero(),
+            tension:                quantities::Force::new::<force::newton>(0.0),
+            is_slack:               false,
         });
 
-    return endmass;
+    return remote_unit_element;
 }
 
 
@@ -246,10 +434,13 @@ fn spawn_esail_element(
         .insert(Name::new("E-sail element")) 
         .insert(components::Mass(mass))
         .insert(physics::verlet_object::VerletObject { 
-            previous_coordinates:   physics::position_vector::PositionVector::new(x, zero, zero),
-            current_coordinates:    physics::position_vector::PositionVector::new(x, zero, zero),
+            previous_coordinates:   physics::vector3::PositionVector::new(x, zero, zero),
+            current_coordinates:    physics::vector3::PositionVector::new(x, zero, zero),
             is_deployed:            deployment,
-            current_force:          physics::force_vector::ForceVector::empty(),
+            current_force:          physics::vector3::ForceVector::zero(),
+            coulomb_force:          physics::vector3::ForceVector::zero(),
+            tension:                quantities::Force::new::<force::newton>(0.0),
+            is_slack:               false,
         })
         .insert(components::ElectricallyCharged{ ..Default::default() })
         ;
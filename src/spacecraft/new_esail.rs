@@ -4,9 +4,9 @@ use uom::si::*;
 use uom::si::f64 as quantities;
 
 use crate::{ physics };
-use physics::force_vector::ForceVector as ForceVector;
+use physics::vector3::ForceVector as ForceVector;
 use physics::verlet_object::VerletObject as VerletObject;
-use physics::position_vector::PositionVector as PositionVector;
+use physics::vector3::PositionVector as PositionVector;
 
 #[derive(Component)]
 pub struct NewESail {  
@@ -71,7 +71,7 @@ pub fn spawn_new_esail (
             previous_coordinates:   PositionVector::new(x, zero, zero),
             current_coordinates:    PositionVector::new(x, zero, zero),
             is_deployed:            false,
-            current_force:          ForceVector::empty(),
+            current_force:          ForceVector::zero(),
         };
         
         deployed_elements.push(verlet);
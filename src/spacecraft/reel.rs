@@ -0,0 +1,64 @@
+// Reel the tether is wound on, fixed to the spacecraft body at the E-sail origin. The wire still on
+// it is part of the reel, not of the elements waiting to be deployed: those carry no mass until they
+// pay out, and the reel loses a segment of wire for every one that does (and takes it back when the
+// tether is retracted). So the mass of the spacecraft is the same whatever is deployed, and only
+// where it is changes.
+//
+// For its moment of inertia about the spin axis, the reel is a thin ring, wire and all, lying in the
+// spin plane: m r² about its own axis, and m d² more for being d away from the spin axis.
+
+use bevy::prelude::*;
+use bevy::math::DVec3;
+use uom::si::f64 as quantities;
+use uom::si::*;
+
+/// How the reel is built. The wire on it comes from the tether.
+
+#[derive(Debug, Clone)]
+pub struct ReelParameters {
+    pub mass:       quantities::Mass,       // Empty, with its motor
+    pub radius:     quantities::Length,     // Where the wire is wound
+}
+
+impl Default for ReelParameters {
+    fn default() -> Self {
+        ReelParameters {
+            mass:       quantities::Mass::new::<mass::gram>(20.0),
+            radius:     quantities::Length::new::<length::centimeter>(1.0),
+        }
+    }
+}
+
+/// State of the reel, on the E-sail entity, which stands for the spacecraft body.
+
+#[derive(Component, Debug, Clone)]
+pub struct Reel {
+    pub stowed: quantities::Mass,   // Wire still on the reel
+}
+
+impl Reel {
+
+    /// A reel with the wire of the given number of elements on it.
+    pub fn holding (elements: usize, craft_params: &super::SpacecraftParameters) -> Self {
+        return Reel {
+            stowed: craft_params.segment_mass() * elements as f64,
+        };
+    }
+
+    /// The reel with the wire on it.
+    pub fn mass (&self, craft_params: &super::SpacecraftParameters) -> quantities::Mass {
+        return craft_params.reel.mass + self.stowed;
+    }
+
+    /// About the spin axis, with the reel at the given position, in meters.
+    pub fn moment_of_inertia (&self, craft_params: &super::SpacecraftParameters, position: DVec3) -> quantities::MomentOfInertia {
+
+        let rotation_axis   = craft_params.rotation_axis.normalize();
+        let from_axis       = position - rotation_axis * position.dot(rotation_axis);
+        let radius          = craft_params.reel.radius.get::<length::meter>();
+
+        return quantities::MomentOfInertia::new::<moment_of_inertia::kilogram_square_meter>(
+            self.mass(craft_params).get::<mass::kilogram>() * (radius * radius + from_axis.length_squared())
+        );
+    }
+}
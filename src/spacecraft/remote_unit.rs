@@ -0,0 +1,231 @@
+// Remote unit at the tip of the tether, where the endmass used to be: a small spacecraft of its own,
+// with the reel of the auxiliary tether that joins the tips of a full sail, and thrusters.
+//
+// The unit is the last element of the tether, and it's integrated with its real mass: the dry mass,
+// the propellant left, the auxiliary tether still on its reel, and the half segment of wire that
+// every element carries. The constraint solvers weigh their corrections by mass, so the light wire
+// elements do the moving and the unit hardly notices them, and the uniform pull of
+// simulation/graph_solver.rs takes the stretch of the tether out at the unit.
+//
+// The thrusters only push along the spin, tangentially, which is what remote units are for:
+//
+//      spin_up     full thrust forwards, to spin the sail up after deployment
+//      spin_down   full thrust backwards
+//      damping     thrust against the tangential velocity of the unit, gain times mass times
+//                  velocity, to damp the swinging of the tether in the spin plane
+//
+// Thrust is limited to max_thrust, and burns propellant at F / (Isp g₀). Once the propellant is gone
+// the thrusters stop. A single tether has no other tips to join, so the auxiliary tether stays on its
+// reel, as mass.
+
+use bevy::prelude::*;
+use bevy::math::DVec3;
+use serde::{ Deserialize, Serialize };
+use uom::si::f64 as quantities;
+use uom::si::*;
+
+use crate::physics;
+use physics::vector3::ForceVector;
+
+const STANDARD_GRAVITY: f64 = 9.806_65;     // m/s², for the specific impulse
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThrusterType {
+    ColdGas,
+    Ionic,
+}
+
+impl ThrusterType {
+
+    pub const ALL: [ThrusterType; 2] = [ThrusterType::ColdGas, ThrusterType::Ionic];
+
+    pub fn name (&self) -> &'static str {
+        match self {
+            ThrusterType::ColdGas   => "Cold gas",
+            ThrusterType::Ionic     => "Ionic",
+        }
+    }
+
+    /// Typical specific impulse, in seconds: nitrogen cold gas, and a field emission thruster.
+    pub fn specific_impulse (&self) -> f64 {
+        match self {
+            ThrusterType::ColdGas   => 70.0,
+            ThrusterType::Ionic     => 4000.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThrustCommand {
+    Off,
+    SpinUp,
+    SpinDown,
+    Damping,
+}
+
+impl ThrustCommand {
+
+    pub const ALL: [ThrustCommand; 4] = [ThrustCommand::Off, ThrustCommand::SpinUp, ThrustCommand::SpinDown, ThrustCommand::Damping];
+
+    pub fn name (&self) -> &'static str {
+        match self {
+            ThrustCommand::Off      => "Off",
+            ThrustCommand::SpinUp   => "Spin up",
+            ThrustCommand::SpinDown => "Spin down",
+            ThrustCommand::Damping  => "Damping",
+        }
+    }
+}
+
+/// How the remote unit is built, and what its thrusters are told to do.
+
+#[derive(Debug, Clone)]
+pub struct RemoteUnitParameters {
+    pub dry_mass:                   quantities::Mass,
+    pub propellant:                 quantities::Mass,               // At the start of the run
+    pub auxiliary_tether:           quantities::Length,             // On the reel
+    pub auxiliary_tether_density:   quantities::LinearMassDensity,
+    pub thruster:                   ThrusterType,
+    pub max_thrust:                 quantities::Force,
+    pub damping_gain:               quantities::Frequency,          // Per second, of the damping command
+    pub command:                    ThrustCommand,
+}
+
+impl Default for RemoteUnitParameters {
+    fn default() -> Self {
+        RemoteUnitParameters {
+            dry_mass:                   quantities::Mass::new::<mass::gram>(45.0),
+            propellant:                 quantities::Mass::new::<mass::gram>(5.0),
+            auxiliary_tether:           quantities::Length::new::<length::meter>(10.0),
+            auxiliary_tether_density:   quantities::LinearMassDensity::new::<linear_mass_density::kilogram_per_meter>(1.0e-6),
+            thruster:                   ThrusterType::ColdGas,
+            max_thrust:                 quantities::Force::new::<force::millinewton>(1.0),
+            damping_gain:               quantities::Frequency::new::<frequency::hertz>(0.2),
+            command:                    ThrustCommand::Off,
+        }
+    }
+}
+
+impl RemoteUnitParameters {
+
+    /// The unit without its propellant, auxiliary tether included.
+    pub fn empty_mass (&self) -> quantities::Mass {
+        return self.dry_mass + self.auxiliary_tether * self.auxiliary_tether_density;
+    }
+
+    /// The unit as it starts, with all of its propellant.
+    pub fn mass (&self) -> quantities::Mass {
+        return self.empty_mass() + self.propellant;
+    }
+}
+
+/// State of the remote unit, on the last element of the tether. What it's made of is in the
+/// SpacecraftParameters.
+
+#[derive(Component, Debug, Clone)]
+pub struct RemoteUnit {
+    pub propellant: quantities::Mass,   // Left
+    pub thrust:     ForceVector,        // During the last timestep
+}
+
+impl RemoteUnit {
+
+    pub fn new (parameters: &RemoteUnitParameters) -> Self {
+        return RemoteUnit {
+            propellant: parameters.propellant,
+            thrust:     ForceVector::zero(),
+        };
+    }
+
+    /// Mass of the element the unit is: the unit with the propellant it has left, and the half
+    /// segment of tether the element stands for.
+    pub fn element_mass (&self, craft_params: &super::SpacecraftParameters) -> quantities::Mass {
+        return craft_params.remote_unit.empty_mass() + self.propellant + craft_params.segment_mass() * 0.5;
+    }
+
+    /// Fires the thrusters for a timestep, as commanded, given where the unit is and how fast it
+    /// moves in the spinning frame. Keeps the thrust, and burns the propellant it takes.
+    pub fn fire (
+        &mut self,
+        craft_params:   &super::SpacecraftParameters,
+        verlet_object:  &physics::verlet_object::VerletObject,
+        timestep:       quantities::Time,
+        ) {
+
+        let parameters  = &craft_params.remote_unit;
+        let dt          = timestep.get::<time::second>();
+
+        // Direction the unit goes as the sail spins, none on the axis
+        let rotation_axis   = craft_params.rotation_axis.normalize();
+        let position        = verlet_object.current_coordinates.to_dvec3();
+        let from_axis       = position - rotation_axis * position.dot(rotation_axis);
+        let along_spin      = rotation_axis.cross(from_axis).normalize_or_zero();
+
+        let velocity    = (verlet_object.current_coordinates - verlet_object.previous_coordinates).to_dvec3() / dt;
+        let max_thrust  = parameters.max_thrust.get::<force::newton>();
+        let mass        = self.element_mass(craft_params).get::<mass::kilogram>();
+
+        let thrust = match parameters.command {
+            ThrustCommand::Off      => 0.0,
+            ThrustCommand::SpinUp   => max_thrust,
+            ThrustCommand::SpinDown => -max_thrust,
+            ThrustCommand::Damping  => (-parameters.damping_gain.value * mass * velocity.dot(along_spin)).clamp(-max_thrust, max_thrust),
+        };
+
+        // Nothing left to burn, or no direction to push in on the axis
+        let thrust = if self.propellant.value > 0.0 && along_spin != DVec3::ZERO { thrust } else { 0.0 };
+
+        let burnt = thrust.abs() * dt / (parameters.thruster.specific_impulse() * STANDARD_GRAVITY);
+
+        self.propellant = quantities::Mass::new::<mass::kilogram>((self.propellant.get::<mass::kilogram>() - burnt).max(0.0));
+        self.thrust     = ForceVector::from_dvec3(along_spin * thrust);
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    use physics::vector3::PositionVector;
+
+    fn unit_at (position: DVec3, craft_params: &crate::spacecraft::SpacecraftParameters) -> RemoteUnit {
+
+        let position = PositionVector::from_dvec3(position);
+
+        let verlet_object = physics::verlet_object::VerletObject {
+            previous_coordinates:   position,
+            current_coordinates:    position,
+            is_deployed:            true,
+            current_force:          ForceVector::zero(),
+            coulomb_force:          ForceVector::zero(),
+            tension:                quantities::Force::default(),
+            is_slack:               false,
+        };
+
+        let mut unit = RemoteUnit::new(&craft_params.remote_unit);
+        unit.fire(craft_params, &verlet_object, quantities::Time::new::<time::second>(1.0));
+
+        return unit;
+    }
+
+    #[test]
+    fn no_thrust_on_the_axis () {
+
+        let mut craft_params = crate::spacecraft::SpacecraftParameters::default();
+        craft_params.remote_unit.command = ThrustCommand::SpinUp;
+
+        let on_axis = unit_at(DVec3::new(0.0, 0.0, 2.0), &craft_params);
+        assert_eq!(on_axis.thrust.to_dvec3(), DVec3::ZERO);
+        assert_eq!(on_axis.propellant, craft_params.remote_unit.propellant, "Propellant burnt on the axis");
+
+        // Off the axis it pushes along the spin, +y at +x for the default +z axis
+        let off_axis = unit_at(DVec3::new(2.0, 0.0, 0.0), &craft_params);
+        assert_eq!(off_axis.thrust.to_dvec3(), DVec3::Y * craft_params.remote_unit.max_thrust.get::<force::newton>());
+        assert!(off_axis.propellant < craft_params.remote_unit.propellant, "No propellant burnt");
+    }
+}
@@ -0,0 +1,439 @@
+// Spectral analysis of the motion of the tether tip, to see which modes deployment or a voltage
+// modulation excite.
+//
+// The simulation hands the tip position to TipHistory after every timestep, so the samples are
+// evenly spaced in simulated time. Two components are kept, relative to the root of the tether:
+//
+//  - In-plane: in the spin plane, across the radial direction of the E-sail origin (along the spin).
+//  - Out-of-plane: along the spin axis.
+//
+// The spectrum is a periodogram: both signals lose their linear trend, get a Hann window and go
+// through an FFT, zero-padded to a power of two. The highest peaks are the dominant frequencies.
+//
+// Damping ratios come from the half-power bandwidth of the peaks, ζ = Δf / 2f. The oscillations are
+// mostly transients that die out within the history, and the Hann window would hide their start, so
+// the bandwidth is measured on the periodogram without window. That one makes every peak at least
+// about 0.89 / T wide (T being the length of the history), which is taken off in quadrature. Peaks
+// not much wider than that only give an upper bound.
+
+use std::collections::VecDeque;
+use std::io::{ self, BufWriter, Write };
+
+use bevy::prelude::*;
+use uom::si::f64 as quantities;
+use uom::si::*;
+
+use crate::{ physics, resources, spacecraft };
+
+mod fft;
+
+const MAX_PEAKS:        usize   = 5;
+const WINDOW_WIDTH:     f64     = 0.886;    // Half-power width of a peak without window, in bins of 1 / T
+const PEAK_THRESHOLD:   f64     = 1.0e-3;   // Peaks below this fraction of the highest one are left out
+
+pub struct SpectrumPlugin;
+
+impl Plugin for SpectrumPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(TipHistory::default());
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TipMotion {
+    InPlane,
+    OutOfPlane,
+}
+
+impl TipMotion {
+
+    pub const ALL: [TipMotion; 2] = [TipMotion::InPlane, TipMotion::OutOfPlane];
+
+    pub fn name (&self) -> &'static str {
+        match self {
+            TipMotion::InPlane      => "In-plane",
+            TipMotion::OutOfPlane   => "Out-of-plane",
+        }
+    }
+}
+
+struct TipSample {
+    time:       f64,        // Seconds
+    position:   [f64; 2],   // In-plane and out-of-plane, in meters
+}
+
+#[derive(Resource)]
+pub struct TipHistory {
+    pub duration:   quantities::Time,   // How much of the past is kept
+    samples:        VecDeque<TipSample>,
+    timestep:       f64,                // Seconds between samples. Changing it starts the history over.
+}
+
+impl Default for TipHistory {
+    fn default() -> Self {
+        TipHistory {
+            duration:   quantities::Time::new::<time::second>(120.0),
+            samples:    VecDeque::new(),
+            timestep:   0.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Peak {
+    pub frequency:      quantities::Frequency,
+    pub density:        f64,    // Power spectral density at the peak, in m²/Hz
+    pub damping_ratio:  f64,    // An upper bound if the peak isn't resolved
+    pub resolved:       bool,   // Wider than the window makes it
+}
+
+#[derive(Clone, Debug)]
+pub struct TipSpectrum {
+    pub frequencies:    Vec<f64>,       // Hz
+    pub densities:      [Vec<f64>; 2],  // In-plane and out-of-plane power spectral densities, in m²/Hz
+    pub peaks:          [Vec<Peak>; 2], // Highest first
+    pub duration:       quantities::Time,
+    pub samples:        usize,
+}
+
+impl TipSpectrum {
+
+    pub fn density (&self, motion: TipMotion) -> &Vec<f64> {
+        return &self.densities[motion as usize];
+    }
+
+    pub fn peaks (&self, motion: TipMotion) -> &Vec<Peak> {
+        return &self.peaks[motion as usize];
+    }
+
+    /// Frequency resolution: one over the length of the history.
+
+    pub fn resolution (&self) -> quantities::Frequency {
+        return 1.0 / self.duration;
+    }
+}
+
+
+
+impl TipHistory {
+
+    pub fn clear (&mut self) {
+        self.samples.clear();
+    }
+
+    pub fn sample_count (&self) -> usize {
+        return self.samples.len();
+    }
+
+    pub fn span (&self) -> quantities::Time {
+        let span = match (self.samples.front(), self.samples.back()) {
+            (Some(first), Some(last))   => last.time - first.time,
+            _                           => 0.0,
+        };
+        return quantities::Time::new::<time::second>(span);
+    }
+
+    /// Called by the simulation after every timestep.
+
+    pub fn record_step (
+        &mut self,
+        sim_params:     &resources::SimulationParameters,
+        craft_params:   &spacecraft::SpacecraftParameters,
+        esail:          &spacecraft::esail::ESail,
+        verlet_query:   &Query<&mut physics::verlet_object::VerletObject>,
+        ) {
+
+        let Some(tip_entity) = esail.deployed_elements.last() else { return };
+
+        let time = sim_params.simulated_time.get::<time::second>();
+
+        // A restarted run, or a new timestep: the old samples don't fit anymore
+        if self.samples.back().map_or(false, |last| time <= last.time) || sim_params.timestep != self.timestep {
+            self.samples.clear();
+            self.timestep = sim_params.timestep;
+        }
+
+        let tip         = verlet_query.get(*tip_entity).expect("No sail element found").current_coordinates;
+        let relative    = (tip - esail.origin).to_dvec3();
+
+        let spin_axis   = craft_params.rotation_axis.normalize();
+        let across      = spin_axis.cross(craft_params.radial_direction());
+
+        self.samples.push_back(TipSample { time: time, position: [relative.dot(across), relative.dot(spin_axis)] });
+
+        let oldest = time - self.duration.get::<time::second>();
+        while self.samples.front().map_or(false, |first| first.time < oldest) {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Periodogram of both components, or None if there are too few samples.
+
+    pub fn spectrum (&self) -> Option<TipSpectrum> {
+
+        let n = self.samples.len();
+
+        if n < 16 || self.timestep <= 0.0 {
+            return None;
+        }
+
+        let padded          = n.next_power_of_two();
+        let sample_rate     = 1.0 / self.timestep;
+        let bin_width       = sample_rate / padded as f64;
+        let duration        = n as f64 * self.timestep;
+
+        let window: Vec<f64> = (0..n).map(|index| 0.5 - 0.5 * (2.0 * std::f64::consts::PI * index as f64 / (n - 1) as f64).cos()).collect();
+
+        let frequencies: Vec<f64> = (0..=padded / 2).map(|bin| bin as f64 * bin_width).collect();
+
+        let signals = [0, 1].map(|component| detrended(&self.samples.iter().map(|sample| sample.position[component]).collect::<Vec<f64>>()));
+
+        let densities   = [0, 1].map(|component| periodogram(&signals[component], &window, padded, sample_rate));
+        let unwindowed  = [0, 1].map(|component| periodogram(&signals[component], &vec![1.0; n], padded, sample_rate));
+
+        let peaks = [0, 1].map(|component| find_peaks(&frequencies, &densities[component], &unwindowed[component], duration));
+
+        return Some(TipSpectrum {
+            frequencies:    frequencies,
+            densities:      densities,
+            peaks:          peaks,
+            duration:       quantities::Time::new::<time::second>(duration),
+            samples:        n,
+        });
+    }
+
+    /// Writes the history, the spectrum and the peaks as three CSV files: the path itself for the
+    /// spectrum, and the same name ending in _history and _peaks for the others.
+
+    pub fn export (&self, spectrum: &TipSpectrum, path: &str) -> io::Result<Vec<String>> {
+
+        let (stem, extension) = match path.rfind('.') {
+            Some(dot) if !path[dot..].contains('/') => (&path[..dot], &path[dot..]),
+            _                                       => (path, ""),
+        };
+
+        let history_path    = format!("{}_history{}", stem, extension);
+        let peaks_path      = format!("{}_peaks{}", stem, extension);
+
+        let mut file = BufWriter::new(std::fs::File::create(path)?);
+        writeln!(file, "frequency_Hz,in_plane_m2_per_Hz,out_of_plane_m2_per_Hz")?;
+        for (index, frequency) in spectrum.frequencies.iter().enumerate() {
+            writeln!(file, "{},{},{}", frequency, spectrum.densities[0][index], spectrum.densities[1][index])?;
+        }
+        file.flush()?;
+
+        let mut file = BufWriter::new(std::fs::File::create(&history_path)?);
+        writeln!(file, "time_s,in_plane_m,out_of_plane_m")?;
+        for sample in self.samples.iter() {
+            writeln!(file, "{},{},{}", sample.time, sample.position[0], sample.position[1])?;
+        }
+        file.flush()?;
+
+        let mut file = BufWriter::new(std::fs::File::create(&peaks_path)?);
+        writeln!(file, "motion,frequency_Hz,density_m2_per_Hz,damping_ratio,resolved")?;
+        for motion in TipMotion::ALL {
+            for peak in spectrum.peaks(motion) {
+                writeln!(file, "{},{},{},{},{}", motion.name(), peak.frequency.get::<frequency::hertz>(), peak.density, peak.damping_ratio, peak.resolved)?;
+            }
+        }
+        file.flush()?;
+
+        return Ok(vec![path.to_string(), history_path, peaks_path]);
+    }
+}
+
+
+
+/// The signal minus its least squares line, so that a slow drift doesn't swamp the low frequencies.
+
+fn detrended (signal: &[f64]) -> Vec<f64> {
+
+    let n       = signal.len() as f64;
+    let mean_x  = (n - 1.0) / 2.0;
+    let mean_y  = signal.iter().sum::<f64>() / n;
+
+    let (covariance, variance) = signal.iter().enumerate().fold((0.0, 0.0), |(covariance, variance), (index, value)| {
+        let dx = index as f64 - mean_x;
+        (covariance + dx * (value - mean_y), variance + dx * dx)
+    });
+
+    let slope = if variance > 0.0 { covariance / variance } else { 0.0 };
+
+    return signal.iter().enumerate().map(|(index, value)| value - mean_y - slope * (index as f64 - mean_x)).collect();
+}
+
+/// One-sided power spectral density of the signal times the window, zero-padded to the given length.
+
+fn periodogram (signal: &[f64], window: &[f64], padded: usize, sample_rate: f64) -> Vec<f64> {
+
+    let window_power = window.iter().map(|weight| weight * weight).sum::<f64>();
+
+    let mut data: Vec<(f64, f64)> = signal.iter().zip(window).map(|(value, weight)| (value * weight, 0.0)).collect();
+    data.resize(padded, (0.0, 0.0));
+
+    fft::fft(&mut data);
+
+    // Everything but the zero and Nyquist bins counts twice
+    return (0..=padded / 2).map(|bin| {
+        let (re, im)    = data[bin];
+        let sides       = if bin == 0 || bin == padded / 2 { 1.0 } else { 2.0 };
+        sides * (re * re + im * im) / (sample_rate * window_power)
+    }).collect();
+}
+
+/// Highest local maxima of the spectrum, with their damping ratios from the spectrum without window.
+
+fn find_peaks (frequencies: &[f64], densities: &[f64], unwindowed: &[f64], duration: f64) -> Vec<Peak> {
+
+    let bin_width       = frequencies[1] - frequencies[0];
+    let window_width    = WINDOW_WIDTH / duration;
+
+    // What's left of the trend sits in the main lobe around zero
+    let first_bin = ((2.0 / duration / bin_width).ceil() as usize).max(1);
+
+    let highest = densities.iter().skip(first_bin).fold(0.0_f64, |highest, density| highest.max(*density));
+
+    let mut maxima: Vec<usize> = (first_bin..densities.len() - 1)
+        .filter(|&bin| densities[bin] > densities[bin - 1] && densities[bin] >= densities[bin + 1])
+        .filter(|&bin| densities[bin] > PEAK_THRESHOLD * highest)
+        .collect();
+
+    maxima.sort_by(|a, b| densities[*b].total_cmp(&densities[*a]));
+    maxima.truncate(MAX_PEAKS);
+
+    return maxima.iter().map(|&bin| {
+
+        // Parabola through the log of the three bins around the maximum
+        let (left, middle, right) = (densities[bin - 1].ln(), densities[bin].ln(), densities[bin + 1].ln());
+        let curvature   = left - 2.0 * middle + right;
+        let offset      = if curvature < 0.0 && curvature.is_finite() { 0.5 * (left - right) / curvature } else { 0.0 };
+        let frequency   = frequencies[bin] + offset * bin_width;
+
+        // The same peak without window, within a bin or two
+        let top = (bin.saturating_sub(2)..=(bin + 2).min(unwindowed.len() - 1))
+            .fold(bin, |top, other| if unwindowed[other] > unwindowed[top] { other } else { top });
+
+        // Where the density drops to half on both sides, interpolated between bins
+        let half = unwindowed[top] / 2.0;
+
+        let mut low = top;
+        while low > 0 && unwindowed[low] > half { low -= 1; }
+        let low_frequency = crossing(frequencies, unwindowed, low, half);
+
+        let mut high = top;
+        while high < unwindowed.len() - 1 && unwindowed[high] > half { high += 1; }
+        let high_frequency = crossing(frequencies, unwindowed, high.max(1) - 1, half);
+
+        let width       = high_frequency - low_frequency;
+        let resolved    = width > 1.5 * window_width;
+        let own_width   = if resolved { (width * width - window_width * window_width).sqrt() } else { width };
+
+        Peak {
+            frequency:      quantities::Frequency::new::<frequency::hertz>(frequency),
+            density:        densities[bin],
+            damping_ratio:  if frequency > 0.0 { own_width / (2.0 * frequency) } else { 0.0 },
+            resolved:       resolved,
+        }
+    }).collect();
+}
+
+/// Frequency between bin and bin + 1 where the density crosses the level.
+
+fn crossing (frequencies: &[f64], densities: &[f64], bin: usize, level: f64) -> f64 {
+
+    let (before, after) = (densities[bin], densities[bin + 1]);
+
+    let fraction = if after != before { ((level - before) / (after - before)).clamp(0.0, 1.0) } else { 0.5 };
+
+    return frequencies[bin] + fraction * (frequencies[bin + 1] - frequencies[bin]);
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    use std::f64::consts::PI;
+
+    /// History of the given motion, sampled every timestep for the whole duration.
+
+    fn history (timestep: f64, duration: f64, motion: impl Fn(f64) -> [f64; 2]) -> TipHistory {
+        return TipHistory {
+            duration:   quantities::Time::new::<time::second>(duration),
+            samples:    (0..(duration / timestep) as usize).map(|step| {
+                            let time = step as f64 * timestep;
+                            TipSample { time: time, position: motion(time) }
+                        }).collect(),
+            timestep:   timestep,
+        };
+    }
+
+    #[test]
+    fn fft_matches_the_definition () {
+
+        let signal: Vec<(f64, f64)> = (0..64).map(|index| ((index as f64 * 0.7).sin() + 0.1 * index as f64, (index as f64 * 1.3).cos())).collect();
+
+        let mut data = signal.clone();
+        fft::fft(&mut data);
+
+        for (bin, value) in data.iter().enumerate() {
+
+            let expected = signal.iter().enumerate().fold((0.0, 0.0), |(re, im), (index, sample)| {
+                let angle = -2.0 * PI * (bin * index) as f64 / signal.len() as f64;
+                (re + sample.0 * angle.cos() - sample.1 * angle.sin(), im + sample.0 * angle.sin() + sample.1 * angle.cos())
+            });
+
+            assert!((value.0 - expected.0).abs() < 1.0e-9 && (value.1 - expected.1).abs() < 1.0e-9, "Bin {}: {:?} instead of {:?}", bin, value, expected);
+        }
+    }
+
+    #[test]
+    fn detrending_leaves_no_line () {
+
+        let signal: Vec<f64> = (0..100).map(|index| 3.0 - 0.25 * index as f64 + (index as f64).sin()).collect();
+
+        let flat = detrended(&signal);
+
+        // What's left has no mean and no slope
+        let n       = flat.len() as f64;
+        let mean    = flat.iter().sum::<f64>() / n;
+        let slope   = flat.iter().enumerate().map(|(index, value)| (index as f64 - (n - 1.0) / 2.0) * value).sum::<f64>();
+
+        assert!(mean.abs() < 1.0e-12, "Mean {}", mean);
+        assert!(slope.abs() < 1.0e-9, "Slope {}", slope);
+
+        // And a line alone is nothing
+        assert!(detrended(&[1.0, 2.0, 3.0, 4.0]).iter().all(|value| value.abs() < 1.0e-12));
+    }
+
+    #[test]
+    fn damped_oscillation_peaks () {
+
+        // A ringing in-plane mode that dies out within the history, on top of a drift, and a steady
+        // out-of-plane one
+        let (in_plane_frequency, damping_ratio, out_of_plane_frequency) = (0.5, 0.02, 1.3);
+
+        let angular_frequency = 2.0 * PI * in_plane_frequency;
+
+        let history = history(0.05, 200.0, |time| [
+            0.3 * (-damping_ratio * angular_frequency * time).exp() * (angular_frequency * (1.0 - damping_ratio * damping_ratio).sqrt() * time).sin() + 0.01 * time,
+            0.1 * (2.0 * PI * out_of_plane_frequency * time).cos(),
+        ]);
+
+        let spectrum = history.spectrum().expect("No spectrum");
+
+        let bin_width = spectrum.frequencies[1];
+
+        let in_plane = &spectrum.peaks(TipMotion::InPlane)[0];
+        assert!((in_plane.frequency.get::<frequency::hertz>() - in_plane_frequency).abs() < bin_width / 2.0, "In-plane peak at {} Hz", in_plane.frequency.get::<frequency::hertz>());
+        assert!(in_plane.resolved, "In-plane peak not resolved");
+        assert!((in_plane.damping_ratio - damping_ratio).abs() < 0.1 * damping_ratio, "In-plane damping ratio {}", in_plane.damping_ratio);
+
+        let out_of_plane = &spectrum.peaks(TipMotion::OutOfPlane)[0];
+        assert!((out_of_plane.frequency.get::<frequency::hertz>() - out_of_plane_frequency).abs() < bin_width / 2.0, "Out-of-plane peak at {} Hz", out_of_plane.frequency.get::<frequency::hertz>());
+        assert!(!out_of_plane.resolved, "A steady oscillation is only as wide as the window");
+        assert!(out_of_plane.damping_ratio < 1.0 / (spectrum.duration.get::<time::second>() * out_of_plane_frequency), "Out-of-plane damping ratio {}", out_of_plane.damping_ratio);
+    }
+}
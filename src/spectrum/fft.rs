@@ -0,0 +1,57 @@
+// Radix-2 fast Fourier transform, iterative and in place. Complex numbers are (re, im) pairs.
+
+use std::f64::consts::PI;
+
+/// Transforms the data in place. Its length has to be a power of two.
+
+pub fn fft (data: &mut [(f64, f64)]) {
+
+    let n = data.len();
+
+    assert!(n.is_power_of_two(), "FFT length {} is not a power of two", n);
+
+    if n < 2 {
+        return;
+    }
+
+    // Bit-reversed order
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            data.swap(i, j);
+        }
+    }
+
+    // Butterflies, doubling the length every pass
+    let mut length = 2;
+
+    while length <= n {
+
+        let angle   = -2.0 * PI / length as f64;
+        let step    = (angle.cos(), angle.sin());
+
+        for start in (0..n).step_by(length) {
+
+            let mut twiddle = (1.0, 0.0);
+
+            for k in 0..length / 2 {
+
+                let (a, b)  = (data[start + k], data[start + k + length / 2]);
+                let product = (b.0 * twiddle.0 - b.1 * twiddle.1, b.0 * twiddle.1 + b.1 * twiddle.0);
+
+                data[start + k]                 = (a.0 + product.0, a.1 + product.1);
+                data[start + k + length / 2]    = (a.0 - product.0, a.1 - product.1);
+
+                twiddle = (twiddle.0 * step.0 - twiddle.1 * step.1, twiddle.0 * step.1 + twiddle.1 * step.0);
+            }
+        }
+
+        length *= 2;
+    }
+}
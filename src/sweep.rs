@@ -0,0 +1,408 @@
+// Parameter sweeps: runs a scenario many times, headless, with some of its parameters changed, and
+// writes a table with the steady state of every case. A case ends when the convergence monitor says
+// that it has settled (see simulation/convergence.rs, and the [convergence] section of the
+// scenario), or after the given duration if it never does. Run with:
+//      cargo run --release -- --sweep sweeps/potential_rpm.toml
+//
+// A sweep file names a base scenario and the parameters to vary. Every combination of values is a
+// case, and cases run in parallel on every core.
+//
+//      scenario    = "../scenarios/sweep_base.toml"   # From the directory of the sweep file
+//      duration    = "600 s"       # Longest simulated time of a case, the state at the end is the result
+//      output      = "sweep.csv"
+//      threads     = 4             # Optional, all the cores by default
+//
+//      [[parameter]]
+//      key         = "spacecraft.wire_potential"   # Any field of the scenario file
+//      from        = 5
+//      to          = 30
+//      steps       = 6
+//      unit        = "kV"
+//
+//      [[parameter]]
+//      key         = "spacecraft.rpm"
+//      values      = ["0.5 rpm", "1 rpm", "5 rpm"]
+//
+// Keys are written like paths in the scenario file, and reach into [[timeline]] actions by their
+// number, counting from 0: "timeline.0.length".
+
+use std::sync::Mutex;
+use std::sync::atomic::{ AtomicUsize, Ordering };
+
+use bevy::prelude::*;
+use bevy::math::DVec3;
+use serde::Deserialize;
+
+use crate::{ physics, resources, scenario, simulation, spacecraft };
+use scenario::units;
+
+use uom::si::f64 as quantities;
+use uom::si::*;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SweepFile {
+    scenario:   String,
+    duration:   String,
+    output:     String,
+    threads:    Option<usize>,
+    parameter:  Vec<ParameterSection>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ParameterSection {
+    key:        String,
+    values:     Option<Vec<toml::Value>>,
+    from:       Option<f64>,
+    to:         Option<f64>,
+    steps:      Option<usize>,
+    unit:       Option<String>,     // Left out for plain numbers, like iterations
+}
+
+/// Parameter with the values it takes, already written as they go in the scenario file.
+
+struct Parameter {
+    key:        String,
+    values:     Vec<toml::Value>,
+}
+
+/// Steady state of one case.
+
+pub struct CaseResult {
+    pub time:           quantities::Time,
+    pub steady:         bool,               // Settled, instead of running out of time
+    pub thrust:         physics::vector3::ForceVector,
+    pub coning_angle:   quantities::Angle,  // Of the tip, out of the spin plane
+    pub tip_lag:        quantities::Angle,  // Of the tip, behind the radial direction in the spin plane
+    pub max_tension:    quantities::Force,
+}
+
+
+
+/// Runs every case of a sweep file and writes the table. Progress goes to the terminal.
+
+pub fn run_sweep (path: &str) -> Result<(), String> {
+
+    let text = std::fs::read_to_string(path).map_err(|error| format!("Can't read {}: {}", path, error))?;
+
+    let file: SweepFile = toml::from_str(&text).map_err(|error| error.to_string())?;
+
+    let duration: quantities::Time = units::parse(&file.duration, units::TIME).map_err(|error| format!("duration: {}", error))?;
+
+    let scenario_path = std::path::Path::new(path).parent().unwrap_or(std::path::Path::new("")).join(&file.scenario);
+
+    let base_text = std::fs::read_to_string(&scenario_path).map_err(|error| format!("Can't read {}: {}", scenario_path.display(), error))?;
+
+    let base: toml::Table = toml::from_str(&base_text).map_err(|error| format!("{}: {}", scenario_path.display(), error))?;
+
+    let parameters = file.parameter.iter().map(parameter_values).collect::<Result<Vec<_>, _>>()?;
+
+    // Every combination, the last parameter changing fastest
+    let mut cases: Vec<Vec<toml::Value>> = vec![Vec::new()];
+    for parameter in parameters.iter() {
+        cases = cases.iter()
+                     .flat_map(|case| parameter.values.iter().map(move |value| {
+                         let mut case = case.clone();
+                         case.push(value.clone());
+                         case
+                     }))
+                     .collect();
+    }
+
+    // Every case is checked before anything runs, a typo shouldn't show up hours later
+    let scenarios = cases.iter().enumerate().map(|(index, case)| {
+
+        let mut table = base.clone();
+
+        for (parameter, value) in parameters.iter().zip(case) {
+            set_value(&mut table, &parameter.key, value.clone()).map_err(|error| format!("case {}: {}", index, error))?;
+        }
+
+        let case_text = toml::to_string(&table).map_err(|error| error.to_string())?;
+
+        return scenario::parse_scenario(&case_text).map_err(|error| format!("case {} ({}): {}", index, describe_case(&parameters, case), error.message));
+
+    }).collect::<Result<Vec<_>, String>>()?;
+
+    let threads = file.threads.unwrap_or_else(|| std::thread::available_parallelism().map(|threads| threads.get()).unwrap_or(1)).max(1);
+
+    println!("Sweep: {} cases on {} threads, {} s each", scenarios.len(), threads, duration.get::<time::second>());
+
+    let number_of_cases = scenarios.len();
+    let scenarios       = Mutex::new(scenarios.into_iter().map(Some).collect::<Vec<_>>());
+    let results         = Mutex::new((0..number_of_cases).map(|_| None).collect::<Vec<Option<CaseResult>>>());
+    let next_case       = AtomicUsize::new(0);
+
+    std::thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| loop {
+
+                let index = next_case.fetch_add(1, Ordering::SeqCst);
+
+                if index >= number_of_cases {
+                    break;
+                }
+
+                let scenario = scenarios.lock().expect("Sweep poisoned")[index].take().expect("Case run twice");
+
+                let result = run_case(scenario, duration);
+
+                println!("Case {} ({}): thrust {:.4e} N, {} at t = {} s", index, describe_case(&parameters, &cases[index]),
+                         result.thrust.norm().get::<force::newton>(), if result.steady { "settled" } else { "not settled" },
+                         result.time.get::<time::second>());
+
+                results.lock().expect("Sweep poisoned")[index] = Some(result);
+            });
+        }
+    });
+
+    let results: Vec<CaseResult> = results.into_inner().expect("Sweep poisoned").into_iter().map(|result| result.expect("Case not run")).collect();
+
+    write_table(&file.output, &parameters, &cases, &results).map_err(|error| format!("Can't write {}: {}", file.output, error))?;
+
+    println!("Results written to {}", file.output);
+
+    return Ok(());
+}
+
+
+
+/// Runs one case until it settles, or for the given simulated time at most.
+
+pub fn run_case (scenario: scenario::Scenario, duration: quantities::Time) -> CaseResult {
+
+    let mut app = simulation::headless_app(scenario);
+
+    while app.world.resource::<resources::SimulationParameters>().simulated_time < duration
+       && !app.world.resource::<simulation::convergence::ConvergenceMonitor>().is_steady() {
+        app.update();
+    }
+
+    return case_result(&mut app.world);
+}
+
+/// Reads the results from the state of the world.
+
+fn case_result (world: &mut World) -> CaseResult {
+
+    let time            = world.resource::<resources::SimulationParameters>().simulated_time;
+    let steady          = world.resource::<simulation::convergence::ConvergenceMonitor>().is_steady();
+    let rotation_axis   = world.resource::<spacecraft::SpacecraftParameters>().rotation_axis.normalize();
+    let radial          = world.resource::<spacecraft::SpacecraftParameters>().radial_direction();
+
+    let esail = world.query::<&spacecraft::esail::ESail>().single(world);
+
+    let deployed: Vec<&physics::verlet_object::VerletObject> = esail.deployed_elements.iter()
+        .map(|entity| world.get::<physics::verlet_object::VerletObject>(*entity).expect("No sail element found"))
+        .collect();
+
+    let max_tension = deployed.iter().map(|element| element.tension).fold(quantities::Force::default(), |max, tension| max.max(tension));
+
+    let tip = deployed.last().map_or(DVec3::ZERO, |element| (element.current_coordinates - esail.origin).to_dvec3());
+
+    // The spin is around the rotation axis, so "behind" is -y for the default +z axis and radial x
+    let out_of_plane    = tip.dot(rotation_axis);
+    let in_plane        = tip - rotation_axis * out_of_plane;
+    let behind          = -rotation_axis.cross(radial);
+
+    let coning_angle    = if tip.length() > 0.0 { (out_of_plane / tip.length()).asin() } else { 0.0 };
+    let tip_lag         = in_plane.dot(behind).atan2(in_plane.dot(radial));
+
+    return CaseResult {
+        time:           time,
+        steady:         steady,
+        thrust:         esail.total_force,
+        coning_angle:   quantities::Angle::new::<angle::radian>(coning_angle),
+        tip_lag:        quantities::Angle::new::<angle::radian>(tip_lag),
+        max_tension:    max_tension,
+    };
+}
+
+
+
+fn parameter_values (section: &ParameterSection) -> Result<Parameter, String> {
+
+    let values = match (&section.values, section.from, section.to, section.steps) {
+
+        (Some(values), None, None, None) => values.clone(),
+
+        (None, Some(from), Some(to), Some(steps)) if steps >= 1 => (0..steps).map(|step| {
+
+            let value = if steps == 1 { from } else { from + (to - from) * step as f64 / (steps - 1) as f64 };
+
+            match &section.unit {
+                Some(unit)                          => toml::Value::String(format!("{} {}", value, unit)),
+                None if value.fract() == 0.0        => toml::Value::Integer(value as i64),
+                None                                => toml::Value::Float(value),
+            }
+        }).collect(),
+
+        _ => return Err(format!("parameter {}: needs either values, or from, to and steps (at least 1)", section.key)),
+    };
+
+    if values.is_empty() {
+        return Err(format!("parameter {}: no values", section.key));
+    }
+
+    return Ok(Parameter { key: section.key.clone(), values: values });
+}
+
+/// Sets a field of the scenario file, by its path like "spacecraft.rpm" or "timeline.0.length".
+/// Sections that aren't in the base scenario are created.
+
+fn set_value (table: &mut toml::Table, key: &str, value: toml::Value) -> Result<(), String> {
+
+    let parts: Vec<&str> = key.split('.').collect();
+
+    let (field, path) = parts.split_last().expect("Splitting always gives at least one part");
+
+    let mut current = table;
+    let mut depth   = 0;
+
+    while depth < path.len() {
+
+        let part = path[depth];
+
+        current = match current.entry(part.to_string()).or_insert_with(|| toml::Value::Table(toml::Table::new())) {
+
+            toml::Value::Table(table) => table,
+
+            // Arrays of tables, like [[timeline]], take the number that follows
+            toml::Value::Array(array) => {
+                depth += 1;
+                let element = path.get(depth).and_then(|number| number.parse::<usize>().ok()).and_then(|number| array.get_mut(number));
+                match element {
+                    Some(toml::Value::Table(table)) => table,
+                    _ => return Err(format!("{}: {} has no entry {}", key, part, path.get(depth).unwrap_or(&""))),
+                }
+            },
+
+            _ => return Err(format!("{}: {} isn't a section", key, part)),
+        };
+
+        depth += 1;
+    }
+
+    current.insert(field.to_string(), value);
+
+    return Ok(());
+}
+
+/// Like "spacecraft.rpm = 1 rpm, spacecraft.wire_potential = 10 kV"
+
+fn describe_case (parameters: &[Parameter], case: &[toml::Value]) -> String {
+    return parameters.iter()
+                     .zip(case)
+                     .map(|(parameter, value)| format!("{} = {}", parameter.key, plain_value(value)))
+                     .collect::<Vec<_>>()
+                     .join(", ");
+}
+
+/// Strings without their quotes.
+
+fn plain_value (value: &toml::Value) -> String {
+    return match value {
+        toml::Value::String(text)   => text.clone(),
+        other                       => other.to_string(),
+    };
+}
+
+fn write_table (
+    path:       &str,
+    parameters: &[Parameter],
+    cases:      &[Vec<toml::Value>],
+    results:    &[CaseResult],
+    ) -> std::io::Result<()> {
+
+    let mut text = String::from("case,");
+
+    for parameter in parameters.iter() {
+        text.push_str(&format!("{},", parameter.key));
+    }
+
+    text.push_str("time_s,steady,thrust_N,thrust_x_N,thrust_y_N,thrust_z_N,coning_angle_deg,tip_lag_deg,max_tension_N\n");
+
+    for (index, (case, result)) in cases.iter().zip(results).enumerate() {
+
+        let thrust = result.thrust.to_dvec3();
+
+        text.push_str(&format!("{},", index));
+
+        for value in case.iter() {
+            text.push_str(&format!("{},", plain_value(value)));
+        }
+
+        text.push_str(&format!("{},{},{},{},{},{},{},{},{}\n",
+            result.time.get::<time::second>(),
+            result.steady,
+            thrust.length(), thrust.x, thrust.y, thrust.z,
+            result.coning_angle.get::<angle::degree>(),
+            result.tip_lag.get::<angle::degree>(),
+            result.max_tension.get::<force::newton>(),
+        ));
+    }
+
+    return std::fs::write(path, text);
+}
+
+
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn section (key: &str) -> ParameterSection {
+        return ParameterSection { key: key.to_string(), values: None, from: None, to: None, steps: None, unit: None };
+    }
+
+    #[test]
+    fn ranges_become_values () {
+
+        let rpm = parameter_values(&ParameterSection { from: Some(1.0), to: Some(2.0), steps: Some(3), unit: Some("rpm".to_string()), ..section("spacecraft.rpm") }).unwrap();
+        assert_eq!(rpm.values, vec![toml::Value::from("1 rpm"), toml::Value::from("1.5 rpm"), toml::Value::from("2 rpm")]);
+
+        // A single step is the start of the range, and plain whole numbers stay integers
+        let iterations = parameter_values(&ParameterSection { from: Some(10.0), to: Some(20.0), steps: Some(1), ..section("simulation.iterations") }).unwrap();
+        assert_eq!(iterations.values, vec![toml::Value::Integer(10)]);
+
+        let listed = parameter_values(&ParameterSection { values: Some(vec![toml::Value::from("5 kV")]), ..section("spacecraft.wire_potential") }).unwrap();
+        assert_eq!(listed.values, vec![toml::Value::from("5 kV")]);
+
+        assert!(parameter_values(&ParameterSection { from: Some(1.0), to: Some(2.0), steps: Some(0), ..section("spacecraft.rpm") }).is_err(), "No steps");
+        assert!(parameter_values(&ParameterSection { from: Some(1.0), steps: Some(2), ..section("spacecraft.rpm") }).is_err(), "No end of the range");
+        assert!(parameter_values(&ParameterSection { values: Some(Vec::new()), ..section("spacecraft.rpm") }).is_err(), "No values");
+        assert!(parameter_values(&ParameterSection { values: Some(vec![toml::Value::from("1 rpm")]), from: Some(1.0), ..section("spacecraft.rpm") }).is_err(), "Values and a range");
+    }
+
+    #[test]
+    fn values_go_where_their_key_says () {
+
+        let mut table: toml::Table = toml::from_str("
+            [spacecraft]
+            rpm = \"1 rpm\"
+
+            [[timeline]]
+            action = \"deploy\"
+            length = \"10 m\"
+
+            [[timeline]]
+            action = \"wait\"
+        ").unwrap();
+
+        set_value(&mut table, "spacecraft.rpm", toml::Value::from("2 rpm")).unwrap();
+        set_value(&mut table, "timeline.0.length", toml::Value::from("20 m")).unwrap();
+        set_value(&mut table, "simulation.iterations", toml::Value::Integer(50)).unwrap();
+
+        assert_eq!(table["spacecraft"]["rpm"].as_str(), Some("2 rpm"));
+        assert_eq!(table["timeline"][0]["length"].as_str(), Some("20 m"));
+        assert_eq!(table["timeline"][0]["action"].as_str(), Some("deploy"), "The rest of the action is kept");
+        assert!(table["timeline"][1].get("length").is_none(), "Only the action with the number changes");
+        assert_eq!(table["simulation"]["iterations"].as_integer(), Some(50), "Missing sections are created");
+
+        for key in ["timeline.2.length", "timeline.length", "timeline.x.length", "spacecraft.rpm.value"] {
+            assert!(set_value(&mut table, key, toml::Value::Integer(1)).is_err(), "{} set", key);
+        }
+    }
+}